@@ -132,6 +132,7 @@ pub struct Other {{
     pub oid: Oid,
     pub kind: Kind,
     pub schema: String,
+    pub binary_io: bool,
 }}
 "
     ).unwrap();
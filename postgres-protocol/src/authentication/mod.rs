@@ -1,5 +1,6 @@
 //! Authentication protocol support.
 use md5::Context;
+use std::io;
 
 pub mod sasl;
 
@@ -20,6 +21,27 @@ pub fn md5_hash(username: &[u8], password: &[u8], salt: [u8; 4]) -> String {
     format!("md5{:x}", context.compute())
 }
 
+/// Like `md5_hash`, but takes the hash of the password and username in the
+/// `md5<hex digest>` form stored by the server rather than the password
+/// itself.
+///
+/// An error is returned if the hash is not in that form.
+#[inline]
+pub fn md5_hash_prehashed(hash: &str, salt: [u8; 4]) -> io::Result<String> {
+    let digest = match hash.get(..3) {
+        Some("md5") => &hash[3..],
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid md5 password hash")),
+    };
+    if digest.len() != 32 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid md5 password hash"));
+    }
+
+    let mut context = Context::new();
+    context.consume(digest.to_ascii_lowercase());
+    context.consume(&salt);
+    Ok(format!("md5{:x}", context.compute()))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -35,4 +57,17 @@ mod test {
             "md562af4dd09bbb41884907a838a3233294"
         );
     }
+
+    #[test]
+    fn md5_prehashed() {
+        let salt = [0x2a, 0x3d, 0x8f, 0xe0];
+
+        assert_eq!(
+            md5_hash_prehashed("md57cd2b9a92689eab2e58caf2da80afa15", salt).unwrap(),
+            "md562af4dd09bbb41884907a838a3233294"
+        );
+        assert!(md5_hash_prehashed("password", salt).is_err());
+        assert!(md5_hash_prehashed("md57cd2b9a92689eab2e58caf2da80afa1", salt).is_err());
+        assert!(md5_hash_prehashed("md57cd2b9a92689eab2e58caf2da80afaxx", salt).is_err());
+    }
 }
@@ -28,6 +28,7 @@ pub enum Message {
     BindComplete,
     CloseComplete,
     CommandComplete(CommandCompleteBody),
+    CopyBothResponse(CopyBothResponseBody),
     CopyData(CopyDataBody),
     CopyDone,
     CopyInResponse(CopyInResponseBody),
@@ -35,6 +36,7 @@ pub enum Message {
     DataRow(DataRowBody),
     EmptyQueryResponse,
     ErrorResponse(ErrorResponseBody),
+    NegotiateProtocolVersion(NegotiateProtocolVersionBody),
     NoData,
     NoticeResponse(NoticeResponseBody),
     NotificationResponse(NotificationResponseBody),
@@ -51,6 +53,13 @@ pub enum Message {
 impl Message {
     #[inline]
     pub fn parse(buf: &mut BytesMut) -> io::Result<Option<Message>> {
+        Message::parse_with_max_len(buf, u32::max_value() as usize)
+    }
+
+    /// Like `parse`, but returns an error rather than reserving space for a
+    /// message if its length prefix exceeds `max_len` bytes.
+    #[inline]
+    pub fn parse_with_max_len(buf: &mut BytesMut, max_len: usize) -> io::Result<Option<Message>> {
         if buf.len() < 5 {
             let to_read = 5 - buf.len();
             buf.reserve(to_read);
@@ -67,6 +76,13 @@ impl Message {
             ));
         }
 
+        if len as usize > max_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("message length {} exceeds the maximum of {}", len, max_len),
+            ));
+        }
+
         let total_len = len as usize + 1;
         if buf.len() < total_len {
             let to_read = total_len - buf.len();
@@ -212,6 +228,26 @@ impl Message {
                     len: len,
                 })
             }
+            b'v' => {
+                let newest_minor_version = buf.read_i32::<BigEndian>()?;
+                let len = buf.read_u32::<BigEndian>()?;
+                let storage = buf.read_all();
+                Message::NegotiateProtocolVersion(NegotiateProtocolVersionBody {
+                    newest_minor_version: newest_minor_version,
+                    storage: storage,
+                    len: len,
+                })
+            }
+            b'W' => {
+                let format = buf.read_u8()?;
+                let len = buf.read_u16::<BigEndian>()?;
+                let storage = buf.read_all();
+                Message::CopyBothResponse(CopyBothResponseBody {
+                    format: format,
+                    len: len,
+                    storage: storage,
+                })
+            }
             b'Z' => {
                 let status = buf.read_u8()?;
                 Message::ReadyForQuery(ReadyForQueryBody { status: status })
@@ -471,6 +507,27 @@ impl CopyOutResponseBody {
     }
 }
 
+pub struct CopyBothResponseBody {
+    storage: Bytes,
+    len: u16,
+    format: u8,
+}
+
+impl CopyBothResponseBody {
+    #[inline]
+    pub fn format(&self) -> u8 {
+        self.format
+    }
+
+    #[inline]
+    pub fn column_formats<'a>(&'a self) -> ColumnFormats<'a> {
+        ColumnFormats {
+            remaining: self.len,
+            buf: &self.storage,
+        }
+    }
+}
+
 pub struct DataRowBody {
     storage: Bytes,
     len: u16,
@@ -601,6 +658,63 @@ impl<'a> ErrorField<'a> {
     }
 }
 
+pub struct NegotiateProtocolVersionBody {
+    newest_minor_version: i32,
+    storage: Bytes,
+    len: u32,
+}
+
+impl NegotiateProtocolVersionBody {
+    #[inline]
+    pub fn newest_minor_version(&self) -> i32 {
+        self.newest_minor_version
+    }
+
+    #[inline]
+    pub fn options<'a>(&'a self) -> ProtocolOptions<'a> {
+        ProtocolOptions {
+            buf: &self.storage,
+            remaining: self.len,
+        }
+    }
+}
+
+pub struct ProtocolOptions<'a> {
+    buf: &'a [u8],
+    remaining: u32,
+}
+
+impl<'a> FallibleIterator for ProtocolOptions<'a> {
+    type Item = &'a str;
+    type Error = io::Error;
+
+    #[inline]
+    fn next(&mut self) -> io::Result<Option<&'a str>> {
+        if self.remaining == 0 {
+            if self.buf.is_empty() {
+                return Ok(None);
+            } else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid message length",
+                ));
+            }
+        }
+
+        self.remaining -= 1;
+        let end = find_null(self.buf, 0)?;
+        let option = get_str(&self.buf[..end])?;
+        self.buf = &self.buf[end + 1..];
+        Ok(Some(option))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining as usize;
+        (len, Some(len))
+    }
+}
+
 pub struct NoticeResponseBody {
     storage: Bytes,
 }
@@ -300,13 +300,31 @@ pub fn ssl_request(buf: &mut Vec<u8>) {
     write_body(buf, |buf| buf.write_i32::<BigEndian>(80877103)).unwrap();
 }
 
+/// The protocol version sent in the startup message by default, 3.0.
+pub const PROTOCOL_VERSION: i32 = 196608;
+
 #[inline]
 pub fn startup_message<'a, I>(parameters: I, buf: &mut Vec<u8>) -> io::Result<()>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    startup_message_with_version(PROTOCOL_VERSION, parameters, buf)
+}
+
+/// Like `startup_message`, but requests a specific protocol version, encoded
+/// as the major version in the high 16 bits and the minor version in the low
+/// 16 bits.
+#[inline]
+pub fn startup_message_with_version<'a, I>(
+    version: i32,
+    parameters: I,
+    buf: &mut Vec<u8>,
+) -> io::Result<()>
 where
     I: IntoIterator<Item = (&'a str, &'a str)>,
 {
     write_body(buf, |buf| {
-        buf.write_i32::<BigEndian>(196608).unwrap();
+        buf.write_i32::<BigEndian>(version).unwrap();
         for (key, value) in parameters {
             buf.write_cstr(key.as_ref())?;
             buf.write_cstr(value.as_ref())?;
@@ -0,0 +1,151 @@
+//! Streaming replication messages.
+//!
+//! These messages are carried in `CopyData` messages once `START_REPLICATION`
+//! has switched the connection into CopyBoth mode. See [Postgres's
+//! documentation][docs] for more information.
+//!
+//! [docs]: https://www.postgresql.org/docs/current/static/protocol-replication.html
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
+use std::io;
+
+/// A message sent by the server during streaming replication.
+pub enum ReplicationMessage<'a> {
+    /// A chunk of the WAL stream.
+    XLogData(XLogDataBody<'a>),
+    /// A keepalive message, possibly requesting a status update.
+    PrimaryKeepalive(PrimaryKeepaliveBody),
+    #[doc(hidden)]
+    __ForExtensibility,
+}
+
+impl<'a> ReplicationMessage<'a> {
+    /// Parses a replication message from the contents of a `CopyData` message.
+    #[inline]
+    pub fn parse(mut buf: &'a [u8]) -> io::Result<ReplicationMessage<'a>> {
+        let tag = buf.read_u8()?;
+        let message = match tag {
+            b'w' => {
+                let wal_start = buf.read_u64::<BigEndian>()?;
+                let wal_end = buf.read_u64::<BigEndian>()?;
+                let timestamp = buf.read_i64::<BigEndian>()?;
+                ReplicationMessage::XLogData(XLogDataBody {
+                    wal_start: wal_start,
+                    wal_end: wal_end,
+                    timestamp: timestamp,
+                    data: buf,
+                })
+            }
+            b'k' => {
+                let wal_end = buf.read_u64::<BigEndian>()?;
+                let timestamp = buf.read_i64::<BigEndian>()?;
+                let reply_requested = buf.read_u8()? == 1;
+                if !buf.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "invalid message length",
+                    ));
+                }
+                ReplicationMessage::PrimaryKeepalive(PrimaryKeepaliveBody {
+                    wal_end: wal_end,
+                    timestamp: timestamp,
+                    reply_requested: reply_requested,
+                })
+            }
+            tag => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown replication message tag `{}`", tag),
+                ));
+            }
+        };
+
+        Ok(message)
+    }
+}
+
+/// The body of an `XLogData` message.
+pub struct XLogDataBody<'a> {
+    wal_start: u64,
+    wal_end: u64,
+    timestamp: i64,
+    data: &'a [u8],
+}
+
+impl<'a> XLogDataBody<'a> {
+    /// Returns the WAL position of the start of the data.
+    #[inline]
+    pub fn wal_start(&self) -> u64 {
+        self.wal_start
+    }
+
+    /// Returns the current end of WAL on the server.
+    #[inline]
+    pub fn wal_end(&self) -> u64 {
+        self.wal_end
+    }
+
+    /// Returns the server's clock at the time of transmission, as
+    /// microseconds since midnight, January 1st, 2000.
+    #[inline]
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Returns the data itself.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// The body of a `PrimaryKeepalive` message.
+pub struct PrimaryKeepaliveBody {
+    wal_end: u64,
+    timestamp: i64,
+    reply_requested: bool,
+}
+
+impl PrimaryKeepaliveBody {
+    /// Returns the current end of WAL on the server.
+    #[inline]
+    pub fn wal_end(&self) -> u64 {
+        self.wal_end
+    }
+
+    /// Returns the server's clock at the time of transmission, as
+    /// microseconds since midnight, January 1st, 2000.
+    #[inline]
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Determines if the server has requested an immediate status update to
+    /// avoid a timeout disconnect.
+    #[inline]
+    pub fn reply_requested(&self) -> bool {
+        self.reply_requested
+    }
+}
+
+/// Serializes a standby status update, to be sent in a `CopyData` message.
+///
+/// The positions are those of the last WAL byte + 1 written to disk, flushed
+/// to disk and applied on the standby, and the timestamp the client's clock
+/// as microseconds since midnight, January 1st, 2000.
+#[inline]
+pub fn standby_status_update(
+    write: u64,
+    flush: u64,
+    apply: u64,
+    timestamp: i64,
+    reply_requested: bool,
+    buf: &mut Vec<u8>,
+) {
+    buf.push(b'r');
+    buf.write_u64::<BigEndian>(write).unwrap();
+    buf.write_u64::<BigEndian>(flush).unwrap();
+    buf.write_u64::<BigEndian>(apply).unwrap();
+    buf.write_i64::<BigEndian>(timestamp).unwrap();
+    buf.push(reply_requested as u8);
+}
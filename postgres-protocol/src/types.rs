@@ -1,7 +1,10 @@
 //! Conversions to and from Postgres's binary format for various types.
 use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
 use fallible_iterator::FallibleIterator;
+use memchr::memchr;
 use std::error::Error;
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str;
 use std::boxed::Box as StdBox;
 
@@ -13,6 +16,9 @@ const RANGE_UPPER_INCLUSIVE: u8 = 0b0000_0100;
 const RANGE_LOWER_INCLUSIVE: u8 = 0b0000_0010;
 const RANGE_EMPTY: u8 = 0b0000_0001;
 
+const PGSQL_AF_INET: u8 = 2;
+const PGSQL_AF_INET6: u8 = 3;
+
 /// Serializes a `BOOL` value.
 #[inline]
 pub fn bool_to_sql(v: bool, buf: &mut Vec<u8>) {
@@ -41,6 +47,64 @@ pub fn bytea_from_sql(buf: &[u8]) -> &[u8] {
     buf
 }
 
+/// Deserializes the text representation of a `BYTEA` value.
+///
+/// Unlike the other functions in this module, this operates on the text
+/// format, as returned by the simple query protocol or a cast to `TEXT`. Both
+/// the `hex` (e.g. `\x00ff`) and `escape` (e.g. `\000\377`) values of the
+/// server's `bytea_output` setting are supported.
+pub fn bytea_from_text(s: &str) -> Result<Vec<u8>, StdBox<Error + Sync + Send>> {
+    let s = s.as_bytes();
+
+    if s.starts_with(b"\\x") {
+        let mut buf = Vec::with_capacity((s.len() - 2) / 2);
+        let mut digits = s[2..].iter().filter(|b| !(**b as char).is_whitespace());
+        while let Some(&hi) = digits.next() {
+            let lo = match digits.next() {
+                Some(&lo) => lo,
+                None => return Err("invalid hex BYTEA value".into()),
+            };
+            buf.push((hex_digit(hi)? << 4) | hex_digit(lo)?);
+        }
+        return Ok(buf);
+    }
+
+    let mut buf = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] != b'\\' {
+            buf.push(s[i]);
+            i += 1;
+        } else if s.get(i + 1) == Some(&b'\\') {
+            buf.push(b'\\');
+            i += 2;
+        } else {
+            match s.get(i + 1..i + 4).and_then(octal_byte) {
+                Some(b) => {
+                    buf.push(b);
+                    i += 4;
+                }
+                None => return Err("invalid escaped BYTEA value".into()),
+            }
+        }
+    }
+    Ok(buf)
+}
+
+fn octal_byte(s: &[u8]) -> Option<u8> {
+    if s[0] > b'3' || s.iter().any(|b| *b < b'0' || *b > b'7') {
+        return None;
+    }
+    Some(s.iter().fold(0, |v, b| (v << 3) | (b - b'0')))
+}
+
+fn hex_digit(b: u8) -> Result<u8, StdBox<Error + Sync + Send>> {
+    match (b as char).to_digit(16) {
+        Some(d) => Ok(d as u8),
+        None => Err("invalid hex BYTEA value".into()),
+    }
+}
+
 /// Serializes a `TEXT`, `VARCHAR`, `CHAR(n)`, `NAME`, or `CITEXT` value.
 #[inline]
 pub fn text_to_sql(v: &str, buf: &mut Vec<u8>) {
@@ -366,7 +430,7 @@ pub fn date_from_sql(mut buf: &[u8]) -> Result<i32, StdBox<Error + Sync + Send>>
     Ok(v)
 }
 
-/// Serializes a `TIME` or `TIMETZ` value.
+/// Serializes a `TIME` value.
 ///
 /// The value should represent the number of microseconds since midnight.
 #[inline]
@@ -374,7 +438,7 @@ pub fn time_to_sql(v: i64, buf: &mut Vec<u8>) {
     buf.write_i64::<BigEndian>(v).unwrap();
 }
 
-/// Deserializes a `TIME` or `TIMETZ` value.
+/// Deserializes a `TIME` value.
 ///
 /// The value represents the number of microseconds since midnight.
 #[inline]
@@ -386,6 +450,175 @@ pub fn time_from_sql(mut buf: &[u8]) -> Result<i64, StdBox<Error + Sync + Send>>
     Ok(v)
 }
 
+/// Serializes a `TIMETZ` value.
+///
+/// The time should represent the number of microseconds since midnight, and
+/// the zone the offset from UTC in seconds, positive west of Greenwich.
+#[inline]
+pub fn timetz_to_sql(time: i64, zone: i32, buf: &mut Vec<u8>) {
+    buf.write_i64::<BigEndian>(time).unwrap();
+    buf.write_i32::<BigEndian>(zone).unwrap();
+}
+
+/// Deserializes a `TIMETZ` value.
+///
+/// The time represents the number of microseconds since midnight, and the
+/// zone the offset from UTC in seconds, positive west of Greenwich.
+#[inline]
+pub fn timetz_from_sql(mut buf: &[u8]) -> Result<(i64, i32), StdBox<Error + Sync + Send>> {
+    let time = buf.read_i64::<BigEndian>()?;
+    let zone = buf.read_i32::<BigEndian>()?;
+    if !buf.is_empty() {
+        return Err("invalid message length".into());
+    }
+    Ok((time, zone))
+}
+
+/// Serializes an `INTERVAL` value.
+#[inline]
+pub fn interval_to_sql(microseconds: i64, days: i32, months: i32, buf: &mut Vec<u8>) {
+    buf.write_i64::<BigEndian>(microseconds).unwrap();
+    buf.write_i32::<BigEndian>(days).unwrap();
+    buf.write_i32::<BigEndian>(months).unwrap();
+}
+
+/// Deserializes an `INTERVAL` value.
+#[inline]
+pub fn interval_from_sql(mut buf: &[u8]) -> Result<Interval, StdBox<Error + Sync + Send>> {
+    let microseconds = buf.read_i64::<BigEndian>()?;
+    let days = buf.read_i32::<BigEndian>()?;
+    let months = buf.read_i32::<BigEndian>()?;
+    if !buf.is_empty() {
+        return Err("invalid message length".into());
+    }
+    Ok(Interval {
+        microseconds: microseconds,
+        days: days,
+        months: months,
+    })
+}
+
+/// A Postgres interval.
+#[derive(Copy, Clone)]
+pub struct Interval {
+    microseconds: i64,
+    days: i32,
+    months: i32,
+}
+
+impl Interval {
+    /// Returns the number of microseconds in the interval, in addition to its
+    /// days and months.
+    #[inline]
+    pub fn microseconds(&self) -> i64 {
+        self.microseconds
+    }
+
+    /// Returns the number of days in the interval, in addition to its
+    /// months.
+    #[inline]
+    pub fn days(&self) -> i32 {
+        self.days
+    }
+
+    /// Returns the number of months in the interval.
+    #[inline]
+    pub fn months(&self) -> i32 {
+        self.months
+    }
+}
+
+/// Serializes a `TXID_SNAPSHOT` or `PG_SNAPSHOT` value.
+#[inline]
+pub fn snapshot_to_sql(
+    xmin: u64,
+    xmax: u64,
+    xip: &[u64],
+    buf: &mut Vec<u8>,
+) -> Result<(), StdBox<Error + Sync + Send>> {
+    let nxip = i32::from_usize(xip.len())?;
+    buf.write_i32::<BigEndian>(nxip).unwrap();
+    buf.write_u64::<BigEndian>(xmin).unwrap();
+    buf.write_u64::<BigEndian>(xmax).unwrap();
+    for &xid in xip {
+        buf.write_u64::<BigEndian>(xid).unwrap();
+    }
+    Ok(())
+}
+
+/// Deserializes a `TXID_SNAPSHOT` or `PG_SNAPSHOT` value.
+#[inline]
+pub fn snapshot_from_sql<'a>(
+    mut buf: &'a [u8],
+) -> Result<Snapshot<'a>, StdBox<Error + Sync + Send>> {
+    let nxip = buf.read_i32::<BigEndian>()?;
+    let xmin = buf.read_u64::<BigEndian>()?;
+    let xmax = buf.read_u64::<BigEndian>()?;
+    if nxip < 0 {
+        return Err("invalid xip count".into());
+    }
+    if buf.len() != nxip as usize * 8 {
+        return Err("invalid message length".into());
+    }
+
+    Ok(Snapshot {
+        xmin: xmin,
+        xmax: xmax,
+        xip: buf,
+    })
+}
+
+/// A `TXID_SNAPSHOT` or `PG_SNAPSHOT` value.
+pub struct Snapshot<'a> {
+    xmin: u64,
+    xmax: u64,
+    xip: &'a [u8],
+}
+
+impl<'a> Snapshot<'a> {
+    /// Returns the earliest transaction ID that is still active.
+    #[inline]
+    pub fn xmin(&self) -> u64 {
+        self.xmin
+    }
+
+    /// Returns the first as-yet-unassigned transaction ID.
+    #[inline]
+    pub fn xmax(&self) -> u64 {
+        self.xmax
+    }
+
+    /// Returns an iterator over the IDs of the transactions in progress.
+    #[inline]
+    pub fn xip(&self) -> SnapshotXip<'a> {
+        SnapshotXip(self.xip)
+    }
+}
+
+/// An iterator over the in-progress transaction IDs of a snapshot.
+pub struct SnapshotXip<'a>(&'a [u8]);
+
+impl<'a> Iterator for SnapshotXip<'a> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<u64> {
+        if self.0.is_empty() {
+            None
+        } else {
+            Some(self.0.read_u64::<BigEndian>().unwrap())
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.len() / 8;
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for SnapshotXip<'a> {}
+
 /// Serializes a `MACADDR` value.
 #[inline]
 pub fn macaddr_to_sql(v: [u8; 6], buf: &mut Vec<u8>) {
@@ -403,6 +636,23 @@ pub fn macaddr_from_sql(buf: &[u8]) -> Result<[u8; 6], StdBox<Error + Sync + Sen
     Ok(out)
 }
 
+/// Serializes a `MACADDR8` value.
+#[inline]
+pub fn macaddr8_to_sql(v: [u8; 8], buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&v);
+}
+
+/// Deserializes a `MACADDR8` value.
+#[inline]
+pub fn macaddr8_from_sql(buf: &[u8]) -> Result<[u8; 8], StdBox<Error + Sync + Send>> {
+    if buf.len() != 8 {
+        return Err("invalid message length".into());
+    }
+    let mut out = [0; 8];
+    out.copy_from_slice(buf);
+    Ok(out)
+}
+
 /// Serializes a `UUID` value.
 #[inline]
 pub fn uuid_to_sql(v: [u8; 16], buf: &mut Vec<u8>) {
@@ -615,6 +865,67 @@ impl<'a> FallibleIterator for ArrayValues<'a> {
     }
 }
 
+/// Deserializes a composite value.
+#[inline]
+pub fn composite_from_sql<'a>(
+    mut buf: &'a [u8],
+) -> Result<CompositeFields<'a>, StdBox<Error + Sync + Send>> {
+    let count = buf.read_i32::<BigEndian>()?;
+    if count < 0 {
+        return Err("invalid field count".into());
+    }
+
+    Ok(CompositeFields {
+        remaining: count,
+        buf: buf,
+    })
+}
+
+/// An iterator over the fields of a composite value.
+///
+/// Each item consists of the OID of the field's type and its value.
+pub struct CompositeFields<'a> {
+    remaining: i32,
+    buf: &'a [u8],
+}
+
+impl<'a> FallibleIterator for CompositeFields<'a> {
+    type Item = (Oid, Option<&'a [u8]>);
+    type Error = StdBox<Error + Sync + Send>;
+
+    #[inline]
+    fn next(&mut self) -> Result<Option<(Oid, Option<&'a [u8]>)>, StdBox<Error + Sync + Send>> {
+        if self.remaining == 0 {
+            if !self.buf.is_empty() {
+                return Err("invalid message length".into());
+            }
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        let oid = self.buf.read_u32::<BigEndian>()?;
+        let len = self.buf.read_i32::<BigEndian>()?;
+        let val = if len < 0 {
+            None
+        } else {
+            if self.buf.len() < len as usize {
+                return Err("invalid value length".into());
+            }
+
+            let (val, buf) = self.buf.split_at(len as usize);
+            self.buf = buf;
+            Some(val)
+        };
+
+        Ok(Some((oid, val)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining as usize;
+        (len, Some(len))
+    }
+}
+
 /// Serializes an empty range.
 #[inline]
 pub fn empty_range_to_sql(buf: &mut Vec<u8>) {
@@ -939,6 +1250,221 @@ impl<'a> FallibleIterator for PathPoints<'a> {
     }
 }
 
+/// Serializes an `INET` or `CIDR` value.
+#[inline]
+pub fn inet_to_sql(addr: IpAddr, netmask: u8, is_cidr: bool, buf: &mut Vec<u8>) {
+    let family = match addr {
+        IpAddr::V4(_) => PGSQL_AF_INET,
+        IpAddr::V6(_) => PGSQL_AF_INET6,
+    };
+    buf.push(family);
+    buf.push(netmask);
+    buf.push(is_cidr as u8);
+    match addr {
+        IpAddr::V4(addr) => {
+            buf.push(4);
+            buf.extend_from_slice(&addr.octets());
+        }
+        IpAddr::V6(addr) => {
+            buf.push(16);
+            buf.extend_from_slice(&addr.octets());
+        }
+    }
+}
+
+/// Deserializes an `INET` or `CIDR` value.
+#[inline]
+pub fn inet_from_sql(mut buf: &[u8]) -> Result<Inet, StdBox<Error + Sync + Send>> {
+    let family = buf.read_u8()?;
+    let netmask = buf.read_u8()?;
+    buf.read_u8()?; // is_cidr
+    let len = buf.read_u8()?;
+
+    let addr = match family {
+        PGSQL_AF_INET => {
+            if netmask > 32 {
+                return Err("invalid IPv4 netmask".into());
+            }
+            if len != 4 {
+                return Err("invalid IPv4 address length".into());
+            }
+            let mut addr = [0; 4];
+            buf.read_exact(&mut addr)?;
+            IpAddr::V4(Ipv4Addr::from(addr))
+        }
+        PGSQL_AF_INET6 => {
+            if netmask > 128 {
+                return Err("invalid IPv6 netmask".into());
+            }
+            if len != 16 {
+                return Err("invalid IPv6 address length".into());
+            }
+            let mut addr = [0; 16];
+            buf.read_exact(&mut addr)?;
+            IpAddr::V6(Ipv6Addr::from(addr))
+        }
+        _ => return Err("invalid IP family".into()),
+    };
+
+    if !buf.is_empty() {
+        return Err("invalid buffer size".into());
+    }
+
+    Ok(Inet {
+        addr: addr,
+        netmask: netmask,
+    })
+}
+
+/// A Postgres network address.
+#[derive(Copy, Clone)]
+pub struct Inet {
+    addr: IpAddr,
+    netmask: u8,
+}
+
+impl Inet {
+    /// Returns the IP address.
+    #[inline]
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// Returns the netmask, as a prefix length.
+    #[inline]
+    pub fn netmask(&self) -> u8 {
+        self.netmask
+    }
+}
+
+/// Deserializes a `TSVECTOR` value.
+#[inline]
+pub fn tsvector_from_sql<'a>(
+    mut buf: &'a [u8],
+) -> Result<TsVectorLexemes<'a>, StdBox<Error + Sync + Send>> {
+    let count = buf.read_i32::<BigEndian>()?;
+    if count < 0 {
+        return Err("invalid lexeme count".into());
+    }
+
+    Ok(TsVectorLexemes {
+        remaining: count,
+        buf: buf,
+    })
+}
+
+/// A fallible iterator over the lexemes of a `TSVECTOR`.
+pub struct TsVectorLexemes<'a> {
+    remaining: i32,
+    buf: &'a [u8],
+}
+
+impl<'a> FallibleIterator for TsVectorLexemes<'a> {
+    type Item = TsVectorLexeme<'a>;
+    type Error = StdBox<Error + Sync + Send>;
+
+    #[inline]
+    fn next(&mut self) -> Result<Option<TsVectorLexeme<'a>>, StdBox<Error + Sync + Send>> {
+        if self.remaining == 0 {
+            if !self.buf.is_empty() {
+                return Err("invalid buffer size".into());
+            }
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+
+        let end = match memchr(0, self.buf) {
+            Some(end) => end,
+            None => return Err("unexpected EOF".into()),
+        };
+        let text = str::from_utf8(&self.buf[..end])?;
+        self.buf = &self.buf[end + 1..];
+
+        let count = self.buf.read_u16::<BigEndian>()? as usize;
+        if self.buf.len() < count * 2 {
+            return Err("unexpected EOF".into());
+        }
+        let (positions, buf) = self.buf.split_at(count * 2);
+        self.buf = buf;
+
+        Ok(Some(TsVectorLexeme {
+            text: text,
+            positions: positions,
+        }))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining as usize;
+        (len, Some(len))
+    }
+}
+
+/// A lexeme of a `TSVECTOR`.
+pub struct TsVectorLexeme<'a> {
+    text: &'a str,
+    positions: &'a [u8],
+}
+
+impl<'a> TsVectorLexeme<'a> {
+    /// Returns the text of the lexeme.
+    #[inline]
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// Returns an iterator over the positions of the lexeme.
+    #[inline]
+    pub fn positions(&self) -> TsVectorPositions<'a> {
+        TsVectorPositions(self.positions)
+    }
+}
+
+/// An iterator over the positions of a `TSVECTOR` lexeme.
+pub struct TsVectorPositions<'a>(&'a [u8]);
+
+impl<'a> Iterator for TsVectorPositions<'a> {
+    type Item = TsVectorPosition;
+
+    #[inline]
+    fn next(&mut self) -> Option<TsVectorPosition> {
+        if self.0.len() < 2 {
+            return None;
+        }
+
+        let value = (u16::from(self.0[0]) << 8) | u16::from(self.0[1]);
+        self.0 = &self.0[2..];
+        Some(TsVectorPosition(value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.len() / 2;
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for TsVectorPositions<'a> {}
+
+/// A position of a `TSVECTOR` lexeme.
+#[derive(Copy, Clone)]
+pub struct TsVectorPosition(u16);
+
+impl TsVectorPosition {
+    /// Returns the position of the lexeme in the document.
+    #[inline]
+    pub fn position(&self) -> u16 {
+        self.0 & 0x3fff
+    }
+
+    /// Returns the weight of the position, from 3 for `A` down to 0 for `D`.
+    #[inline]
+    pub fn weight(&self) -> u8 {
+        (self.0 >> 14) as u8
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -958,6 +1484,36 @@ mod test {
         assert_eq!(bool_from_sql(&buf).unwrap(), false);
     }
 
+    #[test]
+    fn bytea_text() {
+        assert_eq!(bytea_from_text("\\x00ff7F").unwrap(), b"\x00\xff\x7f");
+        assert_eq!(bytea_from_text("\\x").unwrap(), b"");
+        assert!(bytea_from_text("\\x0").is_err());
+        assert!(bytea_from_text("\\xzz").is_err());
+
+        assert_eq!(bytea_from_text("a\\000\\377\\\\b").unwrap(), b"a\x00\xff\\b");
+        assert!(bytea_from_text("\\400").is_err());
+        assert!(bytea_from_text("\\0").is_err());
+    }
+
+    #[test]
+    fn inet() {
+        let mut buf = vec![];
+        inet_to_sql("192.168.0.0".parse().unwrap(), 24, true, &mut buf);
+        assert_eq!(buf, [2, 24, 1, 4, 192, 168, 0, 0]);
+        let inet = inet_from_sql(&buf).unwrap();
+        assert_eq!(inet.addr(), "192.168.0.0".parse::<IpAddr>().unwrap());
+        assert_eq!(inet.netmask(), 24);
+
+        let mut buf = vec![];
+        inet_to_sql("::1".parse().unwrap(), 128, false, &mut buf);
+        let inet = inet_from_sql(&buf).unwrap();
+        assert_eq!(inet.addr(), "::1".parse::<IpAddr>().unwrap());
+        assert_eq!(inet.netmask(), 128);
+
+        assert!(inet_from_sql(&[2, 33, 0, 4, 0, 0, 0, 0]).is_err());
+    }
+
     #[test]
     fn int2() {
         let mut buf = vec![];
@@ -979,6 +1535,17 @@ mod test {
         assert_eq!(int8_from_sql(&buf).unwrap(), 0x0102030405060708);
     }
 
+    #[test]
+    fn interval() {
+        let mut buf = vec![];
+        interval_to_sql(-10_800_000_000, 2, 14, &mut buf);
+        let interval = interval_from_sql(&buf).unwrap();
+        assert_eq!(interval.microseconds(), -10_800_000_000);
+        assert_eq!(interval.days(), 2);
+        assert_eq!(interval.months(), 14);
+        assert!(interval_from_sql(&buf[..15]).is_err());
+    }
+
     #[test]
     fn float4() {
         let mut buf = vec![];
@@ -993,6 +1560,46 @@ mod test {
         assert_eq!(float8_from_sql(&buf).unwrap(), 10343.95);
     }
 
+    #[test]
+    fn float_special_values() {
+        for &v in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let mut buf = vec![];
+            float4_to_sql(v, &mut buf);
+            assert_eq!(float4_from_sql(&buf).unwrap().to_bits(), v.to_bits());
+        }
+
+        for &v in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut buf = vec![];
+            float8_to_sql(v, &mut buf);
+            assert_eq!(float8_from_sql(&buf).unwrap().to_bits(), v.to_bits());
+        }
+    }
+
+    #[test]
+    fn tsvector() {
+        let mut buf = vec![];
+        buf.write_i32::<BigEndian>(2).unwrap();
+        buf.extend_from_slice(b"fox\0");
+        buf.write_u16::<BigEndian>(0).unwrap();
+        buf.extend_from_slice(b"quick\0");
+        buf.write_u16::<BigEndian>(2).unwrap();
+        buf.write_u16::<BigEndian>(2).unwrap();
+        buf.write_u16::<BigEndian>((3 << 14) | 7).unwrap();
+
+        let lexemes = tsvector_from_sql(&buf).unwrap().collect::<Vec<_>>().unwrap();
+        assert_eq!(lexemes.len(), 2);
+        assert_eq!(lexemes[0].text(), "fox");
+        assert_eq!(lexemes[0].positions().count(), 0);
+        assert_eq!(lexemes[1].text(), "quick");
+        let positions = lexemes[1]
+            .positions()
+            .map(|p| (p.position(), p.weight()))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, [(2, 0), (7, 3)]);
+
+        assert!(tsvector_from_sql(&buf[..buf.len() - 1]).unwrap().collect::<Vec<_>>().is_err());
+    }
+
     #[test]
     fn hstore() {
         let mut map = HashMap::new();
@@ -1022,6 +1629,33 @@ mod test {
         assert_eq!(out.bytes(), bits);
     }
 
+    #[test]
+    fn snapshot() {
+        let mut buf = vec![];
+        snapshot_to_sql(10, 20, &[12, 15], &mut buf).unwrap();
+        let out = snapshot_from_sql(&buf).unwrap();
+        assert_eq!(out.xmin(), 10);
+        assert_eq!(out.xmax(), 20);
+        assert_eq!(out.xip().collect::<Vec<_>>(), [12, 15]);
+
+        assert!(snapshot_from_sql(&buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn composite() {
+        let buf = b"\
+\x00\x00\x00\x02\
+\x00\x00\x00\x17\x00\x00\x00\x04\x00\x00\x00\x2a\
+\x00\x00\x00\x19\xff\xff\xff\xff";
+        let fields = composite_from_sql(buf).unwrap();
+        assert_eq!(
+            fields.collect::<Vec<_>>().unwrap(),
+            [(23, Some(&b"\x00\x00\x00\x2a"[..])), (25, None)]
+        );
+
+        assert!(composite_from_sql(&buf[..buf.len() - 1]).unwrap().collect::<Vec<_>>().is_err());
+    }
+
     #[test]
     fn array() {
         let dimensions = [
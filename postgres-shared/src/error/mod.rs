@@ -6,6 +6,7 @@ use std::error;
 use std::convert::From;
 use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
 
 pub use self::sqlstate::*;
 
@@ -309,6 +310,30 @@ pub enum ErrorPosition {
     },
 }
 
+// Creates a WARNING notice generated by the client rather than the server.
+#[doc(hidden)]
+pub fn warning(message: String) -> DbError {
+    DbError {
+        severity: "WARNING".to_owned(),
+        parsed_severity: Some(Severity::Warning),
+        code: WARNING,
+        message: message,
+        detail: None,
+        hint: None,
+        position: None,
+        where_: None,
+        schema: None,
+        table: None,
+        column: None,
+        datatype: None,
+        constraint: None,
+        file: None,
+        line: None,
+        routine: None,
+        _p: (),
+    }
+}
+
 #[doc(hidden)]
 pub fn connect(e: Box<error::Error + Sync + Send>) -> Error {
     Error(Box::new(ErrorKind::ConnectParams(e)))
@@ -334,6 +359,41 @@ pub fn conversion(e: Box<error::Error + Sync + Send>) -> Error {
     Error(Box::new(ErrorKind::Conversion(e)))
 }
 
+#[doc(hidden)]
+pub fn empty_query() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, EmptyQuery)
+}
+
+#[derive(Debug)]
+struct EmptyQuery;
+
+impl fmt::Display for EmptyQuery {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("the query was empty")
+    }
+}
+
+impl error::Error for EmptyQuery {
+    fn description(&self) -> &str {
+        "the query was empty"
+    }
+}
+
+#[doc(hidden)]
+pub fn batch(position: usize, e: Error) -> Error {
+    Error(Box::new(ErrorKind::Batch(position, e)))
+}
+
+#[doc(hidden)]
+pub fn prepare(index: usize, e: Error) -> Error {
+    Error(Box::new(ErrorKind::Prepare(index, e)))
+}
+
+#[doc(hidden)]
+pub fn file(path: PathBuf, e: Error) -> Error {
+    Error(Box::new(ErrorKind::File(path, e)))
+}
+
 #[derive(Debug)]
 enum ErrorKind {
     ConnectParams(Box<error::Error + Sync + Send>),
@@ -341,6 +401,9 @@ enum ErrorKind {
     Db(DbError),
     Io(io::Error),
     Conversion(Box<error::Error + Sync + Send>),
+    Batch(usize, Error),
+    Prepare(usize, Error),
+    File(PathBuf, Error),
 }
 
 /// An error communicating with the Postgres server.
@@ -349,6 +412,16 @@ pub struct Error(Box<ErrorKind>);
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if let ErrorKind::Batch(position, ref err) = *self.0 {
+            return write!(fmt, "error in query {} of batch: {}", position, err);
+        }
+        if let ErrorKind::Prepare(index, ref err) = *self.0 {
+            return write!(fmt, "error preparing query at index {}: {}", index, err);
+        }
+        if let ErrorKind::File(ref path, ref err) = *self.0 {
+            return write!(fmt, "error in file {}: {}", path.display(), err);
+        }
+
         fmt.write_str(error::Error::description(self))?;
         match *self.0 {
             ErrorKind::ConnectParams(ref err) => write!(fmt, ": {}", err),
@@ -356,6 +429,9 @@ impl fmt::Display for Error {
             ErrorKind::Db(ref err) => write!(fmt, ": {}", err),
             ErrorKind::Io(ref err) => write!(fmt, ": {}", err),
             ErrorKind::Conversion(ref err) => write!(fmt, ": {}", err),
+            ErrorKind::Batch(..) | ErrorKind::Prepare(..) | ErrorKind::File(..) => {
+                unreachable!()
+            }
         }
     }
 }
@@ -368,6 +444,9 @@ impl error::Error for Error {
             ErrorKind::Db(_) => "database error",
             ErrorKind::Io(_) => "IO error",
             ErrorKind::Conversion(_) => "type conversion error",
+            ErrorKind::Batch(_, ref err) => error::Error::description(err),
+            ErrorKind::Prepare(_, ref err) => error::Error::description(err),
+            ErrorKind::File(_, ref err) => error::Error::description(err),
         }
     }
 
@@ -378,6 +457,9 @@ impl error::Error for Error {
             ErrorKind::Db(ref err) => Some(err),
             ErrorKind::Io(ref err) => Some(err),
             ErrorKind::Conversion(ref err) => Some(&**err),
+            ErrorKind::Batch(_, ref err) => error::Error::cause(err),
+            ErrorKind::Prepare(_, ref err) => error::Error::cause(err),
+            ErrorKind::File(_, ref err) => error::Error::cause(err),
         }
     }
 }
@@ -389,10 +471,70 @@ impl Error {
         self.as_db().map(|e| &e.code)
     }
 
+    /// Determines if the operation that caused this error may succeed if it
+    /// is retried.
+    ///
+    /// This is the case for serialization failures and deadlocks, which
+    /// Postgres reports when concurrent transactions conflict.
+    pub fn is_retryable(&self) -> bool {
+        let code = self.code();
+        code == Some(&T_R_SERIALIZATION_FAILURE) || code == Some(&T_R_DEADLOCK_DETECTED)
+    }
+
+    /// Determines if this error was caused by executing a query which
+    /// contained no statements, such as an empty string or only a comment.
+    ///
+    /// Such an error is an IO error of the `InvalidInput` kind.
+    pub fn is_empty_query(&self) -> bool {
+        match self.as_io().and_then(|e| e.get_ref()) {
+            Some(e) => e.is::<EmptyQuery>(),
+            None => false,
+        }
+    }
+
+    /// Returns the 1-based position of the statement which caused this error
+    /// if it was returned by executing a batch of statements, such as with
+    /// `Connection::batch_execute`.
+    ///
+    /// The other methods of this type inspect the error of that statement.
+    pub fn batch_index(&self) -> Option<usize> {
+        match *self.0 {
+            ErrorKind::Batch(position, _) => Some(position),
+            ErrorKind::File(_, ref err) => err.batch_index(),
+            _ => None,
+        }
+    }
+
+    /// Returns the 0-based index of the query which caused this error if it
+    /// was returned by `Connection::prepare_all`.
+    ///
+    /// The other methods of this type inspect the error of that query.
+    pub fn prepare_index(&self) -> Option<usize> {
+        match *self.0 {
+            ErrorKind::Prepare(index, _) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Returns the path of the file which caused this error if it was
+    /// returned by an operation on a file of queries, such as
+    /// `Connection::execute_file`.
+    ///
+    /// The other methods of this type inspect the error of that file.
+    pub fn file_path(&self) -> Option<&Path> {
+        match *self.0 {
+            ErrorKind::File(ref path, _) => Some(path),
+            _ => None,
+        }
+    }
+
     /// Returns the inner error if this is a connection parameter error.
     pub fn as_connection(&self) -> Option<&(error::Error + 'static + Sync + Send)> {
         match *self.0 {
             ErrorKind::ConnectParams(ref err) => Some(&**err),
+            ErrorKind::Batch(_, ref err) => err.as_connection(),
+            ErrorKind::Prepare(_, ref err) => err.as_connection(),
+            ErrorKind::File(_, ref err) => err.as_connection(),
             _ => None,
         }
     }
@@ -401,6 +543,9 @@ impl Error {
     pub fn as_db(&self) -> Option<&DbError> {
         match *self.0 {
             ErrorKind::Db(ref err) => Some(err),
+            ErrorKind::Batch(_, ref err) => err.as_db(),
+            ErrorKind::Prepare(_, ref err) => err.as_db(),
+            ErrorKind::File(_, ref err) => err.as_db(),
             _ => None
         }
     }
@@ -409,6 +554,9 @@ impl Error {
     pub fn as_conversion(&self) -> Option<&(error::Error + 'static + Sync + Send)> {
         match *self.0 {
             ErrorKind::Conversion(ref err) => Some(&**err),
+            ErrorKind::Batch(_, ref err) => err.as_conversion(),
+            ErrorKind::Prepare(_, ref err) => err.as_conversion(),
+            ErrorKind::File(_, ref err) => err.as_conversion(),
             _ => None,
         }
     }
@@ -418,6 +566,9 @@ impl Error {
     pub fn as_io(&self) -> Option<&io::Error> {
         match *self.0 {
             ErrorKind::Io(ref err) => Some(err),
+            ErrorKind::Batch(_, ref err) => err.as_io(),
+            ErrorKind::Prepare(_, ref err) => err.as_io(),
+            ErrorKind::File(_, ref err) => err.as_io(),
             _ => None,
         }
     }
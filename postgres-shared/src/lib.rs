@@ -30,3 +30,43 @@ pub struct Notification {
     /// The "payload" string passed from the notifying process.
     pub payload: String,
 }
+
+// WARNING: this function is not considered part of this crate's public API.
+// It is subject to change at any time.
+#[doc(hidden)]
+pub fn parse_server_version(version: &str) -> Option<(u16, u16, u16)> {
+    // Strip trailing junk like "beta1" or " (Debian 14.2-1.pgdg110+1)"
+    let end = version
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(version.len());
+
+    let mut parts = version[..end].split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = match parts.next() {
+        Some(minor) => minor.parse().ok()?,
+        None => 0,
+    };
+    let patch = match parts.next() {
+        Some(patch) => patch.parse().ok()?,
+        None => 0,
+    };
+
+    Some((major, minor, patch))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn server_version() {
+        assert_eq!(parse_server_version("9.6.24"), Some((9, 6, 24)));
+        assert_eq!(
+            parse_server_version("14.2 (Debian 14.2-1.pgdg110+1)"),
+            Some((14, 2, 0))
+        );
+        assert_eq!(parse_server_version("10beta1"), Some((10, 0, 0)));
+        assert_eq!(parse_server_version("devel"), None);
+        assert!(parse_server_version("9.6.24") < parse_server_version("14.2"));
+    }
+}
@@ -0,0 +1,123 @@
+//! Connection service files, as read by libpq.
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+/// Returns the paths of the service files which are searched for services.
+///
+/// These are the file named by the `PGSERVICEFILE` environment variable, or
+/// `~/.pg_service.conf` if it is not set, followed by `pg_service.conf` in the
+/// directory named by the `PGSYSCONFDIR` environment variable.
+pub fn default_paths() -> Vec<PathBuf> {
+    let mut paths = vec![];
+    match env::var_os("PGSERVICEFILE") {
+        Some(path) => paths.push(PathBuf::from(path)),
+        None => {
+            if let Some(home) = env::var_os("HOME") {
+                paths.push(PathBuf::from(home).join(".pg_service.conf"));
+            }
+        }
+    }
+    if let Some(dir) = env::var_os("PGSYSCONFDIR") {
+        paths.push(PathBuf::from(dir).join("pg_service.conf"));
+    }
+    paths
+}
+
+/// Looks up the settings of a service in the first of the files which
+/// defines it.
+pub fn load(
+    name: &str,
+    paths: &[PathBuf],
+) -> Result<Vec<(String, String)>, Box<Error + Sync + Send>> {
+    for path in paths {
+        let mut contents = String::new();
+        match File::open(path) {
+            Ok(mut file) => {
+                file.read_to_string(&mut contents)?;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        }
+
+        match parse(&contents, name) {
+            Ok(Some(settings)) => return Ok(settings),
+            Ok(None) => {}
+            Err(e) => return Err(format!("{} in service file `{}`", e, path.display()).into()),
+        }
+    }
+
+    Err(format!("definition of service `{}` not found", name).into())
+}
+
+// Returns the settings of the named service, or `None` if the contents do not
+// define it.
+fn parse(contents: &str, name: &str) -> Result<Option<Vec<(String, String)>>, String> {
+    let mut settings = None;
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            if settings.is_some() {
+                break;
+            }
+            if !line.ends_with(']') {
+                return Err(format!("syntax error on line {}", i + 1));
+            }
+            if line[1..line.len() - 1].trim() == name {
+                settings = Some(vec![]);
+            }
+            continue;
+        }
+
+        let idx = match line.find('=') {
+            Some(idx) => idx,
+            None => return Err(format!("syntax error on line {}", i + 1)),
+        };
+        if let Some(ref mut settings) = settings {
+            let key = line[..idx].trim().to_owned();
+            let value = line[idx + 1..].trim().to_owned();
+            settings.push((key, value));
+        }
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_service() {
+        let contents = "\
+# comment
+[foo]
+host=foo.example.com
+
+[bar]
+host = localhost
+port=5433
+  dbname=postgres
+
+[baz]
+host=baz.example.com
+";
+        assert_eq!(
+            parse(contents, "bar").unwrap(),
+            Some(vec![
+                ("host".to_owned(), "localhost".to_owned()),
+                ("port".to_owned(), "5433".to_owned()),
+                ("dbname".to_owned(), "postgres".to_owned()),
+            ])
+        );
+        assert_eq!(parse(contents, "qux").unwrap(), None);
+        assert!(parse("[foo]\nhost", "foo").is_err());
+    }
+}
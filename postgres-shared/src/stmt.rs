@@ -1,18 +1,30 @@
-use types::Type;
+use types::{Oid, Type};
 
 /// Information about a column of a Postgres query.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Column {
     name: String,
     type_: Type,
+    table_oid: Option<Oid>,
+    column_id: Option<i16>,
+    type_modifier: Option<i32>,
 }
 
 impl Column {
     #[doc(hidden)]
-    pub fn new(name: String, type_: Type) -> Column {
+    pub fn new(
+        name: String,
+        type_: Type,
+        table_oid: Oid,
+        column_id: i16,
+        type_modifier: i32,
+    ) -> Column {
         Column {
             name: name,
             type_: type_,
+            table_oid: if table_oid == 0 { None } else { Some(table_oid) },
+            column_id: if column_id == 0 { None } else { Some(column_id) },
+            type_modifier: if type_modifier == -1 { None } else { Some(type_modifier) },
         }
     }
 
@@ -25,4 +37,31 @@ impl Column {
     pub fn type_(&self) -> &Type {
         &self.type_
     }
+
+    /// Returns the OID of the table the column was taken from, if it can be
+    /// identified as a column of a table.
+    ///
+    /// Together with `column_id`, this can be used to look the column up in
+    /// the `pg_attribute` catalog to determine properties such as whether it
+    /// is nullable.
+    pub fn table_oid(&self) -> Option<Oid> {
+        self.table_oid
+    }
+
+    /// Returns the attribute number of the column within the table identified
+    /// by `table_oid`, if it can be identified as a column of a table.
+    pub fn column_id(&self) -> Option<i16> {
+        self.column_id
+    }
+
+    /// Returns the type modifier of the column, if it has one.
+    ///
+    /// The meaning of the modifier depends on the type. For `VARCHAR(n)` and
+    /// `CHAR(n)` columns it is the maximum length `n` plus 4, which can be
+    /// used to check the length of values before they are sent to the server.
+    /// The server does not report modifiers for the parameters of a
+    /// statement, only for the columns of its result.
+    pub fn type_modifier(&self) -> Option<i32> {
+        self.type_modifier
+    }
 }
@@ -2,10 +2,11 @@ extern crate chrono;
 
 use postgres_protocol::types;
 use self::chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime,
-                   Utc};
+                   Timelike, Utc};
+use std::cmp;
 use std::error::Error;
 
-use types::{FromSql, IsNull, ToSql, Type, DATE, TIME, TIMESTAMP, TIMESTAMPTZ};
+use types::{FromSql, Interval, IsNull, TimeTz, ToSql, Type, DATE, TIME, TIMESTAMP, TIMESTAMPTZ};
 
 fn base() -> NaiveDateTime {
     NaiveDate::from_ymd(2000, 1, 1).and_hms(0, 0, 0)
@@ -138,3 +139,59 @@ impl ToSql for NaiveTime {
     accepts!(TIME);
     to_sql_checked!();
 }
+
+impl Interval {
+    /// Converts the days and microseconds of the interval to a `Duration`,
+    /// treating each day as 24 hours.
+    ///
+    /// Months do not have a fixed length, so an error is returned if the
+    /// interval has a nonzero number of months.
+    ///
+    /// Requires the `with-chrono` feature.
+    pub fn to_chrono_duration(&self) -> Result<Duration, Box<Error + Sync + Send>> {
+        if self.months != 0 {
+            return Err("an interval containing months cannot be converted to a Duration".into());
+        }
+
+        Duration::days(self.days as i64)
+            .checked_add(&Duration::microseconds(self.microseconds))
+            .ok_or_else(|| "value too large to convert to a Duration".into())
+    }
+}
+
+impl TimeTz {
+    /// Converts the value to a `NaiveTime` and the `FixedOffset` of its zone.
+    ///
+    /// An error is returned if the time is `24:00:00`, which cannot be
+    /// represented by a `NaiveTime`.
+    ///
+    /// Requires the `with-chrono` feature.
+    pub fn to_chrono(&self) -> Result<(NaiveTime, FixedOffset), Box<Error + Sync + Send>> {
+        let time = if 0 <= self.time_micros && self.time_micros < 24 * 60 * 60 * 1_000_000 {
+            NaiveTime::from_num_seconds_from_midnight_opt(
+                (self.time_micros / 1_000_000) as u32,
+                (self.time_micros % 1_000_000 * 1000) as u32,
+            )
+        } else {
+            None
+        };
+        let time = time.ok_or("time out of range for a NaiveTime")?;
+        let offset = FixedOffset::east_opt(self.zone_offset_secs)
+            .ok_or("time zone offset out of range for a FixedOffset")?;
+        Ok((time, offset))
+    }
+
+    /// Creates a value from a `NaiveTime` and a `FixedOffset`.
+    ///
+    /// Leap seconds are not supported by Postgres, and are rounded down.
+    ///
+    /// Requires the `with-chrono` feature.
+    pub fn from_chrono(time: NaiveTime, offset: FixedOffset) -> TimeTz {
+        let nanos = cmp::min(time.nanosecond(), 999_999_999);
+        TimeTz {
+            time_micros: time.num_seconds_from_midnight() as i64 * 1_000_000 +
+                (nanos / 1000) as i64,
+            zone_offset_secs: offset.local_minus_utc(),
+        }
+    }
+}
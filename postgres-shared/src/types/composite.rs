@@ -0,0 +1,91 @@
+use fallible_iterator::FallibleIterator;
+use postgres_protocol::types;
+use std::error::Error;
+
+use types::{Field, FromSql, Kind, Type, WrongType};
+
+/// The value of a composite type, whose fields can be read individually.
+///
+/// It can be read from a value of any composite type, and uses the field
+/// information of the type to convert each field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Composite {
+    fields: Vec<Field>,
+    values: Vec<Option<Vec<u8>>>,
+}
+
+impl FromSql for Composite {
+    fn from_sql(ty: &Type, raw: &[u8]) -> Result<Composite, Box<Error + Sync + Send>> {
+        let fields = match *ty.kind() {
+            Kind::Composite(ref fields) => fields,
+            _ => return Err(Box::new(WrongType::new(ty.clone()))),
+        };
+
+        let mut values = Vec::with_capacity(fields.len());
+        let mut it = types::composite_from_sql(raw)?;
+        while let Some((oid, value)) = it.next()? {
+            let field = match fields.get(values.len()) {
+                Some(field) => field,
+                None => return Err("more fields than expected in composite value".into()),
+            };
+            if oid != field.type_().oid() {
+                return Err(format!("unexpected type of field `{}`", field.name()).into());
+            }
+            values.push(value.map(|v| v.to_vec()));
+        }
+        if values.len() != fields.len() {
+            return Err("fewer fields than expected in composite value".into());
+        }
+
+        Ok(Composite {
+            fields: fields.clone(),
+            values: values,
+        })
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        match *ty.kind() {
+            Kind::Composite(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Composite {
+    /// Returns information about the fields of the value.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Converts the value of the field at the specified index.
+    ///
+    /// An error is returned if the index is out of bounds or the field's
+    /// value cannot be converted to the specified type.
+    pub fn get<T>(&self, idx: usize) -> Result<T, Box<Error + Sync + Send>>
+    where
+        T: FromSql,
+    {
+        let field = match self.fields.get(idx) {
+            Some(field) => field,
+            None => return Err(format!("field index {} out of bounds", idx).into()),
+        };
+        if !T::accepts(field.type_()) {
+            return Err(Box::new(WrongType::new(field.type_().clone())));
+        }
+        T::from_sql_nullable(field.type_(), self.values[idx].as_ref().map(|v| &**v))
+    }
+
+    /// Converts the value of the field with the specified name.
+    ///
+    /// An error is returned if there is no such field or its value cannot be
+    /// converted to the specified type.
+    pub fn get_named<T>(&self, name: &str) -> Result<T, Box<Error + Sync + Send>>
+    where
+        T: FromSql,
+    {
+        match self.fields.iter().position(|f| f.name() == name) {
+            Some(idx) => self.get(idx),
+            None => Err(format!("no field named `{}`", name).into()),
+        }
+    }
+}
@@ -4,9 +4,10 @@ use fallible_iterator::FallibleIterator;
 use postgres_protocol;
 use postgres_protocol::types::{self, ArrayDimension};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
+use std::hash::{BuildHasher, Hash};
 use std::sync::Arc;
 
 use types::type_gen::{Inner, Other};
@@ -15,7 +16,11 @@ use types::type_gen::{Inner, Other};
 pub use postgres_protocol::Oid;
 
 pub use types::type_gen::consts::*;
-pub use types::special::{Date, Timestamp};
+pub use types::composite::Composite;
+pub use types::range::{Range, RangeBound};
+pub use types::special::{Cidr, Date, Interval, Json, JsonPath, Jsonb, Lsn, MacAddr, MacAddr8,
+                         Snapshot, TimeTz, Timestamp};
+pub use types::tsearch::{TsLexeme, TsPosition, TsQuery, TsVector, TsWeight};
 
 /// Generates a simple implementation of `ToSql::accepts` which accepts the
 /// types passed to it.
@@ -83,7 +88,10 @@ mod eui48;
 #[cfg(feature = "with-geo")]
 mod geo;
 
+mod composite;
+mod range;
 mod special;
+mod tsearch;
 mod type_gen;
 
 /// A Postgres type.
@@ -93,7 +101,7 @@ pub struct Type(Inner);
 impl fmt::Display for Type {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self.schema() {
-            "public" | "pg_catalog" => {}
+            "public" | "pg_catalog" | "" => {}
             schema => write!(fmt, "{}.", schema)?,
         }
         fmt.write_str(self.name())
@@ -103,12 +111,13 @@ impl fmt::Display for Type {
 impl Type {
     // WARNING: this is not considered public API
     #[doc(hidden)]
-    pub fn _new(name: String, oid: Oid, kind: Kind, schema: String) -> Type {
+    pub fn _new(name: String, oid: Oid, kind: Kind, schema: String, binary_io: bool) -> Type {
         Type(Inner::Other(Arc::new(Other {
             name: name,
             oid: oid,
             kind: kind,
             schema: schema,
+            binary_io: binary_io,
         })))
     }
 
@@ -140,6 +149,23 @@ impl Type {
     pub fn name(&self) -> &str {
         self.0.name()
     }
+
+    /// Determines if values of this type can be sent and received in the
+    /// binary format.
+    ///
+    /// A few types, such as `ACLITEM`, have no binary input and output
+    /// functions, and so can only be exchanged in the text format. This also
+    /// applies to arrays, ranges and composites containing such types.
+    pub fn has_binary_io(&self) -> bool {
+        match self.0 {
+            Inner::Other(ref u) => u.binary_io,
+            Inner::Aclitem |
+            Inner::AclitemArray |
+            Inner::GtsVector |
+            Inner::GtsVectorArray => false,
+            _ => true,
+        }
+    }
 }
 
 /// Represents the kind of a Postgres type.
@@ -244,19 +270,41 @@ impl WrongType {
 /// The following implementations are provided by this crate, along with the
 /// corresponding Postgres types:
 ///
-/// | Rust type                         | Postgres type(s)                              |
-/// |-----------------------------------|-----------------------------------------------|
-/// | `bool`                            | BOOL                                          |
-/// | `i8`                              | "char"                                        |
-/// | `i16`                             | SMALLINT, SMALLSERIAL                         |
-/// | `i32`                             | INT, SERIAL                                   |
-/// | `u32`                             | OID                                           |
-/// | `i64`                             | BIGINT, BIGSERIAL                             |
-/// | `f32`                             | REAL                                          |
-/// | `f64`                             | DOUBLE PRECISION                              |
-/// | `String`                          | VARCHAR, CHAR(n), TEXT, CITEXT, NAME, UNKNOWN |
-/// | `Vec<u8>`                         | BYTEA                                         |
-/// | `HashMap<String, Option<String>>` | HSTORE                                        |
+/// | Rust type                         | Postgres type(s)                                         |
+/// |-----------------------------------|----------------------------------------------------------|
+/// | `bool`                            | BOOL                                                     |
+/// | `i8`                              | "char"                                                   |
+/// | `i16`                             | SMALLINT, SMALLSERIAL                                    |
+/// | `i32`                             | INT, SERIAL                                              |
+/// | `u32`                             | OID, REGCLASS, REGTYPE, etc.                             |
+/// | `i64`                             | BIGINT, BIGSERIAL                                        |
+/// | `f32`                             | REAL                                                     |
+/// | `f64`                             | DOUBLE PRECISION                                         |
+/// | `String`                          | VARCHAR, CHAR(n), TEXT, CITEXT, NAME, UNKNOWN, REFCURSOR |
+/// | `Vec<u8>`                         | BYTEA                                                    |
+/// | `HashMap<String, Option<String>>` | HSTORE                                                   |
+/// | `Json`                            | JSON                                                     |
+/// | `Jsonb`                           | JSONB                                                    |
+/// | `JsonPath`                        | JSONPATH                                                 |
+/// | `Lsn`                             | PG_LSN                                                   |
+/// | `Snapshot`                        | TXID_SNAPSHOT, PG_SNAPSHOT                               |
+/// | `Cidr`                            | CIDR, INET                                               |
+/// | `MacAddr`                         | MACADDR                                                  |
+/// | `MacAddr8`                        | MACADDR8                                                 |
+/// | `Interval`                        | INTERVAL                                                 |
+/// | `TimeTz`                          | TIME WITH TIME ZONE                                      |
+/// | `TsVector`                        | TSVECTOR                                                 |
+/// | `()`                              | VOID                                                     |
+/// | `Range<T>`                        | range types with a subtype accepted by `T`               |
+/// | `Composite`                       | composite types                                          |
+///
+/// Note that OIDs are unsigned, so OID columns, such as those of the system
+/// catalogs, must be read as `u32` (or its alias `Oid`) rather than `i32`.
+///
+/// `CHAR(n)` (`BPCHAR`) values are padded with spaces to their declared
+/// length, and the padding is preserved when they are read as a `String`. Use
+/// `Row::get_bpchar_trimmed` to strip it. The single byte `"char"` type is
+/// distinct, and is read as an `i8`.
 ///
 /// In addition, some implementations are provided for types in third party
 /// crates. These are disabled by default; to opt into one of these
@@ -280,6 +328,11 @@ impl WrongType {
 /// | `bit_vec::BitVec`               | BIT, VARBIT                         |
 /// | `eui48::MacAddress`             | MACADDR                             |
 ///
+/// The time zone of a `TIMESTAMP WITH TIME ZONE` value is not preserved by
+/// the server, which only stores the instant. To present a value in a time
+/// zone chosen at runtime, such as a `chrono_tz::Tz`, read it as a
+/// `chrono::DateTime<Utc>` and convert it with `with_timezone`.
+///
 /// # Nullability
 ///
 /// In addition to the types listed above, `FromSql` is implemented for
@@ -289,7 +342,17 @@ impl WrongType {
 /// # Arrays
 ///
 /// `FromSql` is implemented for `Vec<T>` where `T` implements `FromSql`, and
-/// corresponds to one-dimensional Postgres arrays.
+/// corresponds to one-dimensional Postgres arrays. Arrays which may contain
+/// `NULL` elements should be read into a `Vec<Option<T>>`, as a `NULL` element
+/// produces an error if `T` is not nullable. It is also implemented for
+/// fixed-size arrays `[T; N]` with `N` up to 32, which produce an error if the
+/// Postgres array does not contain exactly `N` elements.
+///
+/// `HashSet<T>` and `BTreeSet<T>` can also be read from one-dimensional
+/// arrays, in which case duplicate elements are discarded.
+///
+/// The catalog types `INT2VECTOR` and `OIDVECTOR` use the array format, and
+/// can be read into a `Vec<i16>` and a `Vec<u32>` respectively.
 pub trait FromSql: Sized {
     /// Creates a new value of this type from a buffer of data of the specified
     /// Postgres `Type` in its binary format.
@@ -364,6 +427,30 @@ impl<T: FromSql> FromSql for Vec<T> {
     }
 }
 
+impl<T, S> FromSql for HashSet<T, S>
+where
+    T: FromSql + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_sql(ty: &Type, raw: &[u8]) -> Result<HashSet<T, S>, Box<Error + Sync + Send>> {
+        <Vec<T> as FromSql>::from_sql(ty, raw).map(|v| v.into_iter().collect())
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <Vec<T> as FromSql>::accepts(ty)
+    }
+}
+
+impl<T: FromSql + Ord> FromSql for BTreeSet<T> {
+    fn from_sql(ty: &Type, raw: &[u8]) -> Result<BTreeSet<T>, Box<Error + Sync + Send>> {
+        <Vec<T> as FromSql>::from_sql(ty, raw).map(|v| v.into_iter().collect())
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <Vec<T> as FromSql>::accepts(ty)
+    }
+}
+
 impl FromSql for Vec<u8> {
     fn from_sql(_: &Type, raw: &[u8]) -> Result<Vec<u8>, Box<Error + Sync + Send>> {
         Ok(types::bytea_from_sql(raw).to_owned())
@@ -379,7 +466,7 @@ impl FromSql for String {
 
     fn accepts(ty: &Type) -> bool {
         match *ty {
-            VARCHAR | TEXT | BPCHAR | NAME | UNKNOWN => true,
+            VARCHAR | TEXT | BPCHAR | NAME | UNKNOWN | REFCURSOR => true,
             ref ty if ty.name() == "citext" => true,
             _ => false,
         }
@@ -404,11 +491,35 @@ simple_from!(bool, bool_from_sql, BOOL);
 simple_from!(i8, char_from_sql, CHAR);
 simple_from!(i16, int2_from_sql, INT2);
 simple_from!(i32, int4_from_sql, INT4);
-simple_from!(u32, oid_from_sql, OID);
+// The OID alias types share the representation of OID
+simple_from!(
+    u32,
+    oid_from_sql,
+    OID,
+    REGPROC,
+    REGPROCEDURE,
+    REGOPER,
+    REGOPERATOR,
+    REGCLASS,
+    REGTYPE,
+    REGCONFIG,
+    REGDICTIONARY,
+    REGNAMESPACE,
+    REGROLE
+);
 simple_from!(i64, int8_from_sql, INT8);
 simple_from!(f32, float4_from_sql, FLOAT4);
 simple_from!(f64, float8_from_sql, FLOAT8);
 
+// Functions returning void produce a single value with no contents
+impl FromSql for () {
+    fn from_sql(_: &Type, _: &[u8]) -> Result<(), Box<Error + Sync + Send>> {
+        Ok(())
+    }
+
+    accepts!(VOID);
+}
+
 impl FromSql for HashMap<String, Option<String>> {
     fn from_sql(
         _: &Type,
@@ -445,7 +556,7 @@ pub enum IsNull {
 /// | `i8`                              | "char"                               |
 /// | `i16`                             | SMALLINT, SMALLSERIAL                |
 /// | `i32`                             | INT, SERIAL                          |
-/// | `u32`                             | OID                                  |
+/// | `u32`                             | OID, REGCLASS, REGTYPE, etc.         |
 /// | `i64`                             | BIGINT, BIGSERIAL                    |
 /// | `f32`                             | REAL                                 |
 /// | `f64`                             | DOUBLE PRECISION                     |
@@ -454,6 +565,18 @@ pub enum IsNull {
 /// | `Vec<u8>`                         | BYTEA                                |
 /// | `&[u8]`                           | BYTEA                                |
 /// | `HashMap<String, Option<String>>` | HSTORE                               |
+/// | `Json`                            | JSON                                 |
+/// | `Jsonb`                           | JSONB                                |
+/// | `JsonPath`                        | JSONPATH                             |
+/// | `Lsn`                             | PG_LSN                               |
+/// | `Snapshot`                        | TXID_SNAPSHOT, PG_SNAPSHOT           |
+/// | `Cidr`                            | CIDR, INET                           |
+/// | `MacAddr`                         | MACADDR                              |
+/// | `MacAddr8`                        | MACADDR8                             |
+/// | `Interval`                        | INTERVAL                             |
+/// | `TimeTz`                          | TIME WITH TIME ZONE                  |
+/// | `TsQuery`                         | TSQUERY                              |
+/// | `Range<T>`                        | range types with a suitable subtype  |
 ///
 /// In addition, some implementations are provided for types in third party
 /// crates. These are disabled by default; to opt into one of these
@@ -482,11 +605,20 @@ pub enum IsNull {
 /// `Option<T>` where `T` implements `ToSql`. An `Option<T>` represents a
 /// nullable Postgres value.
 ///
+/// # References
+///
+/// `ToSql` is also implemented for `&T` where `T` implements `ToSql`, so
+/// borrowed values such as `&String` or `Option<&i32>` can be passed as
+/// parameters directly, without cloning them into temporaries.
+///
 /// # Arrays
 ///
 /// `ToSql` is implemented for `Vec<T>` and `&[T]` where `T` implements `ToSql`,
 /// and corresponds to one-dimentional Postgres arrays with an index offset of
-/// 1.
+/// 1. It is also implemented for fixed-size arrays `[T; N]` with `N` up to 32.
+///
+/// `HashSet<T>` and `BTreeSet<T>` are also sent as arrays, with their elements
+/// in iteration order.
 pub trait ToSql: fmt::Debug {
     /// Converts the value of `self` into the binary format of the specified
     /// Postgres `Type`, appending it to `out`.
@@ -518,6 +650,26 @@ pub trait ToSql: fmt::Debug {
     ) -> Result<IsNull, Box<Error + Sync + Send>>;
 }
 
+// These allow `&ToSql`, `&(ToSql + Sync)` and `Box<ToSql + Sync>` to be used
+// wherever a parameter is required to implement `AsRef<ToSql>`.
+impl<'a> AsRef<ToSql + 'a> for ToSql + 'a {
+    fn as_ref(&self) -> &(ToSql + 'a) {
+        self
+    }
+}
+
+impl<'a> AsRef<ToSql + 'a> for ToSql + Sync + 'a {
+    fn as_ref(&self) -> &(ToSql + 'a) {
+        self
+    }
+}
+
+impl<'a> AsRef<ToSql + 'a> for Box<ToSql + Sync + 'a> {
+    fn as_ref(&self) -> &(ToSql + 'a) {
+        &**self
+    }
+}
+
 impl<'a, T> ToSql for &'a T
 where
     T: ToSql,
@@ -550,28 +702,7 @@ impl<T: ToSql> ToSql for Option<T> {
 
 impl<'a, T: ToSql> ToSql for &'a [T] {
     fn to_sql(&self, ty: &Type, w: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
-        let member_type = match *ty.kind() {
-            Kind::Array(ref member) => member,
-            _ => panic!("expected array type"),
-        };
-
-        let dimension = ArrayDimension {
-            len: downcast(self.len())?,
-            lower_bound: 1,
-        };
-
-        types::array_to_sql(
-            Some(dimension),
-            true,
-            member_type.oid(),
-            self.iter(),
-            |e, w| match e.to_sql(member_type, w)? {
-                IsNull::No => Ok(postgres_protocol::IsNull::No),
-                IsNull::Yes => Ok(postgres_protocol::IsNull::Yes),
-            },
-            w,
-        )?;
-        Ok(IsNull::No)
+        array_to_sql(ty, self.len(), self.iter(), w)
     }
 
     fn accepts(ty: &Type) -> bool {
@@ -619,6 +750,170 @@ impl ToSql for Vec<u8> {
     to_sql_checked!();
 }
 
+impl<T, S> ToSql for HashSet<T, S>
+where
+    T: ToSql + Eq + Hash,
+    S: BuildHasher,
+{
+    fn to_sql(&self, ty: &Type, w: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        array_to_sql(ty, self.len(), self.iter(), w)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&[T] as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl<T: ToSql + Ord> ToSql for BTreeSet<T> {
+    fn to_sql(&self, ty: &Type, w: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        array_to_sql(ty, self.len(), self.iter(), w)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&[T] as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+// Serializes the elements as a one-dimensional array.
+fn array_to_sql<'a, T, I>(
+    ty: &Type,
+    len: usize,
+    elements: I,
+    w: &mut Vec<u8>,
+) -> Result<IsNull, Box<Error + Sync + Send>>
+where
+    T: ToSql + 'a,
+    I: Iterator<Item = &'a T>,
+{
+    let member_type = match *ty.kind() {
+        Kind::Array(ref member) => member,
+        _ => panic!("expected array type"),
+    };
+
+    let dimension = ArrayDimension {
+        len: downcast(len)?,
+        lower_bound: 1,
+    };
+
+    types::array_to_sql(
+        Some(dimension),
+        true,
+        member_type.oid(),
+        elements,
+        |e, w| match e.to_sql(member_type, w)? {
+            IsNull::No => Ok(postgres_protocol::IsNull::No),
+            IsNull::Yes => Ok(postgres_protocol::IsNull::Yes),
+        },
+        w,
+    )?;
+    Ok(IsNull::No)
+}
+
+fn check_array_len(array: &types::Array, len: usize) -> Result<(), Box<Error + Sync + Send>> {
+    let mut dimensions = array.dimensions();
+    let actual = match dimensions.next()? {
+        Some(dimension) => dimension.len,
+        None => 0,
+    };
+    if dimensions.next()?.is_some() {
+        return Err("array contains too many dimensions".into());
+    }
+
+    if actual < 0 || actual as usize != len {
+        return Err(format!("expected an array of length {} but got {}", len, actual).into());
+    }
+
+    Ok(())
+}
+
+macro_rules! array_impls {
+    ($($len:expr => ($($value:ident)*))+) => {
+        $(
+            impl<T: FromSql> FromSql for [T; $len] {
+                #[allow(unused_variables, unused_mut)]
+                fn from_sql(ty: &Type, raw: &[u8]) -> Result<[T; $len], Box<Error + Sync + Send>> {
+                    let member_type = match *ty.kind() {
+                        Kind::Array(ref member) => member,
+                        _ => panic!("expected array type"),
+                    };
+
+                    let array = types::array_from_sql(raw)?;
+                    check_array_len(&array, $len)?;
+
+                    let mut values = array.values();
+                    $(
+                        let $value = match values.next()? {
+                            Some(v) => T::from_sql_nullable(member_type, v)?,
+                            None => return Err("unexpected end of array".into()),
+                        };
+                    )*
+                    Ok([$($value),*])
+                }
+
+                fn accepts(ty: &Type) -> bool {
+                    <Vec<T> as FromSql>::accepts(ty)
+                }
+            }
+
+            impl<T: ToSql> ToSql for [T; $len] {
+                fn to_sql(
+                    &self,
+                    ty: &Type,
+                    w: &mut Vec<u8>,
+                ) -> Result<IsNull, Box<Error + Sync + Send>> {
+                    <&[T] as ToSql>::to_sql(&&self[..], ty, w)
+                }
+
+                fn accepts(ty: &Type) -> bool {
+                    <&[T] as ToSql>::accepts(ty)
+                }
+
+                to_sql_checked!();
+            }
+        )+
+    }
+}
+
+array_impls! {
+    0 => ()
+    1 => (a0)
+    2 => (a0 a1)
+    3 => (a0 a1 a2)
+    4 => (a0 a1 a2 a3)
+    5 => (a0 a1 a2 a3 a4)
+    6 => (a0 a1 a2 a3 a4 a5)
+    7 => (a0 a1 a2 a3 a4 a5 a6)
+    8 => (a0 a1 a2 a3 a4 a5 a6 a7)
+    9 => (a0 a1 a2 a3 a4 a5 a6 a7 a8)
+    10 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9)
+    11 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10)
+    12 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11)
+    13 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12)
+    14 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13)
+    15 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14)
+    16 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15)
+    17 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16)
+    18 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17)
+    19 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18)
+    20 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19)
+    21 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20)
+    22 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21)
+    23 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22)
+    24 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22 a23)
+    25 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22 a23 a24)
+    26 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22 a23 a24 a25)
+    27 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22 a23 a24 a25 a26)
+    28 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22 a23 a24 a25 a26 a27)
+    29 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22 a23 a24 a25 a26 a27 a28)
+    30 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22 a23 a24 a25 a26 a27 a28 a29)
+    31 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22 a23 a24 a25 a26 a27 a28 a29 a30)
+    32 => (a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15 a16 a17 a18 a19 a20 a21 a22 a23 a24 a25 a26 a27 a28 a29 a30 a31)
+}
+
 impl<'a> ToSql for &'a str {
     fn to_sql(&self, _: &Type, w: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
         types::text_to_sql(*self, w);
@@ -682,7 +977,21 @@ simple_to!(bool, bool_to_sql, BOOL);
 simple_to!(i8, char_to_sql, CHAR);
 simple_to!(i16, int2_to_sql, INT2);
 simple_to!(i32, int4_to_sql, INT4);
-simple_to!(u32, oid_to_sql, OID);
+simple_to!(
+    u32,
+    oid_to_sql,
+    OID,
+    REGPROC,
+    REGPROCEDURE,
+    REGOPER,
+    REGOPERATOR,
+    REGCLASS,
+    REGTYPE,
+    REGCONFIG,
+    REGDICTIONARY,
+    REGNAMESPACE,
+    REGROLE
+);
 simple_to!(i64, int8_to_sql, INT8);
 simple_to!(f32, float4_to_sql, FLOAT4);
 simple_to!(f64, float8_to_sql, FLOAT8);
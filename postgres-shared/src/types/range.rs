@@ -0,0 +1,132 @@
+use postgres_protocol;
+use postgres_protocol::types;
+use std::error::Error;
+
+use types::{FromSql, IsNull, Kind, ToSql, Type};
+
+/// A Postgres range, used with range types such as `Type::Int4Range` and
+/// `Type::TstzRange`, as well as custom range types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Range<T> {
+    /// An empty range.
+    Empty,
+    /// A nonempty range, specified by its lower and upper bounds.
+    Nonempty(RangeBound<T>, RangeBound<T>),
+}
+
+/// One side of a `Range`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeBound<T> {
+    /// An inclusive bound.
+    Inclusive(T),
+    /// An exclusive bound.
+    Exclusive(T),
+    /// No bound.
+    Unbounded,
+}
+
+// The subtype of a range may itself be a domain, whose values are sent in
+// the representation of the underlying type.
+fn subtype(ty: &Type) -> &Type {
+    let mut subtype = match *ty.kind() {
+        Kind::Range(ref subtype) => subtype,
+        _ => panic!("expected range type"),
+    };
+    while let Kind::Domain(ref base) = *subtype.kind() {
+        subtype = base;
+    }
+    subtype
+}
+
+fn accepts_range(ty: &Type, accepts_subtype: fn(&Type) -> bool) -> bool {
+    match *ty.kind() {
+        Kind::Range(_) => accepts_subtype(subtype(ty)),
+        _ => false,
+    }
+}
+
+impl<T: FromSql> FromSql for Range<T> {
+    fn from_sql(ty: &Type, raw: &[u8]) -> Result<Range<T>, Box<Error + Sync + Send>> {
+        let subtype = subtype(ty);
+
+        match types::range_from_sql(raw)? {
+            types::Range::Empty => Ok(Range::Empty),
+            types::Range::Nonempty(lower, upper) => {
+                let lower = bound_from_sql(subtype, lower)?;
+                let upper = bound_from_sql(subtype, upper)?;
+                Ok(Range::Nonempty(lower, upper))
+            }
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        accepts_range(ty, T::accepts)
+    }
+}
+
+fn bound_from_sql<T>(
+    ty: &Type,
+    bound: types::RangeBound<Option<&[u8]>>,
+) -> Result<RangeBound<T>, Box<Error + Sync + Send>>
+where
+    T: FromSql,
+{
+    match bound {
+        types::RangeBound::Inclusive(value) => {
+            T::from_sql_nullable(ty, value).map(RangeBound::Inclusive)
+        }
+        types::RangeBound::Exclusive(value) => {
+            T::from_sql_nullable(ty, value).map(RangeBound::Exclusive)
+        }
+        types::RangeBound::Unbounded => Ok(RangeBound::Unbounded),
+    }
+}
+
+impl<T: ToSql> ToSql for Range<T> {
+    fn to_sql(&self, ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        let subtype = subtype(ty);
+
+        match *self {
+            Range::Empty => types::empty_range_to_sql(out),
+            Range::Nonempty(ref lower, ref upper) => {
+                types::range_to_sql(
+                    |buf| bound_to_sql(subtype, lower, buf),
+                    |buf| bound_to_sql(subtype, upper, buf),
+                    out,
+                )?;
+            }
+        }
+
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        accepts_range(ty, T::accepts)
+    }
+
+    to_sql_checked!();
+}
+
+fn bound_to_sql<T>(
+    ty: &Type,
+    bound: &RangeBound<T>,
+    buf: &mut Vec<u8>,
+) -> Result<types::RangeBound<postgres_protocol::IsNull>, Box<Error + Sync + Send>>
+where
+    T: ToSql,
+{
+    let is_null = |r: IsNull| match r {
+        IsNull::Yes => postgres_protocol::IsNull::Yes,
+        IsNull::No => postgres_protocol::IsNull::No,
+    };
+
+    match *bound {
+        RangeBound::Inclusive(ref value) => {
+            Ok(types::RangeBound::Inclusive(is_null(value.to_sql(ty, buf)?)))
+        }
+        RangeBound::Exclusive(ref value) => {
+            Ok(types::RangeBound::Exclusive(is_null(value.to_sql(ty, buf)?)))
+        }
+        RangeBound::Unbounded => Ok(types::RangeBound::Unbounded),
+    }
+}
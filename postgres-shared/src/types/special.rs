@@ -1,8 +1,12 @@
 use postgres_protocol::types;
 use std::{i32, i64};
 use std::error::Error;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
 
-use types::{Type, FromSql, ToSql, IsNull, DATE, TIMESTAMP, TIMESTAMPTZ};
+use types::{Type, FromSql, ToSql, IsNull, CIDR, DATE, INET, INTERVAL, JSON, JSONB, MACADDR,
+            MACADDR8, PG_LSN, TIMESTAMP, TIMESTAMPTZ, TIMETZ, TXID_SNAPSHOT};
 
 /// A wrapper that can be used to represent infinity with `Type::Date` types.
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -97,3 +101,388 @@ impl<T: ToSql> ToSql for Timestamp<T> {
 
     to_sql_checked!();
 }
+
+/// A wrapper for `JSON` values, transferred as raw text.
+///
+/// The text is passed through as is, without being parsed, so this can be
+/// used to read and write JSON values without a dependency on a JSON library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Json(pub String);
+
+impl FromSql for Json {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        types::text_from_sql(raw).map(|j| Json(j.to_owned()))
+    }
+
+    accepts!(JSON);
+}
+
+impl ToSql for Json {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        types::text_to_sql(&self.0, out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(JSON);
+    to_sql_checked!();
+}
+
+/// A wrapper for `JSONB` values, transferred as raw text.
+///
+/// Like `Json`, the text is passed through without being parsed. Note that
+/// the server normalizes `JSONB` values, so the text read back may differ
+/// from the text written, for example in its whitespace and key order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jsonb(pub String);
+
+impl FromSql for Jsonb {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        // We only support version 1 of the jsonb binary format
+        match raw.split_first() {
+            Some((&1, json)) => types::text_from_sql(json).map(|j| Jsonb(j.to_owned())),
+            Some(_) => Err("unsupported JSONB encoding version".into()),
+            None => Err("invalid message length".into()),
+        }
+    }
+
+    accepts!(JSONB);
+}
+
+impl ToSql for Jsonb {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        out.push(1);
+        types::text_to_sql(&self.0, out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(JSONB);
+    to_sql_checked!();
+}
+
+/// A wrapper for `JSONPATH` values (Postgres 12+).
+///
+/// The path is transferred in its textual representation, e.g. `$.a.b`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(pub String);
+
+impl FromSql for JsonPath {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        // We only support version 1 of the jsonpath binary format
+        match raw.split_first() {
+            Some((&1, path)) => types::text_from_sql(path).map(|p| JsonPath(p.to_owned())),
+            Some(_) => Err("unsupported JSONPATH encoding version".into()),
+            None => Err("invalid message length".into()),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "jsonpath"
+    }
+}
+
+impl ToSql for JsonPath {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        out.push(1);
+        types::text_to_sql(&self.0, out);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "jsonpath"
+    }
+
+    to_sql_checked!();
+}
+
+/// A Postgres write-ahead log sequence number, used with `Type::PgLsn` types.
+///
+/// It is displayed and parsed in the `XXXXXXXX/XXXXXXXX` hexadecimal notation
+/// used by Postgres, e.g. `16/B374D848`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lsn(pub u64);
+
+impl fmt::Display for Lsn {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{:X}/{:X}", self.0 >> 32, self.0 & 0xffff_ffff)
+    }
+}
+
+impl FromStr for Lsn {
+    type Err = Box<Error + Sync + Send>;
+
+    fn from_str(s: &str) -> Result<Lsn, Box<Error + Sync + Send>> {
+        let mut parts = s.splitn(2, '/');
+        match (parts.next(), parts.next()) {
+            (Some(hi), Some(lo)) => {
+                let hi = u32::from_str_radix(hi, 16).map_err(|_| "invalid LSN")?;
+                let lo = u32::from_str_radix(lo, 16).map_err(|_| "invalid LSN")?;
+                Ok(Lsn((u64::from(hi) << 32) | u64::from(lo)))
+            }
+            _ => Err("invalid LSN".into()),
+        }
+    }
+}
+
+impl FromSql for Lsn {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        types::int8_from_sql(raw).map(|v| Lsn(v as u64))
+    }
+
+    accepts!(PG_LSN);
+}
+
+impl ToSql for Lsn {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        types::int8_to_sql(self.0 as i64, out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(PG_LSN);
+
+    to_sql_checked!();
+}
+
+/// A snapshot of the transactions visible at some point in time, used with
+/// `Type::TxidSnapshot` and `pg_snapshot` types.
+///
+/// It is displayed in the `xmin:xmax:xip_list` notation used by Postgres,
+/// e.g. `10:20:10,14,15`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Snapshot {
+    /// The earliest transaction ID that is still active.
+    pub xmin: u64,
+    /// The first as-yet-unassigned transaction ID.
+    pub xmax: u64,
+    /// The IDs of the transactions in progress, in ascending order.
+    pub xip: Vec<u64>,
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:{}:", self.xmin, self.xmax)?;
+        for (i, xid) in self.xip.iter().enumerate() {
+            if i > 0 {
+                fmt.write_str(",")?;
+            }
+            write!(fmt, "{}", xid)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromSql for Snapshot {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        let snapshot = types::snapshot_from_sql(raw)?;
+        Ok(Snapshot {
+            xmin: snapshot.xmin(),
+            xmax: snapshot.xmax(),
+            xip: snapshot.xip().collect(),
+        })
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == TXID_SNAPSHOT || ty.name() == "pg_snapshot"
+    }
+}
+
+impl ToSql for Snapshot {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        types::snapshot_to_sql(self.xmin, self.xmax, &self.xip, out)?;
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == TXID_SNAPSHOT || ty.name() == "pg_snapshot"
+    }
+
+    to_sql_checked!();
+}
+
+/// A network address along with its prefix length, used with `Type::Cidr`
+/// and `Type::Inet` types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    /// The IP address.
+    pub addr: IpAddr,
+    /// The number of leading bits of the address which make up the network
+    /// portion.
+    pub prefix: u8,
+}
+
+impl FromSql for Cidr {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        let inet = types::inet_from_sql(raw)?;
+        Ok(Cidr {
+            addr: inet.addr(),
+            prefix: inet.netmask(),
+        })
+    }
+
+    accepts!(CIDR, INET);
+}
+
+impl ToSql for Cidr {
+    fn to_sql(&self, ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        let max_prefix = match self.addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if self.prefix > max_prefix {
+            return Err("prefix length out of range".into());
+        }
+
+        types::inet_to_sql(self.addr, self.prefix, *ty == CIDR, out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(CIDR, INET);
+
+    to_sql_checked!();
+}
+
+/// A MAC address, used with `Type::Macaddr` types.
+///
+/// It is displayed in the `08:00:2b:01:02:03` notation used by Postgres.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt_mac(&self.0, fmt)
+    }
+}
+
+impl FromSql for MacAddr {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        types::macaddr_from_sql(raw).map(MacAddr)
+    }
+
+    accepts!(MACADDR);
+}
+
+impl ToSql for MacAddr {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        types::macaddr_to_sql(self.0, out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(MACADDR);
+
+    to_sql_checked!();
+}
+
+/// An EUI-64 MAC address, used with `Type::Macaddr8` types.
+///
+/// It is displayed in the `08:00:2b:01:02:03:04:05` notation used by Postgres.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddr8(pub [u8; 8]);
+
+impl fmt::Display for MacAddr8 {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt_mac(&self.0, fmt)
+    }
+}
+
+impl FromSql for MacAddr8 {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        types::macaddr8_from_sql(raw).map(MacAddr8)
+    }
+
+    accepts!(MACADDR8);
+}
+
+impl ToSql for MacAddr8 {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        types::macaddr8_to_sql(self.0, out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(MACADDR8);
+
+    to_sql_checked!();
+}
+
+fn fmt_mac(bytes: &[u8], fmt: &mut fmt::Formatter) -> fmt::Result {
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 {
+            fmt.write_str(":")?;
+        }
+        write!(fmt, "{:02x}", b)?;
+    }
+    Ok(())
+}
+
+/// A time of day with a time zone offset, used with `TIMETZ` types.
+///
+/// The offset is not a full time zone, so daylight saving time is not taken
+/// into account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TimeTz {
+    /// The number of microseconds since midnight.
+    pub time_micros: i64,
+    /// The offset from UTC in seconds, positive east of Greenwich.
+    pub zone_offset_secs: i32,
+}
+
+impl FromSql for TimeTz {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        let (time, zone) = types::timetz_from_sql(raw)?;
+        // the server counts offsets west of Greenwich as positive
+        Ok(TimeTz {
+            time_micros: time,
+            zone_offset_secs: zone.wrapping_neg(),
+        })
+    }
+
+    accepts!(TIMETZ);
+}
+
+impl ToSql for TimeTz {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        types::timetz_to_sql(self.time_micros, self.zone_offset_secs.wrapping_neg(), out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(TIMETZ);
+
+    to_sql_checked!();
+}
+
+/// A span of time, used with `Type::Interval` types.
+///
+/// Postgres stores the months, days and microseconds of an interval
+/// separately, since the lengths of months and days are not fixed. The
+/// components may have different signs, as in `'1 month -2 days'`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Interval {
+    /// The number of months.
+    pub months: i32,
+    /// The number of days, in addition to the months.
+    pub days: i32,
+    /// The number of microseconds, in addition to the months and days.
+    pub microseconds: i64,
+}
+
+impl FromSql for Interval {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<Self, Box<Error + Sync + Send>> {
+        let interval = types::interval_from_sql(raw)?;
+        Ok(Interval {
+            months: interval.months(),
+            days: interval.days(),
+            microseconds: interval.microseconds(),
+        })
+    }
+
+    accepts!(INTERVAL);
+}
+
+impl ToSql for Interval {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        types::interval_to_sql(self.microseconds, self.days, self.months, out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(INTERVAL);
+
+    to_sql_checked!();
+}
@@ -0,0 +1,367 @@
+use fallible_iterator::FallibleIterator;
+use postgres_protocol::types;
+use std::error::Error;
+
+use types::{FromSql, IsNull, ToSql, Type, TSQUERY, TS_VECTOR};
+
+const QI_VAL: u8 = 1;
+const QI_OPR: u8 = 2;
+
+const OP_NOT: u8 = 1;
+const OP_AND: u8 = 2;
+const OP_OR: u8 = 3;
+const OP_PHRASE: u8 = 4;
+
+// The largest distance accepted by the `<N>` operator.
+const MAX_DISTANCE: u16 = 16384;
+
+/// The weight of a `TsVector` lexeme position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TsWeight {
+    /// The `A` weight.
+    A,
+    /// The `B` weight.
+    B,
+    /// The `C` weight.
+    C,
+    /// The `D` weight, used by default.
+    D,
+}
+
+/// A position of a lexeme in a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TsPosition {
+    /// The position, starting from 1.
+    pub position: u16,
+    /// The weight of the position.
+    pub weight: TsWeight,
+}
+
+/// A lexeme of a `TsVector`, along with its positions, if any.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TsLexeme {
+    /// The lexeme.
+    pub text: String,
+    /// The positions at which the lexeme occurs.
+    pub positions: Vec<TsPosition>,
+}
+
+/// A Postgres full text search document, used with `Type::TsVector` types.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TsVector(pub Vec<TsLexeme>);
+
+impl FromSql for TsVector {
+    fn from_sql(_: &Type, raw: &[u8]) -> Result<TsVector, Box<Error + Sync + Send>> {
+        let lexemes = types::tsvector_from_sql(raw)?
+            .map(|lexeme| {
+                let positions = lexeme
+                    .positions()
+                    .map(|p| {
+                        TsPosition {
+                            position: p.position(),
+                            weight: match p.weight() {
+                                3 => TsWeight::A,
+                                2 => TsWeight::B,
+                                1 => TsWeight::C,
+                                _ => TsWeight::D,
+                            },
+                        }
+                    })
+                    .collect();
+
+                TsLexeme {
+                    text: lexeme.text().to_owned(),
+                    positions: positions,
+                }
+            })
+            .collect()?;
+
+        Ok(TsVector(lexemes))
+    }
+
+    accepts!(TS_VECTOR);
+}
+
+/// A Postgres full text search query, used with `Type::Tsquery` types.
+///
+/// The query is written in the syntax accepted by a `tsquery` literal, for
+/// example `fat & (rat | cat:A*) <-> !dog`. Lexemes are not normalized; use
+/// the `to_tsquery` family of functions on a `TEXT` parameter to do so.
+///
+/// Empty queries cannot be sent to the server in the binary format, and will
+/// produce an error.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TsQuery(pub String);
+
+impl ToSql for TsQuery {
+    fn to_sql(&self, _: &Type, out: &mut Vec<u8>) -> Result<IsNull, Box<Error + Sync + Send>> {
+        let mut parser = Parser {
+            s: &self.0,
+            pos: 0,
+        };
+        let node = parser.parse()?;
+
+        types::int4_to_sql(node.count() as i32, out);
+        node.write(out);
+
+        Ok(IsNull::No)
+    }
+
+    accepts!(TSQUERY);
+
+    to_sql_checked!();
+}
+
+enum Node {
+    Operand {
+        text: String,
+        weight: u8,
+        prefix: bool,
+    },
+    Not(Box<Node>),
+    Binary {
+        oper: u8,
+        distance: u16,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+}
+
+impl Node {
+    fn count(&self) -> usize {
+        match *self {
+            Node::Operand { .. } => 1,
+            Node::Not(ref node) => 1 + node.count(),
+            Node::Binary {
+                ref left,
+                ref right,
+                ..
+            } => 1 + left.count() + right.count(),
+        }
+    }
+
+    // Items are sent in prefix order, with the right operand of a binary
+    // operator preceding its left operand.
+    fn write(&self, out: &mut Vec<u8>) {
+        match *self {
+            Node::Operand {
+                ref text,
+                weight,
+                prefix,
+            } => {
+                out.push(QI_VAL);
+                out.push(weight);
+                out.push(prefix as u8);
+                out.extend_from_slice(text.as_bytes());
+                out.push(0);
+            }
+            Node::Not(ref node) => {
+                out.push(QI_OPR);
+                out.push(OP_NOT);
+                node.write(out);
+            }
+            Node::Binary {
+                oper,
+                distance,
+                ref left,
+                ref right,
+            } => {
+                out.push(QI_OPR);
+                out.push(oper);
+                if oper == OP_PHRASE {
+                    types::int2_to_sql(distance as i16, out);
+                }
+                right.write(out);
+                left.write(out);
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(&mut self) -> Result<Node, Box<Error + Sync + Send>> {
+        // The server is unable to receive an empty query in the binary format
+        if self.peek().is_none() {
+            return Err("empty tsquery".into());
+        }
+
+        let node = self.parse_or()?;
+        match self.peek() {
+            Some(c) => Err(format!("unexpected character `{}` in tsquery", c).into()),
+            None => Ok(node),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Node, Box<Error + Sync + Send>> {
+        let mut node = self.parse_and()?;
+        while self.eat('|') {
+            let right = self.parse_and()?;
+            node = binary(OP_OR, 0, node, right);
+        }
+        Ok(node)
+    }
+
+    fn parse_and(&mut self) -> Result<Node, Box<Error + Sync + Send>> {
+        let mut node = self.parse_phrase()?;
+        while self.eat('&') {
+            let right = self.parse_phrase()?;
+            node = binary(OP_AND, 0, node, right);
+        }
+        Ok(node)
+    }
+
+    fn parse_phrase(&mut self) -> Result<Node, Box<Error + Sync + Send>> {
+        let mut node = self.parse_not()?;
+        while let Some(distance) = self.parse_distance()? {
+            let right = self.parse_not()?;
+            node = binary(OP_PHRASE, distance, node, right);
+        }
+        Ok(node)
+    }
+
+    fn parse_distance(&mut self) -> Result<Option<u16>, Box<Error + Sync + Send>> {
+        if self.peek() != Some('<') {
+            return Ok(None);
+        }
+
+        let rest = &self.s[self.pos + 1..];
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => return Err("unterminated phrase operator in tsquery".into()),
+        };
+        let distance = match &rest[..end] {
+            "-" => 1,
+            distance => match distance.parse() {
+                Ok(distance) if distance <= MAX_DISTANCE => distance,
+                _ => return Err("invalid phrase operator distance in tsquery".into()),
+            },
+        };
+        self.pos += end + 2;
+
+        Ok(Some(distance))
+    }
+
+    fn parse_not(&mut self) -> Result<Node, Box<Error + Sync + Send>> {
+        if self.eat('!') {
+            let node = self.parse_not()?;
+            Ok(Node::Not(Box::new(node)))
+        } else if self.eat('(') {
+            let node = self.parse_or()?;
+            if !self.eat(')') {
+                return Err("expected `)` in tsquery".into());
+            }
+            Ok(node)
+        } else {
+            self.parse_operand()
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Node, Box<Error + Sync + Send>> {
+        let mut text = String::new();
+
+        if self.eat('\'') {
+            loop {
+                match self.next() {
+                    Some('\'') => {
+                        if self.s[self.pos..].starts_with('\'') {
+                            self.pos += 1;
+                            text.push('\'');
+                        } else {
+                            break;
+                        }
+                    }
+                    Some('\\') => match self.next() {
+                        Some(c) => text.push(c),
+                        None => return Err("unterminated lexeme in tsquery".into()),
+                    },
+                    Some(c) => text.push(c),
+                    None => return Err("unterminated lexeme in tsquery".into()),
+                }
+            }
+        } else {
+            while let Some(c) = self.s[self.pos..].chars().next() {
+                if c.is_whitespace() || "&|!()<:'".contains(c) {
+                    break;
+                }
+                self.pos += c.len_utf8();
+                if c == '\\' {
+                    match self.next() {
+                        Some(c) => text.push(c),
+                        None => return Err("unterminated lexeme in tsquery".into()),
+                    }
+                } else {
+                    text.push(c);
+                }
+            }
+        }
+
+        if text.is_empty() {
+            return Err("expected lexeme in tsquery".into());
+        }
+
+        let mut weight = 0;
+        let mut prefix = false;
+        if self.s[self.pos..].starts_with(':') {
+            self.pos += 1;
+            while let Some(c) = self.s[self.pos..].chars().next() {
+                match c {
+                    'a' | 'A' => weight |= 1 << 3,
+                    'b' | 'B' => weight |= 1 << 2,
+                    'c' | 'C' => weight |= 1 << 1,
+                    'd' | 'D' => weight |= 1,
+                    '*' => prefix = true,
+                    _ => break,
+                }
+                self.pos += 1;
+            }
+        }
+
+        Ok(Node::Operand {
+            text: text,
+            weight: weight,
+            prefix: prefix,
+        })
+    }
+
+    // Skips whitespace, returning the next character without consuming it.
+    fn peek(&mut self) -> Option<char> {
+        while let Some(c) = self.s[self.pos..].chars().next() {
+            if !c.is_whitespace() {
+                return Some(c);
+            }
+            self.pos += c.len_utf8();
+        }
+        None
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.s[self.pos..].chars().next();
+        if let Some(c) = c {
+            self.pos += c.len_utf8();
+        }
+        c
+    }
+}
+
+fn binary(oper: u8, distance: u16, left: Node, right: Node) -> Node {
+    Node::Binary {
+        oper: oper,
+        distance: distance,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
@@ -9,6 +9,7 @@ pub struct Other {
     pub oid: Oid,
     pub kind: Kind,
     pub schema: String,
+    pub binary_io: bool,
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
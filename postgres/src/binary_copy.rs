@@ -0,0 +1,332 @@
+//! Decoding of binary `COPY TO STDOUT` output.
+
+use fallible_iterator::FallibleIterator;
+use postgres_protocol::message::backend;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use {bad_response, err, error, Connection, Error, Result};
+use types::{FromSql, Type, WrongType};
+
+const HEADER_MAGIC: &'static [u8] = b"PGCOPY\n\xff\r\n\0";
+
+// the magic, followed by the flags and the length of the header extension area
+const HEADER_LEN: usize = 19;
+
+/// A fallible iterator over the decoded rows of a
+/// `COPY ... TO STDOUT (FORMAT binary)` statement.
+///
+/// Rows are read from the server and decoded as they are requested, so a
+/// large table can be exported without buffering all of it.
+///
+/// Returned by `Statement::copy_out_binary`.
+pub struct BinaryCopyRows<'a> {
+    conn: &'a Connection,
+    types: Arc<Vec<Type>>,
+    buf: Vec<u8>,
+    pos: usize,
+    header: bool,
+    trailer: bool,
+    done: bool,
+}
+
+impl<'a> fmt::Debug for BinaryCopyRows<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("BinaryCopyRows")
+            .field("types", &self.types)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<'a> Drop for BinaryCopyRows<'a> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.drain();
+        }
+    }
+}
+
+impl<'a> BinaryCopyRows<'a> {
+    pub(crate) fn new(conn: &'a Connection, types: &[Type]) -> BinaryCopyRows<'a> {
+        BinaryCopyRows {
+            conn: conn,
+            types: Arc::new(types.to_vec()),
+            buf: vec![],
+            pos: 0,
+            header: false,
+            trailer: false,
+            done: false,
+        }
+    }
+
+    /// Returns the types of the fields of each row.
+    pub fn types(&self) -> &[Type] {
+        &self.types
+    }
+
+    // Reads the rest of the output, stopping once the connection is ready.
+    fn drain(&mut self) -> Result<()> {
+        self.done = true;
+        self.buf.clear();
+        self.pos = 0;
+
+        let mut conn = self.conn.0.borrow_mut();
+        check_desync!(conn);
+        loop {
+            if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
+                return Ok(());
+            }
+        }
+    }
+
+    // Reads the next message of the output into the buffer.
+    fn read(&mut self) -> Result<()> {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+
+        let mut conn = self.conn.0.borrow_mut();
+        check_desync!(conn);
+        match conn.read_message()? {
+            backend::Message::CopyData(body) => self.buf.extend_from_slice(body.data()),
+            backend::Message::CopyDone => {}
+            backend::Message::CommandComplete(_) => {
+                self.done = true;
+                conn.wait_for_ready()?;
+            }
+            backend::Message::ErrorResponse(body) => {
+                self.done = true;
+                self.buf.clear();
+                conn.wait_for_ready()?;
+                return Err(err(&mut body.fields()));
+            }
+            _ => {
+                self.done = true;
+                self.buf.clear();
+                loop {
+                    if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
+                        return Err(bad_response().into());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Fails the iteration, discarding the rest of the output.
+    fn fail(&mut self, e: io::Error) -> Result<Option<BinaryCopyRow>> {
+        if !self.done {
+            self.drain()?;
+        }
+        Err(e.into())
+    }
+}
+
+impl<'a> FallibleIterator for BinaryCopyRows<'a> {
+    type Item = BinaryCopyRow;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<BinaryCopyRow>> {
+        loop {
+            // the header, or a row, may be split across any number of
+            // CopyData messages
+            if !self.header {
+                match parse_header(&self.buf[self.pos..]) {
+                    Ok(Some(len)) => {
+                        self.pos += len;
+                        self.header = true;
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => return self.fail(e),
+                }
+            } else if !self.trailer {
+                match parse_row(&self.buf[self.pos..], self.types.len()) {
+                    Ok(Parsed::Row(len, fields)) => {
+                        let data = self.buf[self.pos..self.pos + len].to_vec();
+                        self.pos += len;
+                        return Ok(Some(BinaryCopyRow {
+                            types: self.types.clone(),
+                            data: data,
+                            fields: fields,
+                        }));
+                    }
+                    Ok(Parsed::Trailer(len)) => {
+                        self.pos += len;
+                        self.trailer = true;
+                        continue;
+                    }
+                    Ok(Parsed::Incomplete) => {}
+                    Err(e) => return self.fail(e),
+                }
+            }
+
+            if self.done {
+                if !self.trailer {
+                    return self.fail(invalid("unexpected EOF"));
+                }
+                return Ok(None);
+            }
+            self.read()?;
+        }
+    }
+}
+
+/// A single row of binary `COPY` output.
+pub struct BinaryCopyRow {
+    types: Arc<Vec<Type>>,
+    data: Vec<u8>,
+    fields: Vec<Option<(usize, usize)>>,
+}
+
+impl fmt::Debug for BinaryCopyRow {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("BinaryCopyRow")
+            .field("types", &self.types)
+            .finish()
+    }
+}
+
+impl BinaryCopyRow {
+    /// Returns the number of fields in the row.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Determines if there are any fields in the row.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Retrieves the contents of a field of the row, decoded as the
+    /// corresponding type passed to `copy_out_binary`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds or if the value cannot be
+    /// converted to the specified type.
+    pub fn get<T>(&self, idx: usize) -> T
+    where
+        T: FromSql,
+    {
+        match self.get_opt(idx) {
+            Some(Ok(ok)) => ok,
+            Some(Err(err)) => panic!("error retrieving field {}: {:?}", idx, err),
+            None => panic!("no such field {}", idx),
+        }
+    }
+
+    /// Retrieves the contents of a field of the row.
+    ///
+    /// Returns `None` if the index is out of bounds, `Some(Err(..))` if there
+    /// was an error converting the value, and `Some(Ok(..))` on success.
+    pub fn get_opt<T>(&self, idx: usize) -> Option<Result<T>>
+    where
+        T: FromSql,
+    {
+        let ty = match self.types.get(idx) {
+            Some(ty) => ty,
+            None => return None,
+        };
+
+        if !<T as FromSql>::accepts(ty) {
+            return Some(Err(error::conversion(Box::new(WrongType::new(ty.clone())))));
+        }
+        let value = FromSql::from_sql_nullable(ty, self.get_bytes(idx));
+        Some(value.map_err(error::conversion))
+    }
+
+    /// Retrieves the specified field as a raw buffer of Postgres data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn get_bytes(&self, idx: usize) -> Option<&[u8]> {
+        self.fields[idx].map(|(start, end)| &self.data[start..end])
+    }
+}
+
+enum Parsed {
+    Incomplete,
+    Row(usize, Vec<Option<(usize, usize)>>),
+    Trailer(usize),
+}
+
+// Returns the length of the header if all of it is present.
+fn parse_header(buf: &[u8]) -> io::Result<Option<usize>> {
+    let magic_len = if buf.len() < HEADER_MAGIC.len() { buf.len() } else { HEADER_MAGIC.len() };
+    if buf[..magic_len] != HEADER_MAGIC[..magic_len] {
+        return Err(invalid("invalid binary COPY header"));
+    }
+    if buf.len() < HEADER_LEN {
+        return Ok(None);
+    }
+
+    let mut rest = &buf[HEADER_MAGIC.len() + 4..];
+    let extension_len = read_i32(&mut rest);
+    if extension_len < 0 {
+        return Err(invalid("invalid binary COPY header"));
+    }
+    if rest.len() < extension_len as usize {
+        return Ok(None);
+    }
+    Ok(Some(HEADER_LEN + extension_len as usize))
+}
+
+// Parses the row at the start of the buffer, recording the offsets of its
+// fields within it.
+fn parse_row(buf: &[u8], types: usize) -> io::Result<Parsed> {
+    let mut rest = buf;
+    if rest.len() < 2 {
+        return Ok(Parsed::Incomplete);
+    }
+    let field_count = read_i16(&mut rest);
+    if field_count == -1 {
+        return Ok(Parsed::Trailer(2));
+    }
+    if field_count < 0 || field_count as usize != types {
+        return Err(invalid(&format!(
+            "expected {} fields but got {}",
+            types,
+            field_count
+        )));
+    }
+
+    let mut fields = Vec::with_capacity(types);
+    for _ in 0..field_count {
+        if rest.len() < 4 {
+            return Ok(Parsed::Incomplete);
+        }
+        let len = read_i32(&mut rest);
+        if len < 0 {
+            fields.push(None);
+            continue;
+        }
+        let len = len as usize;
+        if rest.len() < len {
+            return Ok(Parsed::Incomplete);
+        }
+        let start = buf.len() - rest.len();
+        fields.push(Some((start, start + len)));
+        rest = &rest[len..];
+    }
+
+    Ok(Parsed::Row(buf.len() - rest.len(), fields))
+}
+
+fn read_i16(buf: &mut &[u8]) -> i16 {
+    let v = (i16::from(buf[0]) << 8) | i16::from(buf[1]);
+    *buf = &buf[2..];
+    v
+}
+
+fn read_i32(buf: &mut &[u8]) -> i32 {
+    let v = buf[..4].iter().fold(0, |v, b| (v << 8) | i32::from(*b));
+    *buf = &buf[4..];
+    v
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
@@ -0,0 +1,203 @@
+//! Large objects.
+//!
+//! Large objects store binary data which is too big to conveniently handle
+//! as a single `BYTEA` value, and can be read and written in pieces. They are
+//! accessed through the Postgres server-side `lo_*` functions, and must be
+//! used within a transaction.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use postgres::{Connection, TlsMode};
+//! use postgres::large_objects::{self, LargeObject, Mode};
+//! use std::io::{Read, Write};
+//!
+//! # let conn = Connection::connect("", TlsMode::None).unwrap();
+//! let trans = conn.transaction().unwrap();
+//! let oid = large_objects::create(&trans).unwrap();
+//! {
+//!     let mut lo = LargeObject::open(&trans, oid, Mode::ReadWrite).unwrap();
+//!     lo.write_all(b"hello world").unwrap();
+//! }
+//! let mut lo = LargeObject::open(&trans, oid, Mode::Read).unwrap();
+//! let mut buf = vec![];
+//! lo.read_to_end(&mut buf).unwrap();
+//! assert_eq!(buf, b"hello world");
+//! ```
+
+use std::cmp;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use Result;
+use transaction::Transaction;
+use types::Oid;
+
+const INV_WRITE: i32 = 0x0002_0000;
+const INV_READ: i32 = 0x0004_0000;
+
+const SEEK_SET: i32 = 0;
+const SEEK_CUR: i32 = 1;
+const SEEK_END: i32 = 2;
+
+// Bounds the size of the messages exchanged for a single read or write.
+const MAX_CHUNK: usize = 1024 * 1024;
+
+/// The mode in which a large object is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// The large object can only be read.
+    ///
+    /// Reads see the contents of the object as of the start of the
+    /// transaction's snapshot.
+    Read,
+    /// The large object can only be written.
+    Write,
+    /// The large object can be read and written.
+    ReadWrite,
+}
+
+impl Mode {
+    fn to_i32(&self) -> i32 {
+        match *self {
+            Mode::Read => INV_READ,
+            Mode::Write => INV_WRITE,
+            Mode::ReadWrite => INV_READ | INV_WRITE,
+        }
+    }
+}
+
+/// Creates a new, empty large object, returning its OID.
+pub fn create(trans: &Transaction) -> Result<Oid> {
+    let rows = trans.query("SELECT pg_catalog.lo_create(0)", &[])?;
+    Ok(rows.get(0).get(0))
+}
+
+/// Deletes a large object.
+pub fn unlink(trans: &Transaction, oid: Oid) -> Result<()> {
+    trans.execute("SELECT pg_catalog.lo_unlink($1)", &[&oid])?;
+    Ok(())
+}
+
+/// An open large object.
+///
+/// It implements `Read`, `Write` and `Seek`, each call of which results in a
+/// query, so it should usually be wrapped in a `BufReader` or `BufWriter` when
+/// performing many small operations.
+///
+/// The large object is closed when it is dropped, and can only be used for
+/// the lifetime of the transaction it was opened in.
+pub struct LargeObject<'a> {
+    trans: &'a Transaction<'a>,
+    oid: Oid,
+    fd: i32,
+    finished: bool,
+}
+
+impl<'a> fmt::Debug for LargeObject<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("LargeObject")
+            .field("oid", &self.oid)
+            .field("fd", &self.fd)
+            .finish()
+    }
+}
+
+impl<'a> Drop for LargeObject<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.finish_inner();
+        }
+    }
+}
+
+impl<'a> LargeObject<'a> {
+    /// Opens the large object with the specified OID.
+    pub fn open(trans: &'a Transaction<'a>, oid: Oid, mode: Mode) -> Result<LargeObject<'a>> {
+        let stmt = trans.prepare_cached("SELECT pg_catalog.lo_open($1, $2)")?;
+        let fd = stmt.query(&[&oid, &mode.to_i32()])?.get(0).get(0);
+        Ok(LargeObject {
+            trans: trans,
+            oid: oid,
+            fd: fd,
+            finished: false,
+        })
+    }
+
+    /// Returns the OID of the large object.
+    pub fn oid(&self) -> Oid {
+        self.oid
+    }
+
+    /// Truncates the large object to the specified length, or extends it with
+    /// zeroes if it is shorter.
+    pub fn truncate(&mut self, len: i64) -> Result<()> {
+        let stmt = self.trans.prepare_cached("SELECT pg_catalog.lo_truncate64($1, $2)")?;
+        stmt.execute(&[&self.fd, &len])?;
+        Ok(())
+    }
+
+    /// Consumes the `LargeObject`, closing it.
+    ///
+    /// Functionally identical to the `Drop` implementation of `LargeObject`
+    /// except that it returns any error to the caller.
+    pub fn finish(mut self) -> Result<()> {
+        self.finished = true;
+        self.finish_inner()
+    }
+
+    fn finish_inner(&mut self) -> Result<()> {
+        let stmt = self.trans.prepare_cached("SELECT pg_catalog.lo_close($1)")?;
+        stmt.execute(&[&self.fd])?;
+        Ok(())
+    }
+}
+
+impl<'a> Read for LargeObject<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = cmp::min(buf.len(), MAX_CHUNK) as i32;
+        let stmt = self.trans.prepare_cached("SELECT pg_catalog.loread($1, $2)")?;
+        let rows = stmt.query(&[&self.fd, &len])?;
+        let row = rows.get(0);
+        let data = row.get_bytes(0).unwrap_or(&[]);
+        buf[..data.len()].copy_from_slice(data);
+        Ok(data.len())
+    }
+}
+
+impl<'a> Write for LargeObject<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let buf = &buf[..cmp::min(buf.len(), MAX_CHUNK)];
+        let stmt = self.trans.prepare_cached("SELECT pg_catalog.lowrite($1, $2)")?;
+        let rows = stmt.query(&[&self.fd, &buf])?;
+        let written: i32 = rows.get(0).get(0);
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> Seek for LargeObject<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => {
+                if offset > i64::max_value() as u64 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "cannot seek past the maximum large object size",
+                    ));
+                }
+                (offset as i64, SEEK_SET)
+            }
+            SeekFrom::Current(offset) => (offset, SEEK_CUR),
+            SeekFrom::End(offset) => (offset, SEEK_END),
+        };
+
+        let stmt = self.trans.prepare_cached("SELECT pg_catalog.lo_lseek64($1, $2, $3)")?;
+        let rows = stmt.query(&[&self.fd, &offset, &whence])?;
+        let pos: i64 = rows.get(0).get(0);
+        Ok(pos as u64)
+    }
+}
@@ -81,28 +81,35 @@ extern crate socket2;
 
 use fallible_iterator::FallibleIterator;
 use std::cell::{Cell, RefCell};
+use std::cmp;
 use std::collections::{HashMap, VecDeque};
 use std::fmt;
-use std::io;
+use std::error::Error as StdError;
+use std::fs::File;
+use std::io::{self, Read};
 use std::mem;
+use std::path::Path;
 use std::result;
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 use postgres_protocol::authentication;
 use postgres_protocol::authentication::sasl::{self, ScramSha256};
 use postgres_protocol::message::backend::{self, ErrorFields};
 use postgres_protocol::message::frontend;
 use postgres_shared::rows::RowData;
 
-use error::{DbError, UNDEFINED_COLUMN, UNDEFINED_TABLE};
+use error::{DbError, Severity, ADMIN_SHUTDOWN, CRASH_SHUTDOWN, UNDEFINED_COLUMN, UNDEFINED_TABLE};
 use tls::TlsHandshake;
-use notification::{Notification, Notifications};
-use params::{IntoConnectParams, User};
+use notification::{Notification, Notifications, OverflowPolicy};
+use params::{ConnectParams, IntoConnectParams, User};
 use priv_io::MessageStream;
 use rows::Rows;
-use stmt::{Column, Statement};
-use transaction::{IsolationLevel, Transaction};
-use types::{Field, FromSql, IsNull, Kind, Oid, ToSql, Type, CHAR, NAME, OID};
+use stmt::{Column, CopyOptions, Format, ReadWithInfo, Statement};
+use transaction::{IsolationLevel, Transaction, TransactionStatus};
+use types::{Field, FromSql, IsNull, Kind, Oid, ToSql, Type, BOOL, CHAR, INTERVAL, NAME, OID,
+            OID_ARRAY, TIME, TIMESTAMP, TIMESTAMPTZ, TIMETZ};
 
 #[doc(inline)]
 pub use postgres_shared::CancelData;
@@ -116,17 +123,26 @@ mod macros;
 
 mod feature_check;
 mod priv_io;
+mod simple_query;
+pub mod binary_copy;
+pub mod large_objects;
 pub mod tls;
 pub mod notification;
 pub mod params;
+pub mod replication;
 pub mod rows;
 pub mod stmt;
 pub mod transaction;
 
 const TYPEINFO_QUERY: &'static str = "__typeinfo";
+const TYPEINFO_BATCH_QUERY: &'static str = "__typeinfo_batch";
 const TYPEINFO_ENUM_QUERY: &'static str = "__typeinfo_enum";
 const TYPEINFO_COMPOSITE_QUERY: &'static str = "__typeinfo_composite";
 
+// How often the cancellation flag of a query is checked while waiting for the
+// server.
+const CANCEL_POLL_INTERVAL_MS: u64 = 50;
+
 /// A type alias of the result returned by many methods.
 pub type Result<T> = result::Result<T, Error>;
 
@@ -156,6 +172,24 @@ impl HandleNotice for LoggingNoticeHandler {
     }
 }
 
+/// A trait implemented by types that can log the queries sent by a
+/// connection.
+///
+/// It is implemented for all `Send + FnMut(&str, &[&ToSql])` closures.
+pub trait LogQuery: Send {
+    /// Logs a query just before it is sent to the server.
+    ///
+    /// `params` is empty for queries executed with the simple query protocol,
+    /// such as those run by `batch_execute`.
+    fn log_query(&mut self, query: &str, params: &[&ToSql]);
+}
+
+impl<F: Send + FnMut(&str, &[&ToSql])> LogQuery for F {
+    fn log_query(&mut self, query: &str, params: &[&ToSql]) {
+        self(query, params)
+    }
+}
+
 /// Attempts to cancel an in-progress query.
 ///
 /// The backend provides no information about whether a cancellation attempt
@@ -186,7 +220,8 @@ where
     T: IntoConnectParams,
 {
     let params = params.into_connect_params().map_err(error::connect)?;
-    let mut socket = priv_io::initialize_stream(&params, tls)?;
+    let deadline = params.connect_timeout().map(|t| Instant::now() + t);
+    let mut socket = priv_io::initialize_stream(&params, tls, deadline)?;
 
     let mut buf = vec![];
     frontend::cancel_request(data.process_id, data.secret_key, &mut buf);
@@ -196,6 +231,80 @@ where
     Ok(())
 }
 
+/// Quotes a string for use as an identifier, such as a table or column name,
+/// in a SQL statement.
+///
+/// The string is wrapped in double quotes, and any double quotes it contains
+/// are doubled, matching libpq's `PQescapeIdentifier`. The resulting
+/// identifier is case sensitive.
+///
+/// Query parameters should be used in preference to this function wherever
+/// possible. It is intended for the cases where they cannot be, such as
+/// dynamically chosen table names.
+///
+/// Returns an error if the string contains a null character, which cannot be
+/// represented in an identifier.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(postgres::escape_identifier("my \"table\"").unwrap(), "\"my \"\"table\"\"\"");
+/// ```
+pub fn escape_identifier(s: &str) -> Result<String> {
+    check_no_nulls(s)?;
+    Ok(format!("\"{}\"", s.replace('"', "\"\"")))
+}
+
+/// Quotes a string for use as a string literal in a SQL statement.
+///
+/// The string is wrapped in single quotes, and any single quotes it contains
+/// are doubled, matching libpq's `PQescapeLiteral`. If the string contains
+/// backslashes, they are doubled and the literal is written in the escape
+/// string syntax (`E'...'`), preceded by a space, so that it is interpreted
+/// correctly regardless of the server's `standard_conforming_strings`
+/// setting.
+///
+/// Query parameters should be used in preference to this function wherever
+/// possible.
+///
+/// Returns an error if the string contains a null character, which cannot be
+/// represented in a string literal.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(postgres::escape_literal("it's").unwrap(), "'it''s'");
+/// assert_eq!(postgres::escape_literal("C:\\").unwrap(), " E'C:\\\\'");
+/// ```
+pub fn escape_literal(s: &str) -> Result<String> {
+    check_no_nulls(s)?;
+    let escaped = s.replace('\'', "''");
+    if s.contains('\\') {
+        Ok(format!(" E'{}'", escaped.replace('\\', "\\\\")))
+    } else {
+        Ok(format!("'{}'", escaped))
+    }
+}
+
+fn check_param_count(param_types: &[Type], params: usize) -> Result<()> {
+    if param_types.len() != params {
+        let msg = format!("expected {} parameters but got {}", param_types.len(), params);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into());
+    }
+
+    Ok(())
+}
+
+fn check_no_nulls(s: &str) -> Result<()> {
+    if s.contains('\0') {
+        return Err(
+            io::Error::new(io::ErrorKind::InvalidInput, "string contains a null character").into(),
+        );
+    }
+
+    Ok(())
+}
+
 fn bad_response() -> io::Error {
     io::Error::new(
         io::ErrorKind::InvalidInput,
@@ -203,6 +312,46 @@ fn bad_response() -> io::Error {
     )
 }
 
+fn password(user: &User) -> Result<&str> {
+    match user.password() {
+        Some(pass) => Ok(pass),
+        None if user.password_md5().is_some() => Err(error::connect(
+            "a password was requested but only its md5 hash was provided".into(),
+        )),
+        None => Err(error::connect("a password was requested but not provided".into())),
+    }
+}
+
+// Sent to the server to abort a COPY FROM STDIN, which includes it in the
+// error it responds with.
+const COPY_IN_MISUSE: &'static str = "COPY FROM STDIN statements must be executed with \
+                                      `Statement::copy_in`";
+
+fn copy_out_misuse() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "COPY TO STDOUT statements must be executed with `Statement::copy_out` or \
+         `Statement::copy_out_lines`",
+    )
+}
+
+fn quote_channel(channel: &str) -> Result<String> {
+    if channel.is_empty() {
+        return Err(
+            io::Error::new(io::ErrorKind::InvalidInput, "channel name cannot be empty").into(),
+        );
+    }
+
+    escape_identifier(channel)
+}
+
+fn read_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut query = String::new();
+    file.read_to_string(&mut query)?;
+    Ok(query)
+}
+
 fn desynchronized() -> io::Error {
     io::Error::new(
         io::ErrorKind::Other,
@@ -210,6 +359,25 @@ fn desynchronized() -> io::Error {
     )
 }
 
+fn terminated() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        "the connection was terminated by the server",
+    )
+}
+
+// The server sends a FATAL error with one of these codes before closing the
+// connection when it is shutting down or the backend has been terminated.
+fn is_termination(body: &backend::ErrorResponseBody) -> bool {
+    match DbError::new(&mut body.fields()) {
+        Ok(e) => {
+            e.parsed_severity == Some(Severity::Fatal) &&
+                (e.code == ADMIN_SHUTDOWN || e.code == CRASH_SHUTDOWN)
+        }
+        Err(_) => false,
+    }
+}
+
 /// Specifies the TLS support requested for a new connection.
 #[derive(Debug)]
 pub enum TlsMode<'a> {
@@ -221,26 +389,151 @@ pub enum TlsMode<'a> {
     Require(&'a TlsHandshake),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct StatementInfo {
     name: String,
+    query: String,
     param_types: Vec<Type>,
     columns: Vec<Column>,
+    result_formats: Vec<Format>,
+    // set for the results of simple queries, whose text values of some
+    // builtin types can be read as those types
+    decode_text: bool,
+    // the server's integer_datetimes setting when the statement was prepared
+    integer_datetimes: bool,
+    // set for statements owned by the connection's statement cache
+    cached: bool,
+}
+
+impl StatementInfo {
+    fn new(
+        name: String,
+        query: String,
+        param_types: Vec<Type>,
+        columns: Vec<Column>,
+        integer_datetimes: bool,
+    ) -> StatementInfo {
+        StatementInfo {
+            name: name,
+            query: query,
+            param_types: param_types,
+            result_formats: columns.iter().map(|c| default_format(c.type_())).collect(),
+            columns: columns,
+            decode_text: false,
+            integer_datetimes: integer_datetimes,
+            cached: false,
+        }
+    }
+}
+
+// Types which have no binary representation must be requested in the text
+// format.
+fn default_format(ty: &Type) -> Format {
+    if ty.has_binary_io() {
+        Format::Binary
+    } else {
+        Format::Text
+    }
+}
+
+// Returns an error if a value of the type is converted to or from its binary
+// representation while the server stores date and time values as floating
+// point, which the conversions do not support.
+fn check_datetimes(
+    ty: &Type,
+    integer_datetimes: bool,
+) -> result::Result<(), Box<StdError + Sync + Send>> {
+    if integer_datetimes || !has_datetime(ty) {
+        return Ok(());
+    }
+
+    let msg = format!(
+        "values of type `{}` cannot be converted since the server stores date and time \
+         values as floating point (integer_datetimes is off)",
+        ty.name()
+    );
+    Err(msg.into())
+}
+
+// Determines if the binary representation of the type contains a time,
+// timestamp or interval, which depends on the server's integer_datetimes
+// setting.
+fn has_datetime(ty: &Type) -> bool {
+    match *ty {
+        TIME | TIMETZ | TIMESTAMP | TIMESTAMPTZ | INTERVAL => return true,
+        _ => {}
+    }
+
+    match *ty.kind() {
+        Kind::Array(ref ty) | Kind::Range(ref ty) | Kind::Domain(ref ty) => has_datetime(ty),
+        Kind::Composite(ref fields) => fields.iter().any(|f| has_datetime(f.type_())),
+        _ => false,
+    }
+}
+
+// Fails the connection if the server could not provide the requested protocol
+// version or any of the requested protocol extensions, which are the startup
+// options prefixed with `_pq_.`.
+fn check_protocol_negotiation(
+    body: &backend::NegotiateProtocolVersionBody,
+    minor_version: u16,
+) -> Result<()> {
+    let options = body.options().collect::<Vec<_>>()?;
+    // Postgres sends its entire protocol version number rather than only the
+    // minor version, so the major version is masked off.
+    let newest_minor_version = body.newest_minor_version() & 0xffff;
+    let version_ok = newest_minor_version >= i32::from(minor_version);
+    if version_ok && options.is_empty() {
+        return Ok(());
+    }
+
+    let mut problems = vec![];
+    if !version_ok {
+        problems.push(format!(
+            "the requested protocol version; the newest minor version it supports is {}",
+            newest_minor_version
+        ));
+    }
+    if !options.is_empty() {
+        problems.push(format!(
+            "the requested protocol extensions {}",
+            options.join(", ")
+        ));
+    }
+    let msg = format!("the server does not support {}", problems.join(", or "));
+    Err(error::connect(msg.into()))
 }
 
+// Used to check that an `OwnedStatement` is bound to the connection that
+// prepared it.
+static NEXT_CONNECTION_ID: AtomicUsize = AtomicUsize::new(0);
+
 struct InnerConnection {
+    id: usize,
     stream: MessageStream,
     notice_handler: Box<HandleNotice>,
+    query_logger: Option<Box<LogQuery>>,
     notifications: VecDeque<Notification>,
+    notification_limit: Option<usize>,
+    notification_overflow: OverflowPolicy,
+    notifications_dropped: bool,
     cancel_data: CancelData,
+    cancel_params: ConnectParams,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    cancel_sent: bool,
     unknown_types: HashMap<Oid, Type>,
     cached_statements: HashMap<String, Arc<StatementInfo>>,
     parameters: HashMap<String, String>,
     next_stmt_id: u32,
     trans_depth: u32,
+    transaction_status: TransactionStatus,
     desynchronized: bool,
+    terminated: bool,
     finished: bool,
+    skip_type_introspection: bool,
+    prefer_simple_query: bool,
     has_typeinfo_query: bool,
+    has_typeinfo_batch_query: bool,
     has_typeinfo_enum_query: bool,
     has_typeinfo_composite_query: bool,
 }
@@ -254,12 +547,15 @@ impl Drop for InnerConnection {
 }
 
 impl InnerConnection {
-    fn connect<T>(params: T, tls: TlsMode) -> Result<InnerConnection>
+    fn connect<T>(params: T, tls: TlsMode, replication: Option<&str>) -> Result<InnerConnection>
     where
         T: IntoConnectParams,
     {
         let params = params.into_connect_params().map_err(error::connect)?;
-        let stream = priv_io::initialize_stream(&params, tls)?;
+        // The connect timeout bounds the entire handshake, not just the TCP
+        // connection.
+        let deadline = params.connect_timeout().map(|t| Instant::now() + t);
+        let stream = priv_io::initialize_stream(&params, tls, deadline)?;
 
         let user = match params.user() {
             Some(user) => user,
@@ -270,22 +566,39 @@ impl InnerConnection {
             }
         };
 
+        let mut stream = MessageStream::new(stream);
+        stream.set_deadline(deadline)?;
+        stream.set_max_message_size(params.max_message_size());
+
         let mut conn = InnerConnection {
-            stream: MessageStream::new(stream),
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            stream: stream,
             next_stmt_id: 0,
             notice_handler: Box::new(LoggingNoticeHandler),
+            query_logger: None,
             notifications: VecDeque::new(),
+            notification_limit: None,
+            notification_overflow: OverflowPolicy::DropOldest,
+            notifications_dropped: false,
             cancel_data: CancelData {
                 process_id: 0,
                 secret_key: 0,
             },
+            cancel_params: params.clone(),
+            cancel_flag: None,
+            cancel_sent: false,
             unknown_types: HashMap::new(),
             cached_statements: HashMap::new(),
             parameters: HashMap::new(),
             desynchronized: false,
+            terminated: false,
             finished: false,
             trans_depth: 0,
+            transaction_status: TransactionStatus::Idle,
+            skip_type_introspection: params.skip_type_introspection(),
+            prefer_simple_query: params.prefer_simple_query(),
             has_typeinfo_query: false,
+            has_typeinfo_batch_query: false,
             has_typeinfo_enum_query: false,
             has_typeinfo_composite_query: false,
         };
@@ -300,13 +613,19 @@ impl InnerConnection {
         if let Some(database) = params.database() {
             options.push(("database".to_owned(), database.to_owned()));
         }
+        if let Some(replication) = replication {
+            options.push(("replication".to_owned(), replication.to_owned()));
+        }
 
         let options = options.iter().map(|&(ref a, ref b)| (&**a, &**b));
-        conn.stream
-            .write_message(|buf| frontend::startup_message(options, buf))?;
+        let (major, minor) = params.protocol_version();
+        let version = (major as i32) << 16 | minor as i32;
+        conn.stream.write_message(|buf| {
+            frontend::startup_message_with_version(version, options, buf)
+        })?;
         conn.stream.flush()?;
 
-        conn.handle_auth(user)?;
+        conn.handle_auth(user, minor)?;
 
         loop {
             match conn.read_message()? {
@@ -322,21 +641,64 @@ impl InnerConnection {
             }
         }
 
+        conn.stream.set_deadline(None)?;
+
         Ok(conn)
     }
 
+    // Reads a message from the stream, sending a cancellation request to the
+    // server if the cancellation flag is set while waiting for it.
+    fn read_stream_message(&mut self) -> io::Result<backend::Message> {
+        // the stream's read timeout is replaced by the poll interval while
+        // waiting, so it has to be enforced here instead
+        let start = Instant::now();
+        loop {
+            let set = match self.cancel_flag {
+                Some(ref flag) if !self.cancel_sent => flag.load(Ordering::SeqCst),
+                _ => return self.stream.read_message(),
+            };
+            if set {
+                self.cancel_sent = true;
+                let params = self.cancel_params.clone();
+                if let Err(_e) = cancel_query(params, TlsMode::None, &self.cancel_data) {
+                    warn!("error sending cancellation request: {}", _e);
+                }
+                continue;
+            }
+
+            let mut timeout = Duration::from_millis(CANCEL_POLL_INTERVAL_MS);
+            if let Some(read_timeout) = self.stream.default_read_timeout() {
+                let elapsed = start.elapsed();
+                if elapsed >= read_timeout {
+                    return Err(priv_io::timed_out());
+                }
+                timeout = cmp::min(timeout, read_timeout - elapsed);
+            }
+            if let Some(message) = self.stream.read_message_timeout(timeout)? {
+                return Ok(message);
+            }
+        }
+    }
+
     fn read_message_with_notification(&mut self) -> io::Result<backend::Message> {
         debug_assert!(!self.desynchronized);
         loop {
-            match try_desync!(self, self.stream.read_message()) {
+            match try_desync!(self, self.read_stream_message()) {
                 backend::Message::NoticeResponse(body) => {
                     if let Ok(err) = DbError::new(&mut body.fields()) {
                         self.notice_handler.handle_notice(err);
                     }
                 }
                 backend::Message::ParameterStatus(body) => {
-                    self.parameters
-                        .insert(body.name()?.to_owned(), body.value()?.to_owned());
+                    self.set_parameter(&body)?;
+                }
+                backend::Message::ErrorResponse(body) => {
+                    self.check_termination(&body);
+                    return Ok(backend::Message::ErrorResponse(body));
+                }
+                backend::Message::ReadyForQuery(body) => {
+                    self.transaction_status = TransactionStatus::from_u8(body.status());
+                    return Ok(backend::Message::ReadyForQuery(body));
                 }
                 val => return Ok(val),
             }
@@ -356,8 +718,11 @@ impl InnerConnection {
                     }
                 }
                 Some(backend::Message::ParameterStatus(body)) => {
-                    self.parameters
-                        .insert(body.name()?.to_owned(), body.value()?.to_owned());
+                    self.set_parameter(&body)?;
+                }
+                Some(backend::Message::ErrorResponse(body)) => {
+                    self.check_termination(&body);
+                    return Ok(Some(backend::Message::ErrorResponse(body)));
                 }
                 val => return Ok(val),
             }
@@ -376,19 +741,52 @@ impl InnerConnection {
                     }
                 }
                 Some(backend::Message::ParameterStatus(body)) => {
-                    self.parameters
-                        .insert(body.name()?.to_owned(), body.value()?.to_owned());
+                    self.set_parameter(&body)?;
+                }
+                Some(backend::Message::ErrorResponse(body)) => {
+                    self.check_termination(&body);
+                    return Ok(Some(backend::Message::ErrorResponse(body)));
                 }
                 val => return Ok(val),
             }
         }
     }
 
+    fn set_parameter(&mut self, body: &backend::ParameterStatusBody) -> io::Result<()> {
+        let name = body.name()?;
+        let value = body.value()?;
+
+        // The connection sets TimeZone to GMT on startup, which the text
+        // representation of some values relies on.
+        if name == "TimeZone" && value != "GMT" &&
+            self.parameters.get(name).map_or(false, |v| v != value)
+        {
+            let notice = error::warning(format!(
+                "the TimeZone parameter was changed from GMT to {}, which changes \
+                 the text representation of TIMESTAMP WITH TIME ZONE values",
+                value
+            ));
+            self.notice_handler.handle_notice(notice);
+        }
+
+        self.parameters.insert(name.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    fn check_termination(&mut self, body: &backend::ErrorResponseBody) {
+        if is_termination(body) {
+            // The server closes the socket after sending the error, so there
+            // is nothing left to read.
+            self.terminated = true;
+            self.desynchronized = true;
+        }
+    }
+
     fn read_message(&mut self) -> io::Result<backend::Message> {
         loop {
             match self.read_message_with_notification()? {
                 backend::Message::NotificationResponse(body) => {
-                    self.notifications.push_back(Notification {
+                    self.push_notification(Notification {
                         process_id: body.process_id(),
                         channel: body.channel()?.to_owned(),
                         payload: body.message()?.to_owned(),
@@ -399,23 +797,52 @@ impl InnerConnection {
         }
     }
 
-    fn handle_auth(&mut self, user: &User) -> Result<()> {
-        match self.read_message()? {
+    fn push_notification(&mut self, notification: Notification) {
+        if let Some(limit) = self.notification_limit {
+            if self.notifications.len() >= limit {
+                self.notifications_dropped = true;
+                match self.notification_overflow {
+                    OverflowPolicy::DropOldest if limit > 0 => {
+                        self.notifications.pop_front();
+                    }
+                    _ => return,
+                }
+            }
+        }
+
+        self.notifications.push_back(notification);
+    }
+
+    fn handle_auth(&mut self, user: &User, minor_version: u16) -> Result<()> {
+        // The server reports the parts of the startup message it did not
+        // support before continuing with authentication as normal.
+        let message = match self.read_message()? {
+            backend::Message::NegotiateProtocolVersion(body) => {
+                check_protocol_negotiation(&body, minor_version)?;
+                self.read_message()?
+            }
+            message => message,
+        };
+
+        match message {
             backend::Message::AuthenticationOk => return Ok(()),
             backend::Message::AuthenticationCleartextPassword => {
-                let pass = user.password().ok_or_else(|| {
-                    error::connect("a password was requested but not provided".into())
-                })?;
+                let pass = password(user)?;
                 self.stream
                     .write_message(|buf| frontend::password_message(pass, buf))?;
                 self.stream.flush()?;
             }
             backend::Message::AuthenticationMd5Password(body) => {
-                let pass = user.password().ok_or_else(|| {
-                    error::connect("a password was requested but not provided".into())
-                })?;
-                let output =
-                    authentication::md5_hash(user.name().as_bytes(), pass.as_bytes(), body.salt());
+                let output = match user.password_md5() {
+                    Some(hash) if user.password().is_none() => {
+                        authentication::md5_hash_prehashed(hash, body.salt())
+                            .map_err(|e| error::connect(Box::new(e)))?
+                    }
+                    _ => {
+                        let pass = password(user)?;
+                        authentication::md5_hash(user.name().as_bytes(), pass.as_bytes(), body.salt())
+                    }
+                };
                 self.stream
                     .write_message(|buf| frontend::password_message(&output, buf))?;
                 self.stream.flush()?;
@@ -431,9 +858,7 @@ impl InnerConnection {
                     );
                 }
 
-                let pass = user.password().ok_or_else(|| {
-                    error::connect("a password was requested but not provided".into())
-                })?;
+                let pass = password(user)?;
 
                 let mut scram = ScramSha256::new(pass.as_bytes())?;
 
@@ -485,7 +910,18 @@ impl InnerConnection {
         mem::replace(&mut self.notice_handler, handler)
     }
 
+    fn set_query_logger(&mut self, logger: Option<Box<LogQuery>>) -> Option<Box<LogQuery>> {
+        mem::replace(&mut self.query_logger, logger)
+    }
+
+    fn log_query(&mut self, query: &str, params: &[&ToSql]) {
+        if let Some(ref mut logger) = self.query_logger {
+            logger.log_query(query, params);
+        }
+    }
+
     fn raw_prepare(&mut self, stmt_name: &str, query: &str) -> Result<(Vec<Type>, Vec<Column>)> {
+        check_desync!(self);
         debug!("preparing query with name `{}`: {}", stmt_name, query);
 
         self.stream
@@ -505,8 +941,36 @@ impl InnerConnection {
             _ => bad_response!(self),
         }
 
+        self.read_statement_description()
+    }
+
+    fn raw_describe(&mut self, stmt_name: &str) -> Result<(Vec<Type>, Vec<Column>)> {
+        debug!("describing statement with name `{}`", stmt_name);
+
+        self.stream
+            .write_message(|buf| frontend::describe(b'S', stmt_name, buf))?;
+        self.stream
+            .write_message(|buf| Ok::<(), io::Error>(frontend::sync(buf)))?;
+        self.stream.flush()?;
+
+        self.read_statement_description()
+    }
+
+    fn read_statement_description(&mut self) -> Result<(Vec<Type>, Vec<Column>)> {
+        let (raw_param_types, raw_columns) = self.read_raw_statement_description()?;
+        self.wait_for_ready()?;
+        self.resolve_statement_description(raw_param_types, raw_columns)
+    }
+
+    fn read_raw_statement_description(
+        &mut self,
+    ) -> Result<(backend::ParameterDescriptionBody, Option<backend::RowDescriptionBody>)> {
         let raw_param_types = match self.read_message()? {
             backend::Message::ParameterDescription(body) => body,
+            backend::Message::ErrorResponse(body) => {
+                self.wait_for_ready()?;
+                return Err(err(&mut body.fields()));
+            }
             _ => bad_response!(self),
         };
 
@@ -516,27 +980,49 @@ impl InnerConnection {
             _ => bad_response!(self),
         };
 
-        self.wait_for_ready()?;
+        Ok((raw_param_types, raw_columns))
+    }
 
+    fn resolve_statement_description(
+        &mut self,
+        raw_param_types: backend::ParameterDescriptionBody,
+        raw_columns: Option<backend::RowDescriptionBody>,
+    ) -> Result<(Vec<Type>, Vec<Column>)> {
         let param_types = raw_param_types
             .parameters()
             .map_err(Into::into)
             .and_then(|oid| self.get_type(oid))
             .collect()?;
+        let columns = self.resolve_columns(raw_columns)?;
+        Ok((param_types, columns))
+    }
 
+    fn integer_datetimes(&self) -> bool {
+        self.parameters
+            .get("integer_datetimes")
+            .map_or(true, |v| v == "on")
+    }
+
+    fn resolve_columns(
+        &mut self,
+        raw_columns: Option<backend::RowDescriptionBody>,
+    ) -> Result<Vec<Column>> {
         let columns = match raw_columns {
             Some(body) => body.fields()
                 .and_then(|field| {
                     Ok(Column::new(
                         field.name().to_owned(),
                         self.get_type(field.type_oid())?,
+                        field.table_oid(),
+                        field.column_id(),
+                        field.type_modifier(),
                     ))
                 })
                 .collect()?,
             None => vec![],
         };
 
-        Ok((param_types, columns))
+        Ok(columns)
     }
 
     fn read_rows<F>(&mut self, mut consumer: F) -> Result<bool>
@@ -546,10 +1032,14 @@ impl InnerConnection {
         let more_rows;
         loop {
             match self.read_message()? {
-                backend::Message::EmptyQueryResponse | backend::Message::CommandComplete(_) => {
+                backend::Message::CommandComplete(_) => {
                     more_rows = false;
                     break;
                 }
+                backend::Message::EmptyQueryResponse => {
+                    self.wait_for_ready()?;
+                    return Err(error::empty_query().into());
+                }
                 backend::Message::PortalSuspended => {
                     more_rows = true;
                     break;
@@ -561,7 +1051,7 @@ impl InnerConnection {
                 }
                 backend::Message::CopyInResponse(_) => {
                     self.stream.write_message(|buf| {
-                        frontend::copy_fail("COPY queries cannot be directly executed", buf)
+                        frontend::copy_fail(COPY_IN_MISUSE, buf)
                     })?;
                     self.stream
                         .write_message(|buf| Ok::<(), io::Error>(frontend::sync(buf)))?;
@@ -573,13 +1063,7 @@ impl InnerConnection {
                             break;
                         }
                     }
-                    return Err(
-                        io::Error::new(
-                            io::ErrorKind::InvalidInput,
-                            "COPY queries cannot be directly \
-                             executed",
-                        ).into(),
-                    );
+                    return Err(copy_out_misuse().into());
                 }
                 _ => {
                     self.desynchronized = true;
@@ -598,6 +1082,7 @@ impl InnerConnection {
         row_limit: i32,
         param_types: &[Type],
         params: &[&ToSql],
+        result_formats: &[Format],
     ) -> Result<()> {
         assert!(
             param_types.len() == params.len(),
@@ -612,6 +1097,7 @@ impl InnerConnection {
         );
 
         {
+            let integer_datetimes = self.integer_datetimes();
             let r = self.stream.write_message(|buf| {
                 frontend::bind(
                     portal_name,
@@ -620,10 +1106,13 @@ impl InnerConnection {
                     params.iter().zip(param_types),
                     |(param, ty), buf| match param.to_sql_checked(ty, buf) {
                         Ok(IsNull::Yes) => Ok(postgres_protocol::IsNull::Yes),
-                        Ok(IsNull::No) => Ok(postgres_protocol::IsNull::No),
+                        Ok(IsNull::No) => {
+                            check_datetimes(ty, integer_datetimes)?;
+                            Ok(postgres_protocol::IsNull::No)
+                        }
                         Err(e) => Err(e),
                     },
-                    Some(1),
+                    result_formats.iter().map(|f| f.to_i16()),
                     buf,
                 )
             });
@@ -664,14 +1153,83 @@ impl InnerConnection {
     fn prepare<'a>(&mut self, query: &str, conn: &'a Connection) -> Result<Statement<'a>> {
         let stmt_name = self.make_stmt_name();
         let (param_types, columns) = self.raw_prepare(&stmt_name, query)?;
-        let info = Arc::new(StatementInfo {
-            name: stmt_name,
-            param_types: param_types,
-            columns: columns,
-        });
+        let info = Arc::new(StatementInfo::new(
+            stmt_name,
+            query.to_owned(),
+            param_types,
+            columns,
+            self.integer_datetimes(),
+        ));
         Ok(Statement::new(conn, info, Cell::new(0), false))
     }
 
+    fn prepare_all<'a>(
+        &mut self,
+        queries: &[&str],
+        conn: &'a Connection,
+    ) -> Result<Vec<Statement<'a>>> {
+        check_desync!(self);
+
+        let stmt_names = queries
+            .iter()
+            .map(|_| self.make_stmt_name())
+            .collect::<Vec<_>>();
+        for (stmt_name, query) in stmt_names.iter().zip(queries) {
+            debug!("preparing query with name `{}`: {}", stmt_name, query);
+            self.stream
+                .write_message(|buf| frontend::parse(stmt_name, query, None, buf))?;
+            self.stream
+                .write_message(|buf| frontend::describe(b'S', stmt_name, buf))?;
+        }
+        self.stream
+            .write_message(|buf| Ok::<(), io::Error>(frontend::sync(buf)))?;
+        self.stream.flush()?;
+
+        let mut raw_descriptions = Vec::with_capacity(queries.len());
+        for i in 0..queries.len() {
+            let description = match self.read_message()? {
+                backend::Message::ParseComplete => self.read_raw_statement_description(),
+                backend::Message::ErrorResponse(body) => {
+                    self.wait_for_ready()?;
+                    Err(err(&mut body.fields()))
+                }
+                _ => bad_response!(self),
+            };
+
+            match description {
+                Ok(description) => raw_descriptions.push(description),
+                Err(e) => {
+                    // the statements before the failed one were created
+                    if !self.is_desynchronized() {
+                        for stmt_name in &stmt_names[..i] {
+                            let _ = self.close_statement(stmt_name, b'S');
+                        }
+                    }
+                    return Err(error::prepare(i, e));
+                }
+            }
+        }
+        self.wait_for_ready()?;
+
+        let mut stmts = Vec::with_capacity(queries.len());
+        for ((stmt_name, query), (raw_param_types, raw_columns)) in
+            stmt_names.into_iter().zip(queries).zip(raw_descriptions)
+        {
+            let (param_types, columns) =
+                self.resolve_statement_description(raw_param_types, raw_columns)?;
+            let info = Arc::new(StatementInfo::new(
+                stmt_name,
+                (*query).to_owned(),
+                param_types,
+                columns,
+                self.integer_datetimes(),
+            ));
+            stmts.push(Statement::new(conn, info, Cell::new(0), false));
+        }
+
+        Ok(stmts)
+    }
+
     fn prepare_cached<'a>(&mut self, query: &str, conn: &'a Connection) -> Result<Statement<'a>> {
         let info = self.cached_statements.get(query).cloned();
 
@@ -680,11 +1238,15 @@ impl InnerConnection {
             None => {
                 let stmt_name = self.make_stmt_name();
                 let (param_types, columns) = self.raw_prepare(&stmt_name, query)?;
-                let info = Arc::new(StatementInfo {
-                    name: stmt_name,
-                    param_types: param_types,
-                    columns: columns,
-                });
+                let mut info = StatementInfo::new(
+                    stmt_name,
+                    query.to_owned(),
+                    param_types,
+                    columns,
+                    self.integer_datetimes(),
+                );
+                info.cached = true;
+                let info = Arc::new(info);
                 self.cached_statements
                     .insert(query.to_owned(), info.clone());
                 info
@@ -731,7 +1293,8 @@ impl InnerConnection {
         match self.raw_prepare(
             TYPEINFO_QUERY,
             "SELECT t.typname, t.typtype, t.typelem, r.rngsubtype, \
-             t.typbasetype, n.nspname, t.typrelid \
+             t.typbasetype, n.nspname, t.typrelid, \
+             t.typsend::oid <> 0 AND t.typreceive::oid <> 0 \
              FROM pg_catalog.pg_type t \
              LEFT OUTER JOIN pg_catalog.pg_range r ON \
              r.rngtypid = t.oid \
@@ -745,7 +1308,8 @@ impl InnerConnection {
                 self.raw_prepare(
                     TYPEINFO_QUERY,
                     "SELECT t.typname, t.typtype, t.typelem, NULL::OID, \
-                     t.typbasetype, n.nspname, t.typrelid \
+                     t.typbasetype, n.nspname, t.typrelid, \
+                     t.typsend::oid <> 0 AND t.typreceive::oid <> 0 \
                      FROM pg_catalog.pg_type t \
                      INNER JOIN pg_catalog.pg_namespace n \
                      ON t.typnamespace = n.oid \
@@ -759,16 +1323,104 @@ impl InnerConnection {
         Ok(())
     }
 
-    #[allow(if_not_else)]
+    fn preload_types(&mut self, oids: &[Oid]) -> Result<()> {
+        if self.skip_type_introspection {
+            return Ok(());
+        }
+
+        let mut oids = oids
+            .iter()
+            .cloned()
+            .filter(|oid| Type::from_oid(*oid).is_none() && !self.unknown_types.contains_key(oid))
+            .collect::<Vec<_>>();
+        oids.sort();
+        oids.dedup();
+        if oids.is_empty() {
+            return Ok(());
+        }
+
+        self.setup_typeinfo_batch_query()?;
+        self.raw_execute(
+            TYPEINFO_BATCH_QUERY,
+            "",
+            0,
+            &[OID_ARRAY],
+            &[&oids],
+            &[Format::Binary],
+        )?;
+        let mut rows = vec![];
+        self.read_rows(|row| rows.push(row))?;
+
+        for row in rows {
+            let oid = Oid::from_sql_nullable(&OID, row.get(8)).map_err(error::conversion)?;
+            // an earlier type in the batch may have pulled this one in as a dependency
+            if self.unknown_types.contains_key(&oid) {
+                continue;
+            }
+            let ty = self.type_from_row(oid, Some(&row))?;
+            self.unknown_types.insert(oid, ty);
+        }
+
+        Ok(())
+    }
+
+    fn setup_typeinfo_batch_query(&mut self) -> Result<()> {
+        if self.has_typeinfo_batch_query {
+            return Ok(());
+        }
+
+        match self.raw_prepare(
+            TYPEINFO_BATCH_QUERY,
+            "SELECT t.typname, t.typtype, t.typelem, r.rngsubtype, \
+             t.typbasetype, n.nspname, t.typrelid, \
+             t.typsend::oid <> 0 AND t.typreceive::oid <> 0, t.oid \
+             FROM pg_catalog.pg_type t \
+             LEFT OUTER JOIN pg_catalog.pg_range r ON \
+             r.rngtypid = t.oid \
+             INNER JOIN pg_catalog.pg_namespace n ON \
+             t.typnamespace = n.oid \
+             WHERE t.oid = ANY($1)",
+        ) {
+            Ok(..) => {}
+            // Range types weren't added until Postgres 9.2, so pg_range may not exist
+            Err(ref e) if e.code() == Some(&UNDEFINED_TABLE) => {
+                self.raw_prepare(
+                    TYPEINFO_BATCH_QUERY,
+                    "SELECT t.typname, t.typtype, t.typelem, NULL::OID, \
+                     t.typbasetype, n.nspname, t.typrelid, \
+                     t.typsend::oid <> 0 AND t.typreceive::oid <> 0, t.oid \
+                     FROM pg_catalog.pg_type t \
+                     INNER JOIN pg_catalog.pg_namespace n \
+                     ON t.typnamespace = n.oid \
+                     WHERE t.oid = ANY($1)",
+                )?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        self.has_typeinfo_batch_query = true;
+        Ok(())
+    }
+
     fn read_type(&mut self, oid: Oid) -> Result<Type> {
+        if self.skip_type_introspection {
+            // named the same way the server displays a regtype which has no
+            // corresponding type
+            return Ok(Type::_new(oid.to_string(), oid, Kind::Simple, String::new(), true));
+        }
+
         self.setup_typeinfo_query()?;
-        self.raw_execute(TYPEINFO_QUERY, "", 0, &[OID], &[&oid])?;
+        self.raw_execute(TYPEINFO_QUERY, "", 0, &[OID], &[&oid], &[Format::Binary])?;
         let mut row = None;
         self.read_rows(|r| row = Some(r))?;
+        self.type_from_row(oid, row.as_ref())
+    }
 
-        let get_raw = |i: usize| row.as_ref().and_then(|r| r.get(i));
+    #[allow(if_not_else)]
+    fn type_from_row(&mut self, oid: Oid, row: Option<&RowData>) -> Result<Type> {
+        let get_raw = |i: usize| row.and_then(|r| r.get(i));
 
-        let (name, type_, elem_oid, rngsubtype, basetype, schema, relid) = {
+        let (name, type_, elem_oid, rngsubtype, basetype, schema, relid, binary_io) = {
             let name = String::from_sql_nullable(&NAME, get_raw(0)).map_err(error::conversion)?;
             let type_ = i8::from_sql_nullable(&CHAR, get_raw(1)).map_err(error::conversion)?;
             let elem_oid = Oid::from_sql_nullable(&OID, get_raw(2)).map_err(error::conversion)?;
@@ -777,7 +1429,8 @@ impl InnerConnection {
             let basetype = Oid::from_sql_nullable(&OID, get_raw(4)).map_err(error::conversion)?;
             let schema = String::from_sql_nullable(&NAME, get_raw(5)).map_err(error::conversion)?;
             let relid = Oid::from_sql_nullable(&OID, get_raw(6)).map_err(error::conversion)?;
-            (name, type_, elem_oid, rngsubtype, basetype, schema, relid)
+            let binary_io = bool::from_sql_nullable(&BOOL, get_raw(7)).map_err(error::conversion)?;
+            (name, type_, elem_oid, rngsubtype, basetype, schema, relid, binary_io)
         };
 
         let kind = if type_ == b'e' as i8 {
@@ -797,7 +1450,15 @@ impl InnerConnection {
             }
         };
 
-        Ok(Type::_new(name, oid, kind, schema))
+        // the binary format of a container is built from that of its elements
+        let binary_io = binary_io &&
+            match kind {
+                Kind::Array(ref t) | Kind::Range(ref t) => t.has_binary_io(),
+                Kind::Composite(ref fields) => fields.iter().all(|f| f.type_().has_binary_io()),
+                _ => true,
+            };
+
+        Ok(Type::_new(name, oid, kind, schema, binary_io))
     }
 
     fn setup_typeinfo_enum_query(&mut self) -> Result<()> {
@@ -832,7 +1493,7 @@ impl InnerConnection {
 
     fn read_enum_variants(&mut self, oid: Oid) -> Result<Vec<String>> {
         self.setup_typeinfo_enum_query()?;
-        self.raw_execute(TYPEINFO_ENUM_QUERY, "", 0, &[OID], &[&oid])?;
+        self.raw_execute(TYPEINFO_ENUM_QUERY, "", 0, &[OID], &[&oid], &[Format::Binary])?;
         let mut rows = vec![];
         self.read_rows(|row| rows.push(row))?;
 
@@ -866,7 +1527,14 @@ impl InnerConnection {
 
     fn read_composite_fields(&mut self, relid: Oid) -> Result<Vec<Field>> {
         self.setup_typeinfo_composite_query()?;
-        self.raw_execute(TYPEINFO_COMPOSITE_QUERY, "", 0, &[OID], &[&relid])?;
+        self.raw_execute(
+            TYPEINFO_COMPOSITE_QUERY,
+            "",
+            0,
+            &[OID],
+            &[&relid],
+            &[Format::Binary],
+        )?;
         let mut rows = vec![];
         self.read_rows(|row| rows.push(row))?;
 
@@ -888,8 +1556,16 @@ impl InnerConnection {
         self.desynchronized
     }
 
+    fn was_terminated_by_server(&self) -> bool {
+        self.terminated
+    }
+
     #[allow(needless_return)]
     fn wait_for_ready(&mut self) -> Result<()> {
+        if self.terminated {
+            return Ok(());
+        }
+
         match self.read_message()? {
             backend::Message::ReadyForQuery(_) => Ok(()),
             _ => bad_response!(self),
@@ -899,6 +1575,7 @@ impl InnerConnection {
     fn quick_query(&mut self, query: &str) -> Result<Vec<Vec<Option<String>>>> {
         check_desync!(self);
         debug!("executing query: {}", query);
+        self.log_query(query, &[]);
         self.stream
             .write_message(|buf| frontend::query(query, buf))?;
         self.stream.flush()?;
@@ -917,7 +1594,7 @@ impl InnerConnection {
                 }
                 backend::Message::CopyInResponse(_) => {
                     self.stream.write_message(|buf| {
-                        frontend::copy_fail("COPY queries cannot be directly executed", buf)
+                        frontend::copy_fail(COPY_IN_MISUSE, buf)
                     })?;
                     self.stream
                         .write_message(|buf| Ok::<(), io::Error>(frontend::sync(buf)))?;
@@ -933,6 +1610,40 @@ impl InnerConnection {
         Ok(result)
     }
 
+    // Like quick_query, but discards the rows and reports the 1-based position
+    // of the statement which failed.
+    fn batch_execute(&mut self, query: &str) -> Result<()> {
+        check_desync!(self);
+        debug!("executing batch: {}", query);
+        self.log_query(query, &[]);
+        self.stream
+            .write_message(|buf| frontend::query(query, buf))?;
+        self.stream.flush()?;
+
+        // each statement which runs to completion sends a CommandComplete
+        let mut completed = 0;
+        loop {
+            match self.read_message()? {
+                backend::Message::ReadyForQuery(_) => break,
+                backend::Message::CommandComplete(_) => completed += 1,
+                backend::Message::CopyInResponse(_) => {
+                    self.stream.write_message(|buf| {
+                        frontend::copy_fail(COPY_IN_MISUSE, buf)
+                    })?;
+                    self.stream
+                        .write_message(|buf| Ok::<(), io::Error>(frontend::sync(buf)))?;
+                    self.stream.flush()?;
+                }
+                backend::Message::ErrorResponse(body) => {
+                    self.wait_for_ready()?;
+                    return Err(error::batch(completed + 1, err(&mut body.fields())));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
     fn finish_inner(&mut self) -> Result<()> {
         check_desync!(self);
         self.stream
@@ -945,6 +1656,7 @@ impl InnerConnection {
 fn _ensure_send() {
     fn _is_send<T: Send>() {}
     _is_send::<Connection>();
+    _is_send::<stmt::OwnedStatement>();
 }
 
 /// A connection to a Postgres database.
@@ -959,6 +1671,7 @@ impl fmt::Debug for Connection {
             .field("notifications", &conn.notifications.len())
             .field("transaction_depth", &conn.trans_depth)
             .field("desynchronized", &conn.desynchronized)
+            .field("terminated", &conn.terminated)
             .field("cached_statements", &conn.cached_statements.len())
             .finish()
     }
@@ -984,6 +1697,18 @@ impl Connection {
     /// created manually and passed in. Note that Postgres does not support TLS
     /// over Unix sockets.
     ///
+    /// As with libpq, a `service` parameter, either in the URL or as an option
+    /// of a `ConnectParams`, names a section of a connection service file from
+    /// which the host, port, user, password, database and connect timeout are
+    /// read. Settings in the URL or `ConnectParams` take precedence over those
+    /// in the service file. Of the other settings, the `application_name`,
+    /// `fallback_application_name`, `options` and `client_encoding` runtime
+    /// parameters are passed on to the server, other libpq connection
+    /// keywords such as `sslmode` are ignored, and anything else is an error.
+    /// The file is located via the `PGSERVICEFILE` environment variable,
+    /// defaulting to `~/.pg_service.conf`, followed by `pg_service.conf` in the
+    /// `PGSYSCONFDIR` directory.
+    ///
     /// # Examples
     ///
     /// To connect over TCP:
@@ -1024,7 +1749,7 @@ impl Connection {
     where
         T: IntoConnectParams,
     {
-        InnerConnection::connect(params, tls).map(|conn| Connection(RefCell::new(conn)))
+        InnerConnection::connect(params, tls, None).map(|conn| Connection(RefCell::new(conn)))
     }
 
     /// Executes a statement, returning the number of rows modified.
@@ -1033,6 +1758,8 @@ impl Connection {
     /// index of the parameter in the list provided, 1-indexed.
     ///
     /// If the statement does not modify any rows (e.g. SELECT), 0 is returned.
+    /// An error is returned if the query is empty or consists only of
+    /// comments.
     ///
     /// If the same statement will be repeatedly executed (perhaps with
     /// different query parameters), consider using the `prepare` and
@@ -1055,12 +1782,17 @@ impl Connection {
     /// println!("{} rows updated", rows_updated);
     /// ```
     pub fn execute(&self, query: &str, params: &[&ToSql]) -> Result<u64> {
+        if self.0.borrow().prefer_simple_query {
+            return simple_query::execute(self, query, params, false);
+        }
         let (param_types, columns) = self.0.borrow_mut().raw_prepare("", query)?;
-        let info = Arc::new(StatementInfo {
-            name: String::new(),
-            param_types: param_types,
-            columns: columns,
-        });
+        let info = Arc::new(StatementInfo::new(
+            String::new(),
+            query.to_owned(),
+            param_types,
+            columns,
+            self.integer_datetimes(),
+        ));
         let stmt = Statement::new(self, info, Cell::new(0), true);
         stmt.execute(params)
     }
@@ -1070,6 +1802,8 @@ impl Connection {
     /// A statement may contain parameters, specified by `$n` where `n` is the
     /// index of the parameter in the list provided, 1-indexed.
     ///
+    /// As with `execute`, an error is returned if the query is empty.
+    ///
     /// If the same statement will be repeatedly executed (perhaps with
     /// different query parameters), consider using the `prepare` and
     /// `prepare_cached` methods.
@@ -1091,16 +1825,181 @@ impl Connection {
     /// }
     /// ```
     pub fn query(&self, query: &str, params: &[&ToSql]) -> Result<Rows> {
+        if self.0.borrow().prefer_simple_query {
+            return simple_query::query(self, query, params, false);
+        }
         let (param_types, columns) = self.0.borrow_mut().raw_prepare("", query)?;
-        let info = Arc::new(StatementInfo {
-            name: String::new(),
-            param_types: param_types,
-            columns: columns,
-        });
+        let info = Arc::new(StatementInfo::new(
+            String::new(),
+            query.to_owned(),
+            param_types,
+            columns,
+            self.integer_datetimes(),
+        ));
         let stmt = Statement::new(self, info, Cell::new(0), true);
         stmt.into_query(params)
     }
 
+    /// Like `query`, but cancels the query if `cancel` is set while waiting
+    /// for the server's response.
+    ///
+    /// See `Statement::query_cancellable` for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// use std::sync::Arc;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::thread;
+    ///
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let cancel = Arc::new(AtomicBool::new(false));
+    /// let cancel2 = cancel.clone();
+    /// thread::spawn(move || cancel2.store(true, Ordering::SeqCst));
+    /// let result = conn.query_cancellable("SELECT pg_sleep(10)", &[], &cancel);
+    /// ```
+    pub fn query_cancellable(
+        &self,
+        query: &str,
+        params: &[&ToSql],
+        cancel: &Arc<AtomicBool>,
+    ) -> Result<Rows> {
+        if self.0.borrow().prefer_simple_query {
+            return self.cancellable(cancel, || simple_query::query(self, query, params, false));
+        }
+        let (param_types, columns) = self.0.borrow_mut().raw_prepare("", query)?;
+        let info = Arc::new(StatementInfo::new(
+            String::new(),
+            query.to_owned(),
+            param_types,
+            columns,
+            self.integer_datetimes(),
+        ));
+        let stmt = Statement::new(self, info, Cell::new(0), true);
+        stmt.query_cancellable(params, cancel)
+    }
+
+    /// Executes a query which returns a single value, such as
+    /// `SELECT count(*) FROM foo`, returning that value.
+    ///
+    /// An error is returned if the query does not return exactly one row with
+    /// exactly one column, or if the value cannot be converted to `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let count: i64 = conn.query_scalar("SELECT count(*) FROM foo", &[]).unwrap();
+    /// ```
+    pub fn query_scalar<T>(&self, query: &str, params: &[&ToSql]) -> Result<T>
+    where
+        T: FromSql,
+    {
+        self.query(query, params).and_then(|rows| scalar(&rows))
+    }
+
+    /// Like `execute`, but returns an error rather than panicking if the
+    /// number of parameters provided does not match the number expected.
+    pub fn try_execute(&self, query: &str, params: &[&ToSql]) -> Result<u64> {
+        if self.0.borrow().prefer_simple_query {
+            return simple_query::execute(self, query, params, true);
+        }
+        let (param_types, columns) = self.0.borrow_mut().raw_prepare("", query)?;
+        check_param_count(&param_types, params.len())?;
+        let info = Arc::new(StatementInfo::new(
+            String::new(),
+            query.to_owned(),
+            param_types,
+            columns,
+            self.integer_datetimes(),
+        ));
+        let stmt = Statement::new(self, info, Cell::new(0), true);
+        stmt.execute(params)
+    }
+
+    /// Like `query`, but returns an error rather than panicking if the
+    /// number of parameters provided does not match the number expected.
+    pub fn try_query(&self, query: &str, params: &[&ToSql]) -> Result<Rows> {
+        if self.0.borrow().prefer_simple_query {
+            return simple_query::query(self, query, params, true);
+        }
+        let (param_types, columns) = self.0.borrow_mut().raw_prepare("", query)?;
+        check_param_count(&param_types, params.len())?;
+        let info = Arc::new(StatementInfo::new(
+            String::new(),
+            query.to_owned(),
+            param_types,
+            columns,
+            self.integer_datetimes(),
+        ));
+        let stmt = Statement::new(self, info, Cell::new(0), true);
+        stmt.into_query(params)
+    }
+
+    /// Like `execute`, but takes an iterator of parameters rather than a
+    /// slice.
+    ///
+    /// This is useful when the parameter list is built dynamically, such as
+    /// from a `Vec<Box<ToSql>>`, a `Vec<Box<ToSql + Sync>>` or an iterator of
+    /// `&ToSql`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// # use postgres::types::ToSql;
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let mut params: Vec<Box<ToSql>> = vec![Box::new(1i32)];
+    /// params.push(Box::new("hello".to_owned()));
+    /// conn.execute_raw("UPDATE foo SET bar = $2 WHERE id = $1", &params).unwrap();
+    /// ```
+    pub fn execute_raw<'p, I>(&self, query: &str, params: I) -> Result<u64>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<ToSql + 'p>,
+    {
+        let params = params.into_iter().collect::<Vec<_>>();
+        let params = params.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        self.execute(query, &params)
+    }
+
+    /// Like `query`, but takes an iterator of parameters rather than a slice.
+    ///
+    /// This is useful when the parameter list is built dynamically, such as
+    /// from a `Vec<Box<ToSql>>`, a `Vec<Box<ToSql + Sync>>` or an iterator of
+    /// `&ToSql`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    pub fn query_raw<'p, I>(&self, query: &str, params: I) -> Result<Rows>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<ToSql + 'p>,
+    {
+        let params = params.into_iter().collect::<Vec<_>>();
+        let params = params.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        self.query(query, &params)
+    }
+
     /// Begins a new transaction.
     ///
     /// Returns a `Transaction` object which should be used instead of
@@ -1145,6 +2044,95 @@ impl Connection {
         Ok(Transaction::new(self, 1))
     }
 
+    /// Runs a closure inside of a new transaction, retrying it if the
+    /// transaction fails due to a conflict with a concurrent transaction.
+    ///
+    /// The transaction is committed if the closure returns `Ok`. If the
+    /// closure or the commit fails with an error for which `is_retryable`
+    /// returns true, the transaction is rolled back and the closure is run
+    /// again in a fresh transaction after a short delay, up to a total of
+    /// `max_attempts` times. Any other error is returned immediately.
+    ///
+    /// The closure may call `Transaction::set_config` before running any
+    /// queries to change the isolation level of the transaction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_attempts` is 0, or if there is an active transaction.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// # use postgres::transaction::{Config, IsolationLevel};
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let balance = conn.transaction_retry(5, |trans| {
+    ///     trans.set_config(Config::new().isolation_level(IsolationLevel::Serializable))?;
+    ///     trans.execute("UPDATE account SET balance = balance - 10 WHERE id = 1", &[])?;
+    ///     let rows = trans.query("SELECT balance FROM account WHERE id = 1", &[])?;
+    ///     Ok(rows.get(0).get::<_, i64>(0))
+    /// }).unwrap();
+    /// ```
+    pub fn transaction_retry<F, T>(&self, max_attempts: u32, mut f: F) -> Result<T>
+    where
+        F: FnMut(&Transaction) -> Result<T>,
+    {
+        assert!(max_attempts > 0, "`max_attempts` must be at least 1");
+        let mut attempt = 1;
+        loop {
+            let trans = self.transaction()?;
+            // dropping the uncommitted transaction rolls it back
+            let r = f(&trans).and_then(|v| trans.commit().map(|()| v));
+            match r {
+                Err(ref e) if e.is_retryable() && attempt < max_attempts => {
+                    debug!("retrying transaction after error: {}", e);
+                    thread::sleep(Duration::from_millis(10 * u64::from(attempt)));
+                    attempt += 1;
+                }
+                r => return r,
+            }
+        }
+    }
+
+    /// Executes a `COPY FROM STDIN` statement which loads the contents of
+    /// the provided reader into `target`, returning the number of rows added.
+    ///
+    /// `target` is the table name, optionally followed by a list of columns,
+    /// and is inserted into the statement verbatim. The `WITH` clause of the
+    /// statement is generated from `options`, and values such as the NULL
+    /// string are escaped. Invalid options are reported before the statement
+    /// is sent to the server.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// use postgres::stmt::{CopyFormat, CopyOptions};
+    ///
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let mut options = CopyOptions::new();
+    /// options.format(CopyFormat::Csv).delimiter(";").null("NULL");
+    /// conn.copy_in("people (id, name)", &options, &mut "1;john\n2;NULL\n".as_bytes()).unwrap();
+    /// ```
+    pub fn copy_in<R: ReadWithInfo>(
+        &self,
+        target: &str,
+        options: &CopyOptions,
+        r: &mut R,
+    ) -> Result<u64> {
+        let query = options.copy_in_query(target)?;
+        let (param_types, columns) = self.0.borrow_mut().raw_prepare("", &query)?;
+        let info = Arc::new(StatementInfo::new(
+            String::new(),
+            query,
+            param_types,
+            columns,
+            self.integer_datetimes(),
+        ));
+        let stmt = Statement::new(self, info, Cell::new(0), true);
+        stmt.copy_in(&[], r)
+    }
+
     /// Creates a new prepared statement.
     ///
     /// If the same statement will be executed repeatedly, explicitly preparing
@@ -1166,10 +2154,50 @@ impl Connection {
     ///     stmt.execute(&[bar, baz]).unwrap();
     /// }
     /// ```
+    ///
+    /// # Parameter types
+    ///
+    /// The type of each parameter is inferred by the server from the context
+    /// it is used in. Where the context is ambiguous, such as the operand of
+    /// an overloaded operator, the parameter can be given an explicit cast,
+    /// which the statement's `param_types` will then report. Note that the
+    /// right operand of the JSONB `?` operator is `TEXT`, while that of `@>`
+    /// is `JSONB`:
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let stmt = conn.prepare("SELECT data FROM foo WHERE data @> $1::JSONB OR data ? $2::TEXT")
+    ///     .unwrap();
+    /// ```
     pub fn prepare<'a>(&'a self, query: &str) -> Result<Statement<'a>> {
         self.0.borrow_mut().prepare(query, self)
     }
 
+    /// Creates new prepared statements for each of several queries.
+    ///
+    /// Like `prepare`, except that all of the queries are sent to the backend
+    /// at once, taking a single round trip rather than one per query.
+    ///
+    /// If any query fails to prepare, none of the statements are created, and
+    /// the 0-based index of the failing query in `queries` is available from
+    /// the error's `prepare_index` method.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let stmts = conn.prepare_all(&[
+    ///     "SELECT bar FROM foo WHERE baz = $1",
+    ///     "UPDATE foo SET bar = $1 WHERE baz = $2",
+    /// ]).unwrap();
+    /// let (select, update) = (&stmts[0], &stmts[1]);
+    /// ```
+    pub fn prepare_all<'a>(&'a self, queries: &[&str]) -> Result<Vec<Statement<'a>>> {
+        self.0.borrow_mut().prepare_all(queries, self)
+    }
+
     /// Creates a cached prepared statement.
     ///
     /// Like `prepare`, except that the statement is only prepared once over
@@ -1194,6 +2222,83 @@ impl Connection {
         self.0.borrow_mut().prepare_cached(query, self)
     }
 
+    /// Returns the queries of the statements cached by `prepare_cached`, in
+    /// no particular order.
+    pub fn cached_statement_queries(&self) -> Vec<String> {
+        self.0.borrow().cached_statements.keys().cloned().collect()
+    }
+
+    /// Returns the number of statements cached by `prepare_cached`.
+    pub fn cached_statement_count(&self) -> usize {
+        self.0.borrow().cached_statements.len()
+    }
+
+    /// Loads and caches the definitions of the specified types.
+    ///
+    /// Information about types not built into Postgres is normally looked up
+    /// one at a time as they are encountered while preparing statements. This
+    /// fetches the definitions of all of the specified types in a single
+    /// query, avoiding those round trips later on. Types which are built in
+    /// or have already been loaded are skipped.
+    ///
+    /// Types referenced by the loaded types, such as the element type of an
+    /// array or the fields of a composite, are still looked up individually
+    /// if they are not already known.
+    pub fn preload_types(&self, oids: &[Oid]) -> Result<()> {
+        let mut conn = self.0.borrow_mut();
+        check_desync!(conn);
+        conn.preload_types(oids)
+    }
+
+    /// Clears the cache of type definitions looked up from the server.
+    ///
+    /// Types not built into Postgres are looked up in the catalog the first
+    /// time they are encountered and cached for the lifetime of the
+    /// connection. If types are altered or recreated, for example by a schema
+    /// migration, the cached definitions may be stale; after this is called,
+    /// they will be looked up again as they are next encountered.
+    ///
+    /// Statements which have already been prepared, including those cached
+    /// by `prepare_cached`, retain the definitions they were prepared with.
+    pub fn clear_type_cache(&self) {
+        self.0.borrow_mut().unknown_types.clear();
+    }
+
+    /// Creates a `Statement` for a statement which has already been prepared
+    /// on the server under the specified name.
+    ///
+    /// This can be used with statements prepared with the SQL `PREPARE`
+    /// command, for example. The statement is described rather than parsed
+    /// again, and an error is returned if no statement with the name exists.
+    ///
+    /// The statement is not owned by the returned `Statement`, so it is not
+    /// deallocated when the `Statement` is dropped or finished.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// conn.batch_execute("PREPARE get_name AS SELECT name FROM people WHERE id = $1").unwrap();
+    /// let stmt = conn.statement_from_name("get_name").unwrap();
+    /// let rows = stmt.query(&[&1i32]).unwrap();
+    /// ```
+    pub fn statement_from_name<'a>(&'a self, name: &str) -> Result<Statement<'a>> {
+        let (param_types, columns) = {
+            let mut conn = self.0.borrow_mut();
+            check_desync!(conn);
+            conn.raw_describe(name)?
+        };
+        let info = Arc::new(StatementInfo::new(
+            name.to_owned(),
+            String::new(),
+            param_types,
+            columns,
+            self.integer_datetimes(),
+        ));
+        Ok(Statement::new(self, info, Cell::new(0), true))
+    }
+
     /// Returns the isolation level which will be used for future transactions.
     ///
     /// This is a simple wrapper around `SHOW TRANSACTION ISOLATION LEVEL`.
@@ -1204,19 +2309,32 @@ impl Connection {
         IsolationLevel::new(result[0][0].as_ref().unwrap())
     }
 
+    /// Determines if future transactions will be read-only by default.
+    ///
+    /// This is a simple wrapper around `SHOW default_transaction_read_only`,
+    /// which can be set for the connection with `params::Builder::read_only`.
+    pub fn is_read_only(&self) -> Result<bool> {
+        let mut conn = self.0.borrow_mut();
+        check_desync!(conn);
+        let result = conn.quick_query("SHOW default_transaction_read_only")?;
+        Ok(result[0][0].as_ref().map_or(false, |v| v == "on"))
+    }
+
     /// Sets the configuration that will be used for future transactions.
     pub fn set_transaction_config(&self, config: &transaction::Config) -> Result<()> {
         let mut command = "SET SESSION CHARACTERISTICS AS TRANSACTION".to_owned();
         config.build_command(&mut command);
-        self.batch_execute(&command)
+        self.0.borrow_mut().quick_query(&command).map(|_| ())
     }
 
     /// Execute a sequence of SQL statements.
     ///
     /// Statements should be separated by `;` characters. If an error occurs,
-    /// execution of the sequence will stop at that point. This is intended for
-    /// execution of batches of non-dynamic statements - for example, creation
-    /// of a schema for a fresh database.
+    /// execution of the sequence will stop at that point, and the 1-based
+    /// position of the failing statement is available from the error's
+    /// `batch_index` method. This is intended for execution of batches of
+    /// non-dynamic statements - for example, creation of a schema for a fresh
+    /// database.
     ///
     /// # Warning
     ///
@@ -1246,7 +2364,59 @@ impl Connection {
     ///     ").unwrap();
     /// ```
     pub fn batch_execute(&self, query: &str) -> Result<()> {
-        self.0.borrow_mut().quick_query(query).map(|_| ())
+        self.0.borrow_mut().batch_execute(query)
+    }
+
+    /// Like `batch_execute`, but executes the statements in a single
+    /// transaction.
+    ///
+    /// The transaction is committed if every statement succeeds, and rolled
+    /// back otherwise. Statements which cannot be executed in a transaction
+    /// block, such as `CREATE DATABASE` or `VACUUM`, fail with an
+    /// `ACTIVE_SQL_TRANSACTION` error, and the whole batch is rolled back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a transaction is already active.
+    pub fn batch_execute_atomic(&self, query: &str) -> Result<()> {
+        let trans = self.transaction()?;
+        trans.batch_execute(query)?;
+        trans.commit()
+    }
+
+    /// Reads the file at the specified path and executes its contents with
+    /// `batch_execute`.
+    ///
+    /// If `atomic` is set, the statements are executed in a single
+    /// transaction, as in `batch_execute_atomic`. Any error, including one
+    /// from reading the file, reports the path through `Error::file_path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `atomic` is set and a transaction is already active.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// conn.execute_file("migrations/0001_init.sql", true).unwrap();
+    /// ```
+    pub fn execute_file<P>(&self, path: P, atomic: bool) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let query = match read_file(path) {
+            Ok(query) => query,
+            Err(e) => return Err(error::file(path.to_owned(), error::io(e))),
+        };
+        let r = if atomic {
+            self.batch_execute_atomic(&query)
+        } else {
+            self.batch_execute(&query)
+        };
+        r.map_err(|e| error::file(path.to_owned(), e))
     }
 
     /// Returns a structure providing access to asynchronous notifications.
@@ -1256,6 +2426,71 @@ impl Connection {
         Notifications::new(self)
     }
 
+    /// Registers this connection as a listener on the specified channel.
+    ///
+    /// The channel name is quoted as an identifier, so it is case sensitive
+    /// and may contain arbitrary characters. Notifications sent to the channel
+    /// are available through `notifications`.
+    pub fn listen(&self, channel: &str) -> Result<()> {
+        let channel = quote_channel(channel)?;
+        self.0.borrow_mut().quick_query(&format!("LISTEN {}", channel)).map(|_| ())
+    }
+
+    /// Registers this connection as a listener on each of the specified
+    /// channels.
+    ///
+    /// The channel names are quoted in the same way as in `listen`, and the
+    /// `LISTEN` commands are sent in a single batch, so only one round trip to
+    /// the server is made. If any of the names is invalid, an error is
+    /// returned before anything is sent. The commands are executed in a single
+    /// implicit transaction, so if one fails, none take effect.
+    pub fn listen_all(&self, channels: &[&str]) -> Result<()> {
+        if channels.is_empty() {
+            return Ok(());
+        }
+
+        let mut commands = vec![];
+        for channel in channels {
+            commands.push(format!("LISTEN {}", quote_channel(channel)?));
+        }
+        self.0.borrow_mut().batch_execute(&commands.join("; "))
+    }
+
+    /// Unregisters this connection as a listener on the specified channel.
+    ///
+    /// The channel name is quoted in the same way as in `listen`.
+    pub fn unlisten(&self, channel: &str) -> Result<()> {
+        let channel = quote_channel(channel)?;
+        self.0.borrow_mut().quick_query(&format!("UNLISTEN {}", channel)).map(|_| ())
+    }
+
+    /// Acquires a session-level advisory lock, blocking until it is
+    /// available.
+    ///
+    /// The lock is held until it is released with `advisory_unlock` or the
+    /// connection is closed. Advisory locks are reentrant, so a lock acquired
+    /// several times must be released the same number of times. Locks held
+    /// only for the duration of a transaction can be acquired with
+    /// `Transaction::advisory_lock`.
+    pub fn advisory_lock(&self, key: i64) -> Result<()> {
+        let stmt = self.prepare_cached("SELECT pg_catalog.pg_advisory_lock($1)")?;
+        stmt.execute(&[&key]).map(|_| ())
+    }
+
+    /// Attempts to acquire a session-level advisory lock without blocking,
+    /// returning `true` if it was acquired.
+    pub fn try_advisory_lock(&self, key: i64) -> Result<bool> {
+        let stmt = self.prepare_cached("SELECT pg_catalog.pg_try_advisory_lock($1)")?;
+        Ok(stmt.query(&[&key])?.get(0).get(0))
+    }
+
+    /// Releases a session-level advisory lock, returning `false` if it was
+    /// not held.
+    pub fn advisory_unlock(&self, key: i64) -> Result<bool> {
+        let stmt = self.prepare_cached("SELECT pg_catalog.pg_advisory_unlock($1)")?;
+        Ok(stmt.query(&[&key])?.get(0).get(0))
+    }
+
     /// Returns information used to cancel pending queries.
     ///
     /// Used with the `cancel_query` function. The object returned can be used
@@ -1270,11 +2505,73 @@ impl Connection {
         self.0.borrow().parameters.get(param).cloned()
     }
 
+    /// Determines if the server stores date and time values as 64-bit
+    /// integers, as reported by the `integer_datetimes` backend parameter.
+    ///
+    /// This is always the case since Postgres 10, but older servers could be
+    /// built to store them as floating point instead. The binary conversions
+    /// for time, timestamp and interval types assume the integer
+    /// representation, so on such a server binding a parameter or reading a
+    /// binary column value of those types returns a conversion error.
+    pub fn integer_datetimes(&self) -> bool {
+        self.0.borrow().integer_datetimes()
+    }
+
+    /// Returns the value of the `application_name` backend parameter.
+    ///
+    /// It can be set with `params::Builder::application_name`.
+    pub fn application_name(&self) -> Option<String> {
+        self.parameter("application_name")
+    }
+
+    /// Returns the version of the server as a `(major, minor, patch)` tuple.
+    ///
+    /// This is parsed from the `server_version` backend parameter. Any
+    /// non-numeric suffix (e.g. `beta1` or distribution information) is
+    /// ignored. Since Postgres 10, versions only have two components, so
+    /// `14.2` is returned as `(14, 2, 0)`.
+    ///
+    /// Returns `None` if the server did not report a version or it could not
+    /// be parsed.
+    pub fn server_version(&self) -> Option<(u16, u16, u16)> {
+        self.0
+            .borrow()
+            .parameters
+            .get("server_version")
+            .and_then(|v| postgres_shared::parse_server_version(v))
+    }
+
+    /// Sets a timeout which applies to each read from the server, or clears
+    /// it if `timeout` is `None`.
+    ///
+    /// If the server does not respond within the timeout, the operation
+    /// returns an IO error of kind `TimedOut`. A timeout may occur partway
+    /// through the server's response, so the connection will be
+    /// desynchronized afterwards and should be discarded. This also applies to
+    /// `Notifications::blocking_iter`.
+    ///
+    /// An error is returned if `timeout` is zero.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.0.borrow_mut().stream.set_default_read_timeout(timeout)?;
+        Ok(())
+    }
+
     /// Sets the notice handler for the connection, returning the old handler.
     pub fn set_notice_handler(&self, handler: Box<HandleNotice>) -> Box<HandleNotice> {
         self.0.borrow_mut().set_notice_handler(handler)
     }
 
+    /// Sets the query logger for the connection, returning the old logger.
+    ///
+    /// The logger is called with every query executed by the connection,
+    /// along with its parameters, regardless of the level of the `log` crate.
+    /// Statements created by `statement_from_name` are logged with an empty
+    /// query, as their text is not known to the connection. Passing `None`
+    /// disables logging, which is the default.
+    pub fn set_query_logger(&self, logger: Option<Box<LogQuery>>) -> Option<Box<LogQuery>> {
+        self.0.borrow_mut().set_query_logger(logger)
+    }
+
     /// Returns whether or not the stream has been desynchronized due to an
     /// error in the communication channel with the server.
     ///
@@ -1284,6 +2581,26 @@ impl Connection {
         self.0.borrow().is_desynchronized()
     }
 
+    /// Returns whether or not the server has closed the connection, for
+    /// example because it is shutting down or the backend was terminated with
+    /// `pg_terminate_backend`.
+    ///
+    /// A connection which was terminated by the server is also desynchronized,
+    /// and should be discarded rather than reused.
+    pub fn was_terminated_by_server(&self) -> bool {
+        self.0.borrow().was_terminated_by_server()
+    }
+
+    /// Returns the transaction status reported by the server after the most
+    /// recent query.
+    ///
+    /// Once a statement in a transaction fails, the server rejects every
+    /// further statement until the transaction is rolled back. This can be
+    /// used to detect that situation without issuing a query.
+    pub fn transaction_status(&self) -> TransactionStatus {
+        self.0.borrow().transaction_status
+    }
+
     /// Determines if the `Connection` is currently "active", that is, if there
     /// are no active transactions.
     ///
@@ -1302,6 +2619,19 @@ impl Connection {
         conn.finished = true;
         conn.finish_inner()
     }
+
+    // Runs `f` with `cancel` polled while waiting on the server.
+    fn cancellable<T, F>(&self, cancel: &Arc<AtomicBool>, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        self.0.borrow_mut().cancel_flag = Some(cancel.clone());
+        let result = f();
+        let mut conn = self.0.borrow_mut();
+        conn.cancel_flag = None;
+        conn.cancel_sent = false;
+        result
+    }
 }
 
 /// A trait allowing abstraction over connections and transactions
@@ -1312,6 +2642,12 @@ pub trait GenericConnection {
     /// Like `Connection::query`.
     fn query<'a>(&'a self, query: &str, params: &[&ToSql]) -> Result<Rows>;
 
+    /// Like `Connection::query_scalar`.
+    fn query_scalar<T>(&self, query: &str, params: &[&ToSql]) -> Result<T>
+    where
+        Self: Sized,
+        T: FromSql;
+
     /// Like `Connection::prepare`.
     fn prepare<'a>(&'a self, query: &str) -> Result<Statement<'a>>;
 
@@ -1337,6 +2673,13 @@ impl GenericConnection for Connection {
         self.query(query, params)
     }
 
+    fn query_scalar<T>(&self, query: &str, params: &[&ToSql]) -> Result<T>
+    where
+        T: FromSql,
+    {
+        self.query_scalar(query, params)
+    }
+
     fn prepare<'a>(&'a self, query: &str) -> Result<Statement<'a>> {
         self.prepare(query)
     }
@@ -1367,6 +2710,13 @@ impl<'a> GenericConnection for Transaction<'a> {
         self.query(query, params)
     }
 
+    fn query_scalar<T>(&self, query: &str, params: &[&ToSql]) -> Result<T>
+    where
+        T: FromSql,
+    {
+        self.query_scalar(query, params)
+    }
+
     fn prepare<'b>(&'b self, query: &str) -> Result<Statement<'b>> {
         self.prepare(query)
     }
@@ -1388,6 +2738,22 @@ impl<'a> GenericConnection for Transaction<'a> {
     }
 }
 
+// Extracts the value of a query returning a single row with a single column.
+fn scalar<T>(rows: &Rows) -> Result<T>
+where
+    T: FromSql,
+{
+    if rows.len() != 1 || rows.columns().len() != 1 {
+        let msg = format!(
+            "expected a single value but the query returned {} rows of {} columns",
+            rows.len(),
+            rows.columns().len()
+        );
+        return Err(error::conversion(msg.into()));
+    }
+    rows.get(0).get_opt(0).unwrap()
+}
+
 fn err(fields: &mut ErrorFields) -> Error {
     match DbError::new(fields) {
         Ok(err) => error::db(err),
@@ -12,6 +12,9 @@ macro_rules! try_desync {
 
 macro_rules! check_desync {
     ($e:expr) => ({
+        if $e.was_terminated_by_server() {
+            return Err(::terminated().into());
+        }
         if $e.is_desynchronized() {
             return Err(::desynchronized().into());
         }
@@ -36,6 +39,11 @@ macro_rules! info {
     ($($t:tt)*) => {}
 }
 
+#[cfg(feature = "no-logging")]
+macro_rules! warn {
+    ($($t:tt)*) => {}
+}
+
 /// Generates a simple implementation of `ToSql::accepts` which accepts the
 /// types passed to it.
 #[macro_export]
@@ -2,6 +2,7 @@
 
 use fallible_iterator::{FallibleIterator, IntoFallibleIterator};
 use std::fmt;
+use std::io;
 use std::time::Duration;
 use postgres_protocol::message::backend::{self, ErrorFields};
 use error::DbError;
@@ -10,9 +11,21 @@ use error::DbError;
 use postgres_shared;
 pub use postgres_shared::Notification;
 
-use {desynchronized, Result, Connection};
+use {desynchronized, Result, Connection, InnerConnection};
 use error::Error;
 
+/// The action taken when a notification arrives while the buffer of pending
+/// notifications is full.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The oldest pending notification is discarded to make room for the new
+    /// one.
+    DropOldest,
+    /// The new notification is discarded, and the next attempt to read a
+    /// notification will return an error.
+    Error,
+}
+
 /// Notifications from the Postgres backend.
 pub struct Notifications<'conn> {
     conn: &'conn Connection,
@@ -41,6 +54,46 @@ impl<'conn> Notifications<'conn> {
         self.len() == 0
     }
 
+    /// Limits the number of notifications which will be buffered while
+    /// waiting to be read.
+    ///
+    /// Notifications received while executing queries are held by the
+    /// connection until they are read. By default there is no limit, so a
+    /// flood of notifications can cause unbounded memory use. Once the limit
+    /// is reached, `policy` determines what happens to new notifications.
+    ///
+    /// Notifications which are already pending are not discarded.
+    pub fn set_limit(&self, limit: Option<usize>, policy: OverflowPolicy) {
+        let mut conn = self.conn.0.borrow_mut();
+        conn.notification_limit = limit;
+        conn.notification_overflow = policy;
+    }
+
+    /// Determines if any notifications have been discarded due to the limit
+    /// set by `set_limit` since notifications were last read.
+    pub fn dropped(&self) -> bool {
+        self.conn.0.borrow().notifications_dropped
+    }
+
+    /// Returns the next notification, if one is available.
+    ///
+    /// If none are pending, any data already sent by the server is read
+    /// without blocking, so notifications can be retrieved without running a
+    /// query. `None` is returned if nothing has arrived.
+    pub fn poll(&self) -> Result<Option<Notification>> {
+        self.iter().next()
+    }
+
+    /// Removes and returns all pending notifications, in the order they were
+    /// received.
+    ///
+    /// As with `poll`, any data already sent by the server is read without
+    /// blocking, so notifications which arrived since the last query are
+    /// included.
+    pub fn drain(&self) -> Result<Vec<Notification>> {
+        self.iter().collect()
+    }
+
     /// Returns a fallible iterator over pending notifications.
     ///
     /// # Note
@@ -96,7 +149,7 @@ impl<'a> FallibleIterator for Iter<'a> {
     fn next(&mut self) -> Result<Option<Notification>> {
         let mut conn = self.conn.0.borrow_mut();
 
-        if let Some(notification) = conn.notifications.pop_front() {
+        if let Some(notification) = pop_pending(&mut conn)? {
             return Ok(Some(notification));
         }
 
@@ -136,7 +189,7 @@ impl<'a> FallibleIterator for BlockingIter<'a> {
     fn next(&mut self) -> Result<Option<Notification>> {
         let mut conn = self.conn.0.borrow_mut();
 
-        if let Some(notification) = conn.notifications.pop_front() {
+        if let Some(notification) = pop_pending(&mut conn)? {
             return Ok(Some(notification));
         }
 
@@ -173,7 +226,7 @@ impl<'a> FallibleIterator for TimeoutIter<'a> {
     fn next(&mut self) -> Result<Option<Notification>> {
         let mut conn = self.conn.0.borrow_mut();
 
-        if let Some(notification) = conn.notifications.pop_front() {
+        if let Some(notification) = pop_pending(&mut conn)? {
             return Ok(Some(notification));
         }
 
@@ -201,6 +254,20 @@ impl<'a> FallibleIterator for TimeoutIter<'a> {
     }
 }
 
+fn pop_pending(conn: &mut InnerConnection) -> Result<Option<Notification>> {
+    if conn.notifications_dropped {
+        conn.notifications_dropped = false;
+        if conn.notification_overflow == OverflowPolicy::Error {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "notifications were discarded because the limit was reached",
+            ).into());
+        }
+    }
+
+    Ok(conn.notifications.pop_front())
+}
+
 fn err(fields: &mut ErrorFields) -> Error {
     match DbError::new(fields) {
         Ok(err) => postgres_shared::error::db(err),
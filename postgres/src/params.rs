@@ -1,3 +1,4 @@
 //! Connection parameters
 
-pub use postgres_shared::params::{Builder, ConnectParams, User, Host, IntoConnectParams};
+pub use postgres_shared::params::{Builder, ConnectParams, Connector, User, Host, IntoConnectParams,
+                                  Proxy};
@@ -1,6 +1,6 @@
 use std::io::{self, BufWriter, Read, Write};
-use std::net::{ToSocketAddrs, SocketAddr};
-use std::time::Duration;
+use std::net::{IpAddr, ToSocketAddrs, SocketAddr};
+use std::time::{Duration, Instant};
 use std::result;
 use bytes::{BufMut, BytesMut};
 #[cfg(unix)]
@@ -16,7 +16,7 @@ use socket2::{Socket, SockAddr, Domain, Type};
 use {Result, TlsMode};
 use error;
 use tls::TlsStream;
-use params::{ConnectParams, Host};
+use params::{ConnectParams, Host, Proxy, User};
 
 const INITIAL_CAPACITY: usize = 8 * 1024;
 
@@ -24,6 +24,9 @@ pub struct MessageStream {
     stream: BufWriter<Box<TlsStream>>,
     in_buf: BytesMut,
     out_buf: Vec<u8>,
+    deadline: Option<Instant>,
+    read_timeout: Option<Duration>,
+    max_message_size: usize,
 }
 
 impl MessageStream {
@@ -32,9 +35,48 @@ impl MessageStream {
             stream: BufWriter::new(stream),
             in_buf: BytesMut::with_capacity(INITIAL_CAPACITY),
             out_buf: vec![],
+            deadline: None,
+            read_timeout: None,
+            max_message_size: u32::max_value() as usize,
         }
     }
 
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = max_message_size;
+    }
+
+    /// Bounds all IO on the stream by the deadline, or clears the bound if it
+    /// is `None`.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) -> io::Result<()> {
+        self.deadline = deadline;
+        let timeout = match deadline {
+            Some(deadline) => Some(remaining(deadline)?),
+            None => None,
+        };
+        self.set_read_timeout(timeout.or(self.read_timeout))?;
+        self.stream.get_ref().get_ref().0.set_write_timeout(timeout)
+    }
+
+    /// Bounds each individual read from the stream by the timeout, or clears
+    /// the bound if it is `None`.
+    pub fn set_default_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        if timeout == Some(Duration::from_secs(0)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "read timeout cannot be zero",
+            ));
+        }
+        self.read_timeout = timeout;
+        if self.deadline.is_none() {
+            self.set_read_timeout(timeout)?;
+        }
+        Ok(())
+    }
+
+    pub fn default_read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
     pub fn get_ref(&self) -> &Box<TlsStream> {
         self.stream.get_ref()
     }
@@ -51,7 +93,7 @@ impl MessageStream {
 
     pub fn read_message(&mut self) -> io::Result<backend::Message> {
         loop {
-            match backend::Message::parse(&mut self.in_buf) {
+            match backend::Message::parse_with_max_len(&mut self.in_buf, self.max_message_size) {
                 Ok(Some(message)) => return Ok(message),
                 Ok(None) => self.read_in()?,
                 Err(e) => return Err(e),
@@ -60,6 +102,11 @@ impl MessageStream {
     }
 
     fn read_in(&mut self) -> io::Result<()> {
+        if let Some(deadline) = self.deadline {
+            let timeout = remaining(deadline)?;
+            self.set_read_timeout(Some(timeout))?;
+        }
+
         self.in_buf.reserve(1);
         match self.stream.get_mut().read(
             unsafe { self.in_buf.bytes_mut() },
@@ -72,6 +119,8 @@ impl MessageStream {
                 unsafe { self.in_buf.advance_mut(n) };
                 Ok(())
             }
+            Err(ref e) if (self.deadline.is_some() || self.read_timeout.is_some()) &&
+                              is_timeout(e) => Err(timed_out()),
             Err(e) => Err(e),
         }
     }
@@ -83,7 +132,7 @@ impl MessageStream {
         if self.in_buf.is_empty() {
             self.set_read_timeout(Some(timeout))?;
             let r = self.read_in();
-            self.set_read_timeout(None)?;
+            self.set_read_timeout(self.read_timeout)?;
 
             match r {
                 Ok(()) => {}
@@ -105,7 +154,7 @@ impl MessageStream {
 
             match r {
                 Ok(()) => {}
-                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(ref e) if is_timeout(e) => return Ok(None),
                 Err(e) => return Err(e),
             }
         }
@@ -114,7 +163,10 @@ impl MessageStream {
     }
 
     pub fn flush(&mut self) -> io::Result<()> {
-        self.stream.flush()
+        match self.stream.flush() {
+            Err(ref e) if self.deadline.is_some() && is_timeout(e) => Err(timed_out()),
+            r => r,
+        }
     }
 
     fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
@@ -126,6 +178,23 @@ impl MessageStream {
     }
 }
 
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    let now = Instant::now();
+    if now >= deadline {
+        Err(timed_out())
+    } else {
+        Ok(deadline - now)
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut
+}
+
+pub fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "connection timed out")
+}
+
 /// A connection to the Postgres server.
 ///
 /// It implements `Read`, `Write` and `TlsStream`, as well as `AsRawFd` on
@@ -173,38 +242,52 @@ impl AsRawSocket for Stream {
     }
 }
 
+fn connect_tcp(host: &str, port: u16, timeout: Option<Duration>) -> Result<Socket> {
+    let mut error = None;
+    for addr in (host, port).to_socket_addrs()? {
+        let domain = match addr {
+            SocketAddr::V4(_) => Domain::ipv4(),
+            SocketAddr::V6(_) => Domain::ipv6(),
+        };
+        let socket = Socket::new(domain, Type::stream(), None)?;
+        let addr = SockAddr::from(addr);
+        let r = match timeout {
+            Some(timeout) => socket.connect_timeout(&addr, timeout),
+            None => socket.connect(&addr),
+        };
+        match r {
+            Ok(()) => return Ok(socket),
+            Err(e) => error = Some(e),
+        }
+    }
+
+    Err(
+        error
+            .unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "could not resolve any addresses",
+                )
+            })
+            .into(),
+    )
+}
+
 fn open_socket(params: &ConnectParams) -> Result<Socket> {
     let port = params.port();
     match *params.host() {
         Host::Tcp(ref host) => {
-            let mut error = None;
-            for addr in (&**host, port).to_socket_addrs()? {
-                let domain = match addr {
-                    SocketAddr::V4(_) => Domain::ipv4(),
-                    SocketAddr::V6(_) => Domain::ipv6(),
-                };
-                let socket = Socket::new(domain, Type::stream(), None)?;
-                let addr = SockAddr::from(addr);
-                let r = match params.connect_timeout() {
-                    Some(timeout) => socket.connect_timeout(&addr, timeout),
-                    None => socket.connect(&addr),
-                };
-                match r {
-                    Ok(()) => return Ok(socket),
-                    Err(e) => error = Some(e),
+            let (host, port) = match params.proxy() {
+                Some(proxy) => (proxy.host(), proxy.port()),
+                None => (&**host, port),
+            };
+            match params.connector() {
+                Some(connector) => {
+                    let stream = connector.connect(host, port, params.connect_timeout())?;
+                    Ok(Socket::from(stream))
                 }
+                None => connect_tcp(host, port, params.connect_timeout()),
             }
-
-            Err(
-                error
-                    .unwrap_or_else(|| {
-                        io::Error::new(
-                            io::ErrorKind::InvalidInput,
-                            "could not resolve any addresses",
-                        )
-                    })
-                    .into(),
-            )
         }
         #[cfg(unix)]
         Host::Unix(ref path) => {
@@ -225,8 +308,25 @@ fn open_socket(params: &ConnectParams) -> Result<Socket> {
     }
 }
 
-pub fn initialize_stream(params: &ConnectParams, tls: TlsMode) -> Result<Box<TlsStream>> {
+pub fn initialize_stream(
+    params: &ConnectParams,
+    tls: TlsMode,
+    deadline: Option<Instant>,
+) -> Result<Box<TlsStream>> {
     let mut socket = Stream(open_socket(params)?);
+    if let Some(deadline) = deadline {
+        let timeout = remaining(deadline)?;
+        socket.0.set_read_timeout(Some(timeout))?;
+        socket.0.set_write_timeout(Some(timeout))?;
+    }
+
+    if let (&Host::Tcp(ref host), Some(proxy)) = (params.host(), params.proxy()) {
+        match socks5_handshake(&mut socket, proxy, host, params.port()) {
+            Ok(()) => {}
+            Err(ref e) if deadline.is_some() && is_timeout(e) => return Err(timed_out().into()),
+            Err(e) => return Err(e.into()),
+        }
+    }
 
     let (tls_required, handshaker) = match tls {
         TlsMode::None => return Ok(Box::new(socket)),
@@ -234,26 +334,170 @@ pub fn initialize_stream(params: &ConnectParams, tls: TlsMode) -> Result<Box<Tls
         TlsMode::Require(handshaker) => (true, handshaker),
     };
 
-    let mut buf = vec![];
-    frontend::ssl_request(&mut buf);
-    socket.write_all(&buf)?;
-    socket.flush()?;
-
-    let mut b = [0; 1];
-    socket.read_exact(&mut b)?;
-    if b[0] == b'N' {
-        if tls_required {
-            return Err(error::tls("the server does not support TLS".into()));
-        } else {
-            return Ok(Box::new(socket));
+    if !params.direct_tls() {
+        let mut buf = vec![];
+        frontend::ssl_request(&mut buf);
+        socket.write_all(&buf)?;
+        socket.flush()?;
+
+        let mut b = [0; 1];
+        match socket.read_exact(&mut b) {
+            Ok(()) => {}
+            Err(ref e) if deadline.is_some() && is_timeout(e) => return Err(timed_out().into()),
+            Err(e) => return Err(e.into()),
+        }
+        if b[0] == b'N' {
+            if tls_required {
+                return Err(error::tls("the server does not support TLS".into()));
+            } else {
+                return Ok(Box::new(socket));
+            }
         }
     }
 
     let host = match *params.host() {
         Host::Tcp(ref host) => host,
         // Postgres doesn't support TLS over unix sockets
+        Host::Unix(_) if params.direct_tls() => {
+            return Err(error::tls("TLS is not supported over Unix sockets".into()))
+        }
         Host::Unix(_) => return Err(::bad_response().into()),
     };
 
-    handshaker.tls_handshake(host, socket).map_err(error::tls)
+    match handshaker.tls_handshake(host, socket) {
+        Ok(stream) => Ok(stream),
+        // The handshake error is opaque, so attribute it to the deadline if
+        // that has passed.
+        Err(_) if deadline.map_or(false, |d| Instant::now() >= d) => Err(timed_out().into()),
+        Err(e) => Err(error::tls(e)),
+    }
+}
+
+const SOCKS_VERSION: u8 = 5;
+const SOCKS_AUTH_NONE: u8 = 0;
+const SOCKS_AUTH_PASSWORD: u8 = 2;
+const SOCKS_AUTH_UNACCEPTABLE: u8 = 0xff;
+const SOCKS_CMD_CONNECT: u8 = 1;
+const SOCKS_ATYP_IPV4: u8 = 1;
+const SOCKS_ATYP_DOMAIN: u8 = 3;
+const SOCKS_ATYP_IPV6: u8 = 4;
+
+// Asks the proxy to open a connection to the server, as described in RFC 1928.
+fn socks5_handshake(socket: &mut Stream, proxy: &Proxy, host: &str, port: u16) -> io::Result<()> {
+    let mut buf = vec![SOCKS_VERSION];
+    match proxy.user() {
+        Some(_) => buf.extend_from_slice(&[2, SOCKS_AUTH_NONE, SOCKS_AUTH_PASSWORD]),
+        None => buf.extend_from_slice(&[1, SOCKS_AUTH_NONE]),
+    }
+    socket.write_all(&buf)?;
+    socket.flush()?;
+
+    let mut reply = [0; 2];
+    socket.read_exact(&mut reply)?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(socks_error("the proxy is not a SOCKS5 proxy"));
+    }
+    match (reply[1], proxy.user()) {
+        (SOCKS_AUTH_NONE, _) => {}
+        (SOCKS_AUTH_PASSWORD, Some(user)) => socks5_authenticate(socket, user)?,
+        (SOCKS_AUTH_UNACCEPTABLE, _) => {
+            return Err(socks_error("the proxy rejected the authentication methods offered"))
+        }
+        _ => return Err(socks_error("the proxy selected an unsupported authentication method")),
+    }
+
+    buf.clear();
+    buf.extend_from_slice(&[SOCKS_VERSION, SOCKS_CMD_CONNECT, 0]);
+    match host.parse() {
+        Ok(IpAddr::V4(addr)) => {
+            buf.push(SOCKS_ATYP_IPV4);
+            buf.extend_from_slice(&addr.octets());
+        }
+        Ok(IpAddr::V6(addr)) => {
+            buf.push(SOCKS_ATYP_IPV6);
+            buf.extend_from_slice(&addr.octets());
+        }
+        Err(_) => {
+            if host.len() > u8::max_value() as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "hostname is too long to be sent to a SOCKS5 proxy",
+                ));
+            }
+            buf.push(SOCKS_ATYP_DOMAIN);
+            buf.push(host.len() as u8);
+            buf.extend_from_slice(host.as_bytes());
+        }
+    }
+    buf.push((port >> 8) as u8);
+    buf.push(port as u8);
+    socket.write_all(&buf)?;
+    socket.flush()?;
+
+    let mut reply = [0; 4];
+    socket.read_exact(&mut reply)?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(socks_error("the proxy returned an invalid response"));
+    }
+    if reply[1] != 0 {
+        let msg = match reply[1] {
+            1 => "general SOCKS server failure",
+            2 => "connection not allowed by ruleset",
+            3 => "network unreachable",
+            4 => "host unreachable",
+            5 => "connection refused",
+            6 => "TTL expired",
+            7 => "command not supported",
+            8 => "address type not supported",
+            _ => "unknown error",
+        };
+        return Err(socks_error(&format!("the proxy failed to connect: {}", msg)));
+    }
+
+    // the address the proxy bound to is of no use to us
+    let len = match reply[3] {
+        SOCKS_ATYP_IPV4 => 4,
+        SOCKS_ATYP_IPV6 => 16,
+        SOCKS_ATYP_DOMAIN => {
+            let mut len = [0; 1];
+            socket.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        _ => return Err(socks_error("the proxy returned an invalid response")),
+    };
+    let mut addr = vec![0; len + 2];
+    socket.read_exact(&mut addr)?;
+
+    Ok(())
+}
+
+// Username/password authentication, as described in RFC 1929.
+fn socks5_authenticate(socket: &mut Stream, user: &User) -> io::Result<()> {
+    let name = user.name().as_bytes();
+    let password = user.password().unwrap_or("").as_bytes();
+    if name.len() > u8::max_value() as usize || password.len() > u8::max_value() as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "proxy credentials are too long",
+        ));
+    }
+
+    let mut buf = vec![1, name.len() as u8];
+    buf.extend_from_slice(name);
+    buf.push(password.len() as u8);
+    buf.extend_from_slice(password);
+    socket.write_all(&buf)?;
+    socket.flush()?;
+
+    let mut reply = [0; 2];
+    socket.read_exact(&mut reply)?;
+    if reply[1] != 0 {
+        return Err(socks_error("the proxy rejected the credentials"));
+    }
+
+    Ok(())
+}
+
+fn socks_error(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, msg)
 }
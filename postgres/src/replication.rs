@@ -0,0 +1,417 @@
+//! Streaming replication.
+//!
+//! A `ReplicationConnection` is a connection to a server's WAL sender, which
+//! accepts the commands of the [streaming replication protocol][docs] as well
+//! as ordinary SQL over the simple query protocol. `START_REPLICATION` switches
+//! the connection into CopyBoth mode, in which the server streams WAL data to
+//! the client while the client reports its progress back to the server.
+//!
+//! Connections are established with `replication=database`, so both logical
+//! and physical replication are supported. The server must be configured with
+//! `wal_level = logical` to decode changes with logical replication slots.
+//!
+//! [docs]: https://www.postgresql.org/docs/current/static/protocol-replication.html
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use postgres::TlsMode;
+//! use postgres::replication::{ReplicationConnection, ReplicationMessage};
+//! use postgres::types::Lsn;
+//!
+//! let conn = ReplicationConnection::connect("postgres://postgres@localhost",
+//!                                           TlsMode::None).unwrap();
+//! let mut stream = conn.start_logical_replication("my_slot", Lsn(0), &[]).unwrap();
+//! while let Some(message) = stream.next().unwrap() {
+//!     match message {
+//!         ReplicationMessage::XLogData(data) => {
+//!             println!("{}", String::from_utf8_lossy(&data.data));
+//!             stream.send_standby_status_update(data.wal_end, data.wal_end, data.wal_end, false)
+//!                 .unwrap();
+//!         }
+//!         ReplicationMessage::PrimaryKeepalive(keepalive) => {
+//!             if keepalive.reply_requested {
+//!                 let lsn = keepalive.wal_end;
+//!                 stream.send_standby_status_update(lsn, lsn, lsn, false).unwrap();
+//!             }
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use postgres_protocol::message::{backend, frontend};
+use postgres_protocol::message::replication;
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use params::IntoConnectParams;
+use types::Lsn;
+use {bad_response, err, escape_identifier, escape_literal, Connection, InnerConnection, Result,
+     TlsMode};
+
+// The number of seconds from the Unix epoch to the Postgres epoch, midnight
+// on January 1st, 2000.
+const PG_EPOCH_SECS: u64 = 946_684_800;
+
+/// A connection to a server's WAL sender.
+pub struct ReplicationConnection(Connection);
+
+impl fmt::Debug for ReplicationConnection {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("ReplicationConnection").field(&self.0).finish()
+    }
+}
+
+impl ReplicationConnection {
+    /// Creates a new replication connection to a Postgres database.
+    ///
+    /// The parameters are interpreted as they are by `Connection::connect`.
+    /// The user must have the `REPLICATION` attribute.
+    pub fn connect<T>(params: T, tls: TlsMode) -> Result<ReplicationConnection>
+    where
+        T: IntoConnectParams,
+    {
+        let conn = InnerConnection::connect(params, tls, Some("database"))?;
+        Ok(ReplicationConnection(Connection(RefCell::new(conn))))
+    }
+
+    /// Requests the server to identify itself.
+    pub fn identify_system(&self) -> Result<IdentifySystem> {
+        let mut rows = (self.0).0.borrow_mut().quick_query("IDENTIFY_SYSTEM")?;
+        if rows.len() != 1 || rows[0].len() != 4 {
+            return Err(bad_response().into());
+        }
+        let mut row = rows.remove(0).into_iter();
+
+        let system_id = row.next().unwrap().ok_or_else(bad_response)?;
+        let timeline = row.next()
+            .unwrap()
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(bad_response)?;
+        let xlog_pos = row.next()
+            .unwrap()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(bad_response)?;
+        let database = row.next().unwrap();
+
+        Ok(IdentifySystem {
+            system_id: system_id,
+            timeline: timeline,
+            xlog_pos: xlog_pos,
+            database: database,
+        })
+    }
+
+    /// Executes a sequence of commands, which may be SQL statements or
+    /// replication commands such as `CREATE_REPLICATION_SLOT`, discarding any
+    /// rows they return.
+    ///
+    /// WAL sender connections only support the simple query protocol, so
+    /// this is the only way to execute commands other than those with their
+    /// own methods.
+    pub fn batch_execute(&self, query: &str) -> Result<()> {
+        self.0.batch_execute(query)
+    }
+
+    /// Starts streaming changes from a logical replication slot.
+    ///
+    /// The stream starts at the specified position, or at the slot's
+    /// confirmed position if it is later. The options are passed to the
+    /// slot's output plugin.
+    pub fn start_logical_replication(
+        &self,
+        slot: &str,
+        start: Lsn,
+        options: &[(&str, &str)],
+    ) -> Result<ReplicationStream> {
+        let mut command = format!(
+            "START_REPLICATION SLOT {} LOGICAL {}",
+            escape_identifier(slot)?,
+            start
+        );
+        if !options.is_empty() {
+            command.push_str(" (");
+            for (i, &(name, value)) in options.iter().enumerate() {
+                if i > 0 {
+                    command.push_str(", ");
+                }
+                command.push_str(&escape_identifier(name)?);
+                command.push(' ');
+                command.push_str(&escape_literal(value)?);
+            }
+            command.push(')');
+        }
+        self.start_replication(&command)
+    }
+
+    /// Starts streaming WAL, optionally through a physical replication slot.
+    ///
+    /// If the timeline is not specified, the server's current timeline is
+    /// used.
+    pub fn start_physical_replication(
+        &self,
+        slot: Option<&str>,
+        start: Lsn,
+        timeline: Option<u32>,
+    ) -> Result<ReplicationStream> {
+        let mut command = "START_REPLICATION".to_owned();
+        if let Some(slot) = slot {
+            command.push_str(" SLOT ");
+            command.push_str(&escape_identifier(slot)?);
+        }
+        command.push_str(&format!(" PHYSICAL {}", start));
+        if let Some(timeline) = timeline {
+            command.push_str(&format!(" TIMELINE {}", timeline));
+        }
+        self.start_replication(&command)
+    }
+
+    fn start_replication(&self, command: &str) -> Result<ReplicationStream> {
+        let mut conn = (self.0).0.borrow_mut();
+        check_desync!(conn);
+        debug!("executing replication command: {}", command);
+        conn.log_query(command, &[]);
+        conn.stream
+            .write_message(|buf| frontend::query(command, buf))?;
+        conn.stream.flush()?;
+
+        match conn.read_message()? {
+            backend::Message::CopyBothResponse(_) => {}
+            backend::Message::ErrorResponse(body) => {
+                conn.wait_for_ready()?;
+                return Err(err(&mut body.fields()));
+            }
+            _ => bad_response!(conn),
+        }
+
+        Ok(ReplicationStream {
+            conn: &self.0,
+            done: false,
+        })
+    }
+}
+
+/// Information about the server, returned by `IDENTIFY_SYSTEM`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentifySystem {
+    /// The unique system identifier of the cluster.
+    pub system_id: String,
+    /// The current timeline ID.
+    pub timeline: u32,
+    /// The current WAL flush location.
+    pub xlog_pos: Lsn,
+    /// The database connected to, if any.
+    pub database: Option<String>,
+}
+
+/// A message received from the server while streaming.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplicationMessage {
+    /// A chunk of the WAL stream.
+    XLogData(XLogData),
+    /// A keepalive message.
+    PrimaryKeepalive(PrimaryKeepalive),
+    #[doc(hidden)]
+    __ForExtensibility,
+}
+
+/// A chunk of the WAL stream.
+///
+/// With logical replication, the data is the output of the slot's output
+/// plugin for a single change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XLogData {
+    /// The WAL position of the start of the data.
+    pub wal_start: Lsn,
+    /// The current end of WAL on the server.
+    pub wal_end: Lsn,
+    /// The server's clock at the time of transmission.
+    pub timestamp: SystemTime,
+    /// The data itself.
+    pub data: Vec<u8>,
+}
+
+/// A keepalive message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrimaryKeepalive {
+    /// The current end of WAL on the server.
+    pub wal_end: Lsn,
+    /// The server's clock at the time of transmission.
+    pub timestamp: SystemTime,
+    /// If true, the server will disconnect the client if it does not send a
+    /// status update soon.
+    pub reply_requested: bool,
+}
+
+/// A stream of replication messages, started by `START_REPLICATION`.
+///
+/// The stream is ended, and the connection returned to normal operation,
+/// when it is dropped. Any errors are ignored in that case; use `finish` to
+/// observe them.
+pub struct ReplicationStream<'conn> {
+    conn: &'conn Connection,
+    done: bool,
+}
+
+impl<'a> fmt::Debug for ReplicationStream<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ReplicationStream")
+            .field("connection", self.conn)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<'conn> Drop for ReplicationStream<'conn> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.finish_inner();
+        }
+    }
+}
+
+impl<'conn> ReplicationStream<'conn> {
+    /// Returns the next message from the server, blocking until one is
+    /// available.
+    ///
+    /// `None` is returned once the server has ended the stream, for example
+    /// when a physical replication stream reaches the end of a timeline.
+    pub fn next(&mut self) -> Result<Option<ReplicationMessage>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let mut conn = self.conn.0.borrow_mut();
+        match conn.read_message()? {
+            backend::Message::CopyData(body) => {
+                let message = match replication::ReplicationMessage::parse(body.data())? {
+                    replication::ReplicationMessage::XLogData(body) => {
+                        ReplicationMessage::XLogData(XLogData {
+                            wal_start: Lsn(body.wal_start()),
+                            wal_end: Lsn(body.wal_end()),
+                            timestamp: from_pg_micros(body.timestamp()),
+                            data: body.data().to_vec(),
+                        })
+                    }
+                    replication::ReplicationMessage::PrimaryKeepalive(body) => {
+                        ReplicationMessage::PrimaryKeepalive(PrimaryKeepalive {
+                            wal_end: Lsn(body.wal_end()),
+                            timestamp: from_pg_micros(body.timestamp()),
+                            reply_requested: body.reply_requested(),
+                        })
+                    }
+                    _ => bad_response!(conn),
+                };
+                Ok(Some(message))
+            }
+            backend::Message::CopyDone => {
+                drop(conn);
+                self.finish_inner()?;
+                Ok(None)
+            }
+            backend::Message::ErrorResponse(body) => {
+                self.done = true;
+                conn.wait_for_ready()?;
+                Err(err(&mut body.fields()))
+            }
+            _ => {
+                self.done = true;
+                bad_response!(conn)
+            }
+        }
+    }
+
+    /// Reports the progress of the client to the server.
+    ///
+    /// The positions are those of the last WAL byte + 1 written to disk,
+    /// flushed to disk and applied by the client. With logical replication,
+    /// the flush position is the one up to which the slot's changes are
+    /// confirmed and may be discarded.
+    ///
+    /// If `reply_requested` is true, the server will reply immediately with a
+    /// keepalive message.
+    pub fn send_standby_status_update(
+        &mut self,
+        write: Lsn,
+        flush: Lsn,
+        apply: Lsn,
+        reply_requested: bool,
+    ) -> Result<()> {
+        let mut conn = self.conn.0.borrow_mut();
+        check_desync!(conn);
+        let mut data = vec![];
+        replication::standby_status_update(
+            write.0,
+            flush.0,
+            apply.0,
+            to_pg_micros(SystemTime::now()),
+            reply_requested,
+            &mut data,
+        );
+        conn.stream
+            .write_message(|buf| frontend::copy_data(&data, buf))?;
+        conn.stream.flush()?;
+        Ok(())
+    }
+
+    /// Ends the stream, returning the connection to normal operation.
+    ///
+    /// Functionally equivalent to the `Drop` implementation, but allows
+    /// errors to be observed.
+    pub fn finish(mut self) -> Result<()> {
+        self.finish_inner()
+    }
+
+    fn finish_inner(&mut self) -> Result<()> {
+        self.done = true;
+        let mut conn = self.conn.0.borrow_mut();
+        check_desync!(conn);
+        conn.stream
+            .write_message(|buf| Ok::<(), io::Error>(frontend::copy_done(buf)))?;
+        conn.stream.flush()?;
+
+        // the server may send more data before it acknowledges the end of the
+        // stream, and a physical stream ending at the end of a timeline is
+        // followed by a result set describing the next one
+        loop {
+            match conn.read_message()? {
+                backend::Message::ReadyForQuery(_) => return Ok(()),
+                backend::Message::ErrorResponse(body) => {
+                    conn.wait_for_ready()?;
+                    return Err(err(&mut body.fields()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn pg_epoch() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(PG_EPOCH_SECS)
+}
+
+fn from_pg_micros(micros: i64) -> SystemTime {
+    if micros < 0 {
+        pg_epoch() - micros_to_duration(micros.wrapping_neg() as u64)
+    } else {
+        pg_epoch() + micros_to_duration(micros as u64)
+    }
+}
+
+fn to_pg_micros(time: SystemTime) -> i64 {
+    match time.duration_since(pg_epoch()) {
+        Ok(d) => duration_to_micros(d),
+        Err(e) => -duration_to_micros(e.duration()),
+    }
+}
+
+fn micros_to_duration(micros: u64) -> Duration {
+    Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1000)
+}
+
+fn duration_to_micros(d: Duration) -> i64 {
+    d.as_secs() as i64 * 1_000_000 + i64::from(d.subsec_nanos() / 1000)
+}
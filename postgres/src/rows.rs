@@ -6,18 +6,21 @@ use postgres_shared::rows::RowData;
 use std::collections::VecDeque;
 use std::fmt;
 use std::io;
+use std::marker::PhantomData;
 use std::ops::Deref;
 use std::slice;
+use std::str;
 use std::sync::Arc;
 
 #[doc(inline)]
 pub use postgres_shared::rows::RowIndex;
 
-use {Error, Result, StatementInfo};
+use {check_datetimes, Error, Result, StatementInfo};
+use simple_query;
 use error;
 use transaction::Transaction;
-use types::{FromSql, WrongType};
-use stmt::{Statement, Column};
+use types::{FromSql, Kind, WasNull, WrongType, BPCHAR, INT2, INT4, INT8, TEXT};
+use stmt::{Column, Format, Statement};
 
 enum MaybeOwned<'a, T: 'a> {
     Borrowed(&'a T),
@@ -63,6 +66,15 @@ impl Rows {
         &self.stmt_info.columns[..]
     }
 
+    /// Returns the formats in which the values of each column were returned
+    /// by the server.
+    ///
+    /// These are the formats of the statement when it was executed, so are
+    /// `Format::Binary` unless changed by `Statement::set_result_format`.
+    pub fn result_formats(&self) -> &[Format] {
+        &self.stmt_info.result_formats
+    }
+
     /// Returns the number of rows present.
     pub fn len(&self) -> usize {
         self.data.len()
@@ -92,6 +104,45 @@ impl Rows {
             iter: self.data.iter(),
         }
     }
+
+    /// Returns an iterator which converts each `Row` into a `T`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode, Result};
+    /// use postgres::rows::{FromRow, Row};
+    ///
+    /// struct Person {
+    ///     id: i32,
+    ///     name: String,
+    /// }
+    ///
+    /// impl FromRow for Person {
+    ///     fn from_row(row: &Row) -> Result<Person> {
+    ///         Ok(Person {
+    ///             id: row.get_opt("id").unwrap()?,
+    ///             name: row.get_opt("name").unwrap()?,
+    ///         })
+    ///     }
+    /// }
+    ///
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let rows = conn.query("SELECT id, name FROM person", &[]).unwrap();
+    /// for person in rows.map::<Person>() {
+    ///     let person = person.unwrap();
+    ///     println!("{}: {}", person.id, person.name);
+    /// }
+    /// ```
+    pub fn map<'a, T>(&'a self) -> Map<'a, T>
+    where
+        T: FromRow,
+    {
+        Map {
+            iter: self.iter(),
+            _p: PhantomData,
+        }
+    }
 }
 
 impl<'a> IntoIterator for &'a Rows {
@@ -139,6 +190,82 @@ impl<'a> DoubleEndedIterator for Iter<'a> {
 
 impl<'a> ExactSizeIterator for Iter<'a> {}
 
+/// A trait for types which can be created from a result row.
+///
+/// This allows the conversion of a row to be written once, for example to
+/// read columns by name into the fields of a struct, and applied with
+/// `Rows::map`.
+pub trait FromRow: Sized {
+    /// Creates a value from a row.
+    fn from_row(row: &Row) -> Result<Self>;
+}
+
+/// A trait for integer types which a value can be read into with
+/// `Row::get_as`.
+pub trait FromInteger: Sized {
+    /// Converts an integer value, returning `None` if it is out of range.
+    fn from_i64(v: i64) -> Option<Self>;
+}
+
+macro_rules! from_integer {
+    ($($t:ty),*; $($u:ty),*) => {
+        $(
+            impl FromInteger for $t {
+                fn from_i64(v: i64) -> Option<$t> {
+                    let r = v as $t;
+                    if r as i64 == v { Some(r) } else { None }
+                }
+            }
+        )*
+        $(
+            impl FromInteger for $u {
+                fn from_i64(v: i64) -> Option<$u> {
+                    let r = v as $u;
+                    if v >= 0 && r as i64 == v { Some(r) } else { None }
+                }
+            }
+        )*
+    }
+}
+
+from_integer!(i8, i16, i32, i64, isize; u8, u16, u32, u64, usize);
+
+/// An iterator over `Row`s converted with `FromRow`.
+pub struct Map<'a, T> {
+    iter: Iter<'a>,
+    _p: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> Iterator for Map<'a, T>
+where
+    T: FromRow,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        self.iter.next().map(|row| T::from_row(&row))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Map<'a, T>
+where
+    T: FromRow,
+{
+    fn next_back(&mut self) -> Option<Result<T>> {
+        self.iter.next_back().map(|row| T::from_row(&row))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Map<'a, T>
+where
+    T: FromRow,
+{
+}
+
 /// A single result row of a query.
 pub struct Row<'a> {
     stmt_info: &'a StatementInfo,
@@ -219,6 +346,132 @@ impl<'a> Row<'a> {
         self.get_inner(&idx)
     }
 
+    /// Retrieves the contents of an integer field of the row, converting it to
+    /// the requested type.
+    ///
+    /// Unlike `get_opt`, a `SMALLINT`, `INT` or `BIGINT` column can be read
+    /// into any integer type, for example an `i64` regardless of the width of
+    /// the column. An error is returned if the value does not fit in the
+    /// requested type. Columns returned in the text format, such as those of
+    /// simple queries, are converted in the same way.
+    ///
+    /// Returns `None` if the index does not reference a column, `Some(Err(..))`
+    /// if there was an error converting the result value, and `Some(Ok(..))`
+    /// on success.
+    pub fn get_as<I, T>(&self, idx: I) -> Option<Result<T>>
+    where
+        I: RowIndex,
+        T: FromInteger,
+    {
+        let idx = match idx.__idx(&self.stmt_info.columns) {
+            Some(idx) => idx,
+            None => return None,
+        };
+
+        let format = self.stmt_info.result_formats[idx];
+        let value = match (format, self.stmt_info.columns[idx].type_()) {
+            (Format::Binary, &INT2) => self.get_inner::<_, i16>(&idx).map(|r| r.map(i64::from)),
+            (Format::Binary, &INT4) => self.get_inner::<_, i32>(&idx).map(|r| r.map(i64::from)),
+            (Format::Text, &INT2) | (Format::Text, &INT4) | (Format::Text, &INT8) => {
+                Some(self.get_text_integer(idx))
+            }
+            _ => self.get_inner::<_, i64>(&idx),
+        };
+
+        value.map(|r| {
+            r.and_then(|v| {
+                T::from_i64(v).ok_or_else(|| {
+                    error::conversion(format!("value {} is out of range for the type", v).into())
+                })
+            })
+        })
+    }
+
+    // Parses the text representation of an integer column.
+    fn get_text_integer(&self, idx: usize) -> Result<i64> {
+        let raw = match self.data.get(idx) {
+            Some(raw) => raw,
+            None => return Err(error::conversion(Box::new(WasNull))),
+        };
+        str::from_utf8(raw)
+            .map_err(|e| error::conversion(Box::new(e)))
+            .and_then(|s| s.parse().map_err(|e| error::conversion(Box::new(e))))
+    }
+
+    /// Retrieves the label of an enum field of the row, checking that it is
+    /// one of `labels`.
+    ///
+    /// This guards against the server returning a label the caller does not
+    /// know how to handle, for example after a variant is added to the type.
+    /// An error is returned if the column is not of an enum type, if the value
+    /// is `NULL`, or if the label is not one of `labels`.
+    ///
+    /// Returns `None` if the index does not reference a column, `Some(Err(..))`
+    /// if there was an error converting the result value, and `Some(Ok(..))`
+    /// on success.
+    pub fn get_enum<I>(&self, idx: I, labels: &[&str]) -> Option<Result<String>>
+    where
+        I: RowIndex,
+    {
+        let idx = match idx.__idx(&self.stmt_info.columns) {
+            Some(idx) => idx,
+            None => return None,
+        };
+
+        let ty = self.stmt_info.columns[idx].type_();
+        match *ty.kind() {
+            Kind::Enum(_) => {}
+            _ => return Some(Err(error::conversion(Box::new(WrongType::new(ty.clone()))))),
+        }
+
+        let raw = match self.data.get(idx) {
+            Some(raw) => raw,
+            None => return Some(Err(error::conversion(Box::new(WasNull)))),
+        };
+        let label = match str::from_utf8(raw) {
+            Ok(label) => label,
+            Err(e) => return Some(Err(error::conversion(Box::new(e)))),
+        };
+        if !labels.contains(&label) {
+            let msg = format!("unexpected label `{}` for enum type `{}`", label, ty.name());
+            return Some(Err(error::conversion(msg.into())));
+        }
+
+        Some(Ok(label.to_owned()))
+    }
+
+    /// Retrieves the contents of a `CHAR(n)` field of the row, stripping the
+    /// trailing spaces the server pads it with.
+    ///
+    /// Use `get_opt` to preserve the padding. An error is returned if the
+    /// column is not of the `BPCHAR` type.
+    ///
+    /// Returns `None` if the index does not reference a column, `Some(Err(..))`
+    /// if there was an error converting the result value, and `Some(Ok(..))`
+    /// on success.
+    pub fn get_bpchar_trimmed<I>(&self, idx: I) -> Option<Result<String>>
+    where
+        I: RowIndex,
+    {
+        let idx = match idx.__idx(&self.stmt_info.columns) {
+            Some(idx) => idx,
+            None => return None,
+        };
+
+        let ty = self.stmt_info.columns[idx].type_();
+        if *ty != BPCHAR {
+            return Some(Err(error::conversion(Box::new(WrongType::new(ty.clone())))));
+        }
+
+        self.get_inner::<_, String>(&idx).map(|r| {
+            r.map(|mut s| {
+                let len = s.trim_end_matches(' ').len();
+                s.truncate(len);
+                s
+            })
+        })
+    }
+
     fn get_inner<I, T>(&self, idx: &I) -> Option<Result<T>>
     where
         I: RowIndex,
@@ -229,11 +482,29 @@ impl<'a> Row<'a> {
             None => return None,
         };
 
-        let ty = self.stmt_info.columns[idx].type_();
+        // the server's text representation of any type can be read as a string
+        let column_ty = self.stmt_info.columns[idx].type_();
+        let format = self.stmt_info.result_formats[idx];
+        let ty = match format {
+            Format::Binary => column_ty,
+            Format::Text if self.stmt_info.decode_text && !<T as FromSql>::accepts(&TEXT) &&
+                                simple_query::decodes_text(column_ty) => column_ty,
+            Format::Text => &TEXT,
+        };
         if !<T as FromSql>::accepts(ty) {
             return Some(Err(error::conversion(Box::new(WrongType::new(ty.clone())))));
         }
-        let value = FromSql::from_sql_nullable(ty, self.data.get(idx));
+
+        let value = match (format, self.data.get(idx)) {
+            (Format::Text, Some(raw)) if ty != &TEXT => {
+                simple_query::decode_text(ty, raw).and_then(|raw| FromSql::from_sql(ty, &raw))
+            }
+            (Format::Binary, Some(raw)) => {
+                check_datetimes(ty, self.stmt_info.integer_datetimes)
+                    .and_then(|()| FromSql::from_sql(ty, raw))
+            }
+            (_, raw) => FromSql::from_sql_nullable(ty, raw),
+        };
         Some(value.map_err(error::conversion))
     }
 
@@ -332,11 +603,24 @@ impl<'trans, 'stmt> LazyRows<'trans, 'stmt> {
         self.stmt.columns()
     }
 
+    /// Returns the maximum number of rows fetched from the server at a time.
+    ///
+    /// A value less than or equal to 0 indicates that all rows are fetched at
+    /// once.
+    pub fn row_limit(&self) -> i32 {
+        self.row_limit
+    }
+
     /// Consumes the `LazyRows`, cleaning up associated state.
     ///
+    /// The portal backing the rows is closed on the server immediately, even
+    /// if not all of the rows have been read, releasing its resources without
+    /// waiting for the end of the transaction.
+    ///
     /// Functionally identical to the `Drop` implementation on `LazyRows`
     /// except that it returns any error to the caller.
     pub fn finish(mut self) -> Result<()> {
+        self.finished = true;
         self.finish_inner()
     }
 }
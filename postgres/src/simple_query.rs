@@ -0,0 +1,385 @@
+// Execution of parameterized queries over the simple query protocol, used when
+// `prefer_simple_query` is set.
+//
+// Parameters are interpolated into the query as literals. The literals are
+// generated from the binary representation of each value, so only types with
+// a simple text form are supported. Strings are quoted with `escape_literal`,
+// and every literal is cast to its type so that it cannot be interpreted as
+// anything other than a single value.
+
+use postgres_protocol::message::{backend, frontend};
+use postgres_protocol::types;
+use postgres_shared::rows::RowData;
+use std::cell::Cell;
+use std::error::Error as StdError;
+use std::fmt::Write;
+use std::io;
+use std::f64;
+use std::result;
+use std::str;
+use std::sync::Arc;
+
+use error;
+use rows::Rows;
+use stmt::{self, Column, Format, Statement};
+use types::{IsNull, ToSql, Type, WrongType, BOOL, BYTEA, FLOAT4, FLOAT8, INT2, INT4, INT8, OID,
+            TEXT};
+use {copy_out_misuse, err, escape_literal, Connection, InnerConnection, Result,
+     StatementInfo, COPY_IN_MISUSE};
+
+// The types which parameters can be sent as, in order of preference.
+const PARAM_TYPES: &'static [Type] = &[BOOL, INT2, INT4, INT8, OID, FLOAT4, FLOAT8, TEXT, BYTEA];
+
+pub fn execute(conn: &Connection, query: &str, params: &[&ToSql], checked: bool) -> Result<u64> {
+    let (_, _, count) = run(conn, query, params, checked)?;
+    Ok(count)
+}
+
+pub fn query(conn: &Connection, query: &str, params: &[&ToSql], checked: bool) -> Result<Rows> {
+    let (info, data, _) = run(conn, query, params, checked)?;
+    let stmt = Statement::new(conn, Arc::new(info), Cell::new(0), true);
+    Ok(Rows::new(&stmt, data))
+}
+
+fn run(
+    conn: &Connection,
+    query: &str,
+    params: &[&ToSql],
+    checked: bool,
+) -> Result<(StatementInfo, Vec<RowData>, u64)> {
+    let placeholders = find_placeholders(query)?;
+    let expected = placeholders.iter().map(|p| p.2 + 1).max().unwrap_or(0);
+    if expected != params.len() {
+        if !checked {
+            panic!("expected {} parameters but got {}", expected, params.len());
+        }
+        let msg = format!("expected {} parameters but got {}", expected, params.len());
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into());
+    }
+
+    let mut literals = Vec::with_capacity(params.len());
+    let mut param_types = Vec::with_capacity(params.len());
+    for param in params {
+        let (literal, ty) = literal(*param)?;
+        literals.push(literal);
+        param_types.push(ty);
+    }
+
+    let mut interpolated = String::with_capacity(query.len());
+    let mut last = 0;
+    for &(start, end, idx) in &placeholders {
+        interpolated.push_str(&query[last..start]);
+        interpolated.push_str(&literals[idx]);
+        last = end;
+    }
+    interpolated.push_str(&query[last..]);
+
+    let mut conn_ref = conn.0.borrow_mut();
+    check_desync!(conn_ref);
+    conn_ref.log_query(query, params);
+    let (columns, data, count) = simple_query(&mut conn_ref, &interpolated)?;
+
+    let mut info = StatementInfo::new(
+        String::new(),
+        query.to_owned(),
+        param_types,
+        columns,
+        conn_ref.integer_datetimes(),
+    );
+    info.result_formats = vec![Format::Text; info.columns.len()];
+    info.decode_text = true;
+    Ok((info, data, count))
+}
+
+fn simple_query(
+    conn: &mut InnerConnection,
+    query: &str,
+) -> Result<(Vec<Column>, Vec<RowData>, u64)> {
+    debug!("executing simple query: {}", query);
+    conn.stream
+        .write_message(|buf| frontend::query(query, buf))?;
+    conn.stream.flush()?;
+
+    let mut raw_columns = None;
+    let mut data = vec![];
+    let mut count = 0;
+    let mut empty = false;
+    loop {
+        match conn.read_message()? {
+            backend::Message::ReadyForQuery(_) => break,
+            backend::Message::RowDescription(body) => raw_columns = Some(body),
+            backend::Message::DataRow(body) => data.push(RowData::new(body)?),
+            backend::Message::CommandComplete(body) => {
+                count = stmt::parse_update_count(body.tag()?)
+            }
+            backend::Message::EmptyQueryResponse => empty = true,
+            backend::Message::ErrorResponse(body) => {
+                conn.wait_for_ready()?;
+                return Err(err(&mut body.fields()));
+            }
+            backend::Message::CopyInResponse(_) => {
+                conn.stream.write_message(|buf| {
+                    frontend::copy_fail(COPY_IN_MISUSE, buf)
+                })?;
+                conn.stream
+                    .write_message(|buf| Ok::<(), io::Error>(frontend::sync(buf)))?;
+                conn.stream.flush()?;
+            }
+            backend::Message::CopyOutResponse(_) => {
+                loop {
+                    if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
+                        break;
+                    }
+                }
+                return Err(copy_out_misuse().into());
+            }
+            _ => {}
+        }
+    }
+
+    if empty {
+        return Err(error::empty_query().into());
+    }
+
+    let columns = conn.resolve_columns(raw_columns)?;
+    Ok((columns, data, count))
+}
+
+// Converts a parameter to a literal of the first supported type it accepts.
+fn literal(param: &ToSql) -> Result<(String, Type)> {
+    let mut buf = vec![];
+    for ty in PARAM_TYPES {
+        buf.clear();
+        let is_null = match param.to_sql_checked(ty, &mut buf) {
+            Ok(is_null) => is_null,
+            Err(ref e) if e.is::<WrongType>() => continue,
+            Err(e) => return Err(error::conversion(e)),
+        };
+        let value = match is_null {
+            IsNull::Yes => "NULL".to_owned(),
+            IsNull::No => value_literal(ty, &buf).map_err(error::conversion)?,
+        };
+        return Ok((format!("({}::pg_catalog.{})", value, ty.name()), ty.clone()));
+    }
+
+    let msg = format!("the parameter {:?} cannot be sent in simple query mode", param);
+    Err(error::conversion(msg.into()))
+}
+
+fn value_literal(ty: &Type, raw: &[u8]) -> result::Result<String, Box<StdError + Sync + Send>> {
+    let literal = match *ty {
+        BOOL => {
+            let v = types::bool_from_sql(raw)?;
+            if v { "'t'" } else { "'f'" }.to_owned()
+        }
+        INT2 => format!("'{}'", types::int2_from_sql(raw)?),
+        INT4 => format!("'{}'", types::int4_from_sql(raw)?),
+        INT8 => format!("'{}'", types::int8_from_sql(raw)?),
+        OID => format!("'{}'", types::oid_from_sql(raw)?),
+        FLOAT4 => float_literal(f64::from(types::float4_from_sql(raw)?)),
+        FLOAT8 => float_literal(types::float8_from_sql(raw)?),
+        TEXT => escape_literal(types::text_from_sql(raw)?)?,
+        BYTEA => {
+            let mut hex = "\\x".to_owned();
+            for b in raw {
+                write!(hex, "{:02x}", b).unwrap();
+            }
+            escape_literal(&hex)?
+        }
+        _ => unreachable!(),
+    };
+    Ok(literal)
+}
+
+// Determines if text values of the type can be converted by `decode_text`.
+pub fn decodes_text(ty: &Type) -> bool {
+    PARAM_TYPES.contains(ty) && *ty != TEXT
+}
+
+// Converts the text representation of a value to its binary representation,
+// the inverse of `value_literal`.
+pub fn decode_text(ty: &Type, raw: &[u8]) -> result::Result<Vec<u8>, Box<StdError + Sync + Send>> {
+    let s = str::from_utf8(raw)?;
+    let mut buf = vec![];
+    match *ty {
+        BOOL => {
+            let v = match s {
+                "t" => true,
+                "f" => false,
+                _ => return Err(format!("invalid boolean `{}`", s).into()),
+            };
+            types::bool_to_sql(v, &mut buf);
+        }
+        INT2 => types::int2_to_sql(s.parse()?, &mut buf),
+        INT4 => types::int4_to_sql(s.parse()?, &mut buf),
+        INT8 => types::int8_to_sql(s.parse()?, &mut buf),
+        OID => types::oid_to_sql(s.parse()?, &mut buf),
+        FLOAT4 => types::float4_to_sql(parse_float(s)? as f32, &mut buf),
+        FLOAT8 => types::float8_to_sql(parse_float(s)?, &mut buf),
+        BYTEA => {
+            if !s.starts_with("\\x") || s.len() % 2 != 0 {
+                return Err("bytea values must be in the hex output format".into());
+            }
+            for pair in raw[2..].chunks(2) {
+                buf.push(u8::from_str_radix(str::from_utf8(pair)?, 16)?);
+            }
+        }
+        _ => unreachable!(),
+    }
+    Ok(buf)
+}
+
+fn parse_float(s: &str) -> result::Result<f64, Box<StdError + Sync + Send>> {
+    match s {
+        "NaN" => Ok(f64::NAN),
+        "Infinity" => Ok(f64::INFINITY),
+        "-Infinity" => Ok(f64::NEG_INFINITY),
+        s => Ok(s.parse()?),
+    }
+}
+
+fn float_literal(v: f64) -> String {
+    if v.is_nan() {
+        "'NaN'".to_owned()
+    } else if v.is_infinite() && v > 0. {
+        "'Infinity'".to_owned()
+    } else if v.is_infinite() {
+        "'-Infinity'".to_owned()
+    } else {
+        format!("'{}'", v)
+    }
+}
+
+fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+fn invalid_query(msg: &str) -> error::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg).into()
+}
+
+// Returns the start and end offsets of each `$n` placeholder in the query,
+// along with the zero-based index of its parameter.
+//
+// Placeholders in string literals, quoted identifiers, comments and
+// dollar-quoted strings are skipped. A backslash in a string literal is only
+// interpreted the same way regardless of the server's
+// `standard_conforming_strings` setting in an escape string (`E'...'`), so
+// other string literals containing backslashes are rejected.
+fn find_placeholders(query: &str) -> Result<Vec<(usize, usize, usize)>> {
+    let bytes = query.as_bytes();
+    let mut placeholders = vec![];
+    let mut ended = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        let next = bytes.get(i + 1).cloned();
+
+        if b == b'-' && next == Some(b'-') {
+            i = match bytes[i..].iter().position(|&b| b == b'\n') {
+                Some(pos) => i + pos + 1,
+                None => bytes.len(),
+            };
+            continue;
+        }
+
+        if b == b'/' && next == Some(b'*') {
+            let mut depth = 1;
+            i += 2;
+            while i < bytes.len() && depth > 0 {
+                if bytes[i..].starts_with(b"/*") {
+                    depth += 1;
+                    i += 2;
+                } else if bytes[i..].starts_with(b"*/") {
+                    depth -= 1;
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+            }
+            continue;
+        }
+
+        if b.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if ended {
+            return Err(invalid_query(
+                "simple query mode does not support multiple statements",
+            ));
+        }
+
+        match b {
+            b';' => {
+                ended = true;
+                i += 1;
+            }
+            b'\'' => {
+                let escape = i > 0 && (bytes[i - 1] == b'E' || bytes[i - 1] == b'e') &&
+                    (i < 2 || !is_ident(bytes[i - 2]));
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'\\' if escape => i += 2,
+                        b'\\' => {
+                            return Err(invalid_query(
+                                "string literals containing backslashes must use the escape \
+                                 string syntax in simple query mode",
+                            ))
+                        }
+                        b'\'' if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+                        b'\'' => break,
+                        _ => i += 1,
+                    }
+                }
+                i += 1;
+            }
+            b'"' => {
+                i = match bytes[i + 1..].iter().position(|&b| b == b'"') {
+                    Some(pos) => i + pos + 2,
+                    None => bytes.len(),
+                };
+            }
+            b'$' if i == 0 || !is_ident(bytes[i - 1]) => {
+                let start = i;
+                i += 1;
+                if next.map_or(false, |b| b.is_ascii_digit()) {
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    let idx = match query[start + 1..i].parse::<usize>() {
+                        Ok(idx) if idx > 0 => idx - 1,
+                        _ => return Err(invalid_query("invalid parameter number")),
+                    };
+                    placeholders.push((start, i, idx));
+                    continue;
+                }
+
+                while i < bytes.len() && is_ident(bytes[i]) && bytes[i] != b'$' {
+                    i += 1;
+                }
+                if bytes.get(i) == Some(&b'$') {
+                    let tag = &query[start..i + 1];
+                    i = match query[i + 1..].find(tag) {
+                        Some(pos) => i + 1 + pos + tag.len(),
+                        None => bytes.len(),
+                    };
+                }
+            }
+            _ => {
+                if is_ident(b) {
+                    while i < bytes.len() && is_ident(bytes[i]) {
+                        i += 1;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    Ok(placeholders)
+}
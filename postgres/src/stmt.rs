@@ -6,16 +6,20 @@ use std::collections::VecDeque;
 use std::fmt;
 use std::io::{self, Read, Write};
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use postgres_protocol::message::{backend, frontend};
 use postgres_shared::rows::RowData;
 
 #[doc(inline)]
 pub use postgres_shared::stmt::Column;
 
-use types::{Type, ToSql};
+use types::{Oid, Type, ToSql};
+use binary_copy::BinaryCopyRows;
+use error::{self, Error};
 use rows::{Rows, LazyRows};
 use transaction::Transaction;
-use {bad_response, err, Connection, Result, StatementInfo};
+use {bad_response, check_param_count, copy_out_misuse, desynchronized, err,
+     escape_literal, Connection, InnerConnection, Result, StatementInfo, COPY_IN_MISUSE};
 
 /// A prepared statement.
 pub struct Statement<'conn> {
@@ -91,12 +95,14 @@ impl<'conn> Statement<'conn> {
     {
         let mut conn = self.conn.0.borrow_mut();
 
+        conn.log_query(&self.info.query, params);
         conn.raw_execute(
             &self.info.name,
             portal_name,
             row_limit,
             self.param_types(),
             params,
+            &self.info.result_formats,
         )?;
 
         conn.read_rows(acceptor)
@@ -112,9 +118,39 @@ impl<'conn> Statement<'conn> {
         &self.info.columns
     }
 
+    /// Returns the formats in which the values of each column of the result
+    /// of the query will be returned.
+    ///
+    /// Columns default to `Format::Binary`, except for those of built in
+    /// types which have no binary representation, such as `aclitem`, which
+    /// default to `Format::Text`. Columns of other types without a binary
+    /// representation must be switched to `Format::Text` with
+    /// `set_result_format` before the statement is executed.
+    pub fn result_formats(&self) -> &[Format] {
+        &self.info.result_formats
+    }
+
+    /// Sets the format in which values of the specified column will be
+    /// returned.
+    ///
+    /// Values of columns returned in `Format::Text` are the server's textual
+    /// representation of the value, and can only be retrieved from a `Row` as
+    /// a string type such as `String` or `&str`, regardless of the type of
+    /// the column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds.
+    pub fn set_result_format(&mut self, idx: usize, format: Format) {
+        Arc::make_mut(&mut self.info).result_formats[idx] = format;
+    }
+
     /// Executes the prepared statement, returning the number of rows modified.
     ///
     /// If the statement does not modify any rows (e.g. SELECT), 0 is returned.
+    /// An error is returned if the statement was prepared from an empty query,
+    /// or is a `COPY` statement, which must instead be executed with
+    /// `copy_in` or `copy_out`.
     ///
     /// # Panics
     ///
@@ -133,17 +169,39 @@ impl<'conn> Statement<'conn> {
     /// println!("{} rows updated", rows_updated);
     /// ```
     pub fn execute(&self, params: &[&ToSql]) -> Result<u64> {
+        self.execute_inner(params).map(|tag| parse_update_count(&tag))
+    }
+
+    /// Like `execute`, but also returns the OID of the inserted row.
+    ///
+    /// The OID is only reported by the server for an `INSERT` of a single row
+    /// into a table created `WITH OIDS`, and `None` is returned otherwise.
+    /// Tables with OIDs are not supported by Postgres 12 and newer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    pub fn execute_with_oid(&self, params: &[&ToSql]) -> Result<(u64, Option<Oid>)> {
+        self.execute_inner(params)
+            .map(|tag| (parse_update_count(&tag), parse_insert_oid(&tag)))
+    }
+
+    // Returns the command tag of the statement.
+    fn execute_inner(&self, params: &[&ToSql]) -> Result<String> {
         let mut conn = self.conn.0.borrow_mut();
         check_desync!(conn);
+        conn.log_query(&self.info.query, params);
         conn.raw_execute(
             &self.info.name,
             "",
             0,
             self.param_types(),
             params,
+            &self.info.result_formats,
         )?;
 
-        let num;
+        let tag;
         loop {
             match conn.read_message()? {
                 backend::Message::DataRow(_) => {}
@@ -152,16 +210,16 @@ impl<'conn> Statement<'conn> {
                     return Err(err(&mut body.fields()));
                 }
                 backend::Message::CommandComplete(body) => {
-                    num = parse_update_count(body.tag()?);
+                    tag = body.tag()?.to_owned();
                     break;
                 }
                 backend::Message::EmptyQueryResponse => {
-                    num = 0;
-                    break;
+                    conn.wait_for_ready()?;
+                    return Err(error::empty_query().into());
                 }
                 backend::Message::CopyInResponse(_) => {
                     conn.stream.write_message(|buf| {
-                        frontend::copy_fail("COPY queries cannot be directly executed", buf)
+                        frontend::copy_fail(COPY_IN_MISUSE, buf)
                     })?;
                     conn.stream.write_message(
                         |buf| Ok::<(), io::Error>(frontend::sync(buf)),
@@ -170,17 +228,11 @@ impl<'conn> Statement<'conn> {
                 }
                 backend::Message::CopyOutResponse(_) => {
                     loop {
-                        match conn.read_message()? {
-                            backend::Message::CopyDone => break,
-                            backend::Message::ErrorResponse(body) => {
-                                conn.wait_for_ready()?;
-                                return Err(err(&mut body.fields()));
-                            }
-                            _ => {}
+                        if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
+                            break;
                         }
                     }
-                    num = 0;
-                    break;
+                    return Err(copy_out_misuse().into());
                 }
                 _ => {
                     conn.desynchronized = true;
@@ -190,7 +242,7 @@ impl<'conn> Statement<'conn> {
         }
         conn.wait_for_ready()?;
 
-        Ok(num)
+        Ok(tag)
     }
 
     /// Executes the prepared statement, returning the resulting rows.
@@ -219,13 +271,171 @@ impl<'conn> Statement<'conn> {
         Ok(Rows::new(self, rows))
     }
 
+    /// Like `query`, but cancels the query if `cancel` is set while waiting
+    /// for the server's response.
+    ///
+    /// The flag is checked roughly every 50 milliseconds while the connection
+    /// is waiting on the server, so it can be set from another thread. Once it
+    /// is set, a cancellation request is sent over a new connection without
+    /// TLS, as with `cancel_query` called with `TlsMode::None`. The flag is
+    /// not reset afterwards. A read timeout set with
+    /// `Connection::set_read_timeout` still applies while waiting.
+    ///
+    /// Cancellation is inherently racy: if the query completes before the
+    /// server processes the request, its results are returned as normal.
+    /// Otherwise, the query fails with a `QUERY_CANCELED` error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    pub fn query_cancellable(&self, params: &[&ToSql], cancel: &Arc<AtomicBool>) -> Result<Rows> {
+        self.conn.cancellable(cancel, || self.query(params))
+    }
+
+    /// Executes a statement with a `RETURNING` clause, returning the rows it
+    /// produces.
+    ///
+    /// This is identical to `query`, but makes the intent clearer for
+    /// statements such as `INSERT`, `UPDATE` and `DELETE` which modify rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let stmt = conn.prepare("INSERT INTO foo (bar) VALUES ($1) RETURNING id").unwrap();
+    /// let rows = stmt.execute_returning(&[&"baz"]).unwrap();
+    /// let id: i32 = rows.get(0).get("id");
+    /// ```
+    pub fn execute_returning(&self, params: &[&ToSql]) -> Result<Rows> {
+        self.query(params)
+    }
+
+    /// Executes the prepared statement, writing the resulting rows to the
+    /// provided writer as CSV and returning the number of rows written.
+    ///
+    /// The first line of the output contains the names of the columns. Values
+    /// are written in the server's textual representation regardless of the
+    /// statement's result formats, and `NULL`s are written as empty unquoted
+    /// fields. Unlike `copy_out`, this works with any query returning rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let stmt = conn.prepare("SELECT 1 AS id, 'john' AS name").unwrap();
+    /// let mut buf = vec![];
+    /// stmt.write_csv(&[], &mut buf).unwrap();
+    /// assert_eq!(buf, b"id,name\n1,john\n");
+    /// ```
+    pub fn write_csv<W: Write>(&self, params: &[&ToSql], w: &mut W) -> Result<u64> {
+        check_desync!(self.conn);
+
+        let mut header = vec![];
+        for (i, column) in self.info.columns.iter().enumerate() {
+            if i != 0 {
+                header.push(b',');
+            }
+            write_csv_field(&mut header, Some(column.name().as_bytes()));
+        }
+        header.push(b'\n');
+        w.write_all(&header)?;
+
+        let mut info = (*self.info).clone();
+        info.result_formats = vec![Format::Text; info.columns.len()];
+        let stmt = Statement::new(self.conn, Arc::new(info), Cell::new(0), true);
+
+        let mut count = 0;
+        let mut result = Ok(());
+        let mut buf = vec![];
+        stmt.inner_query("", 0, params, |row| {
+            if result.is_err() {
+                return;
+            }
+            buf.clear();
+            for i in 0..row.len() {
+                if i != 0 {
+                    buf.push(b',');
+                }
+                write_csv_field(&mut buf, row.get(i));
+            }
+            buf.push(b'\n');
+            result = w.write_all(&buf);
+            count += 1;
+        })?;
+        result?;
+
+        Ok(count)
+    }
+
+    /// Like `execute`, but returns an error rather than panicking if the
+    /// number of parameters provided does not match the number expected.
+    pub fn try_execute(&self, params: &[&ToSql]) -> Result<u64> {
+        check_param_count(self.param_types(), params.len())?;
+        self.execute(params)
+    }
+
+    /// Like `query`, but returns an error rather than panicking if the
+    /// number of parameters provided does not match the number expected.
+    pub fn try_query(&self, params: &[&ToSql]) -> Result<Rows> {
+        check_param_count(self.param_types(), params.len())?;
+        self.query(params)
+    }
+
+    /// Like `execute`, but takes an iterator of parameters rather than a
+    /// slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    pub fn execute_raw<'p, I>(&self, params: I) -> Result<u64>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<ToSql + 'p>,
+    {
+        let params = params.into_iter().collect::<Vec<_>>();
+        let params = params.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        self.execute(&params)
+    }
+
+    /// Like `query`, but takes an iterator of parameters rather than a slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number
+    /// expected.
+    pub fn query_raw<'p, I>(&self, params: I) -> Result<Rows>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<ToSql + 'p>,
+    {
+        let params = params.into_iter().collect::<Vec<_>>();
+        let params = params.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        self.query(&params)
+    }
+
     /// Executes the prepared statement, returning a lazily loaded iterator
     /// over the resulting rows.
     ///
-    /// No more than `row_limit` rows will be stored in memory at a time. Rows
-    /// will be pulled from the database in batches of `row_limit` as needed.
-    /// If `row_limit` is less than or equal to 0, `lazy_query` is equivalent
-    /// to `query`.
+    /// `row_limit` is the fetch size of the underlying portal. No more than
+    /// `row_limit` rows will be stored in memory at a time. Rows will be
+    /// pulled from the database in batches of `row_limit` as needed, each
+    /// batch taking one round trip to the server. If `row_limit` is less than
+    /// or equal to 0, all rows are fetched at once and `lazy_query` is
+    /// equivalent to `query`.
     ///
     /// This can only be called inside of a transaction, and the `Transaction`
     /// object representing the active transaction must be passed to
@@ -324,42 +534,17 @@ impl<'conn> Statement<'conn> {
     /// ```
     pub fn copy_in<R: ReadWithInfo>(&self, params: &[&ToSql], r: &mut R) -> Result<u64> {
         let mut conn = self.conn.0.borrow_mut();
+        conn.log_query(&self.info.query, params);
         conn.raw_execute(
             &self.info.name,
             "",
             0,
             self.param_types(),
             params,
+            &self.info.result_formats,
         )?;
 
-        let (format, column_formats) = match conn.read_message()? {
-            backend::Message::CopyInResponse(body) => {
-                let format = body.format();
-                let column_formats = body.column_formats().map(|f| Format::from_u16(f)).collect()?;
-                (format, column_formats)
-            }
-            backend::Message::ErrorResponse(body) => {
-                conn.wait_for_ready()?;
-                return Err(err(&mut body.fields()));
-            }
-            _ => {
-                loop {
-                    if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
-                        return Err(
-                            io::Error::new(
-                                io::ErrorKind::InvalidInput,
-                                "called `copy_in` on a non-`COPY FROM STDIN` statement",
-                            ).into(),
-                        );
-                    }
-                }
-            }
-        };
-
-        let info = CopyInfo {
-            format: Format::from_u16(format as u16),
-            column_formats: column_formats,
-        };
+        let info = read_copy_in_response(&mut conn, "copy_in")?;
 
         let mut buf = [0; 16 * 1024];
         loop {
@@ -371,53 +556,59 @@ impl<'conn> Statement<'conn> {
                     )?;
                 }
                 Err(err) => {
-                    conn.stream.write_message(
-                        |buf| frontend::copy_fail("", buf),
-                    )?;
-                    conn.stream.write_message(|buf| {
-                        Ok::<(), io::Error>(frontend::copy_done(buf))
-                    })?;
-                    conn.stream.write_message(
-                        |buf| Ok::<(), io::Error>(frontend::sync(buf)),
-                    )?;
-                    conn.stream.flush()?;
-                    match conn.read_message()? {
-                        backend::Message::ErrorResponse(_) => {
-                            // expected from the CopyFail
-                        }
-                        _ => {
-                            conn.desynchronized = true;
-                            return Err(bad_response().into());
-                        }
-                    }
-                    conn.wait_for_ready()?;
+                    abort_copy_in(&mut conn)?;
                     return Err(err.into());
                 }
             }
         }
 
-        conn.stream.write_message(|buf| {
-            Ok::<(), io::Error>(frontend::copy_done(buf))
-        })?;
-        conn.stream.write_message(
-            |buf| Ok::<(), io::Error>(frontend::sync(buf)),
+        finish_copy_in(&mut conn)
+    }
+
+    /// Executes a `COPY FROM STDIN` statement, returning a writer to which
+    /// its data is written.
+    ///
+    /// This is an alternative to `copy_in` for data which is produced
+    /// incrementally. Each call to `write` sends the data to the server as
+    /// is, so the writer should usually be wrapped in a `BufWriter` when
+    /// writing many small pieces. `CopyInWriter::finish` must be called to
+    /// complete the `COPY`; if the writer is dropped without being finished,
+    /// the `COPY` is aborted and no rows are added.
+    ///
+    /// The connection must not be used for anything else until the writer
+    /// has been finished or dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use postgres::{Connection, TlsMode};
+    /// use std::io::Write;
+    ///
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let stmt = conn.prepare("COPY people FROM STDIN").unwrap();
+    /// let mut writer = stmt.copy_in_writer(&[]).unwrap();
+    /// writer.write_all(b"1\tjohn\n").unwrap();
+    /// writer.write_all(b"2\tjane\n").unwrap();
+    /// assert_eq!(writer.finish().unwrap(), 2);
+    /// ```
+    pub fn copy_in_writer<'a>(&'a self, params: &[&ToSql]) -> Result<CopyInWriter<'a>> {
+        let mut conn = self.conn.0.borrow_mut();
+        conn.log_query(&self.info.query, params);
+        conn.raw_execute(
+            &self.info.name,
+            "",
+            0,
+            self.param_types(),
+            params,
+            &self.info.result_formats,
         )?;
-        conn.stream.flush()?;
 
-        let num = match conn.read_message()? {
-            backend::Message::CommandComplete(body) => parse_update_count(body.tag()?),
-            backend::Message::ErrorResponse(body) => {
-                conn.wait_for_ready()?;
-                return Err(err(&mut body.fields()));
-            }
-            _ => {
-                conn.desynchronized = true;
-                return Err(bad_response().into());
-            }
-        };
+        read_copy_in_response(&mut conn, "copy_in_writer")?;
 
-        conn.wait_for_ready()?;
-        Ok(num)
+        Ok(CopyInWriter {
+            stmt: self,
+            finished: false,
+        })
     }
 
     /// Executes a `COPY TO STDOUT` statement, passing the resulting data to
@@ -444,70 +635,17 @@ impl<'conn> Statement<'conn> {
     /// ```
     pub fn copy_out<'a, W: WriteWithInfo>(&'a self, params: &[&ToSql], w: &mut W) -> Result<u64> {
         let mut conn = self.conn.0.borrow_mut();
+        conn.log_query(&self.info.query, params);
         conn.raw_execute(
             &self.info.name,
             "",
             0,
             self.param_types(),
             params,
+            &self.info.result_formats,
         )?;
 
-        let (format, column_formats) = match conn.read_message()? {
-            backend::Message::CopyOutResponse(body) => {
-                let format = body.format();
-                let column_formats = body.column_formats().map(|f| Format::from_u16(f)).collect()?;
-                (format, column_formats)
-            }
-            backend::Message::CopyInResponse(_) => {
-                conn.stream.write_message(
-                    |buf| frontend::copy_fail("", buf),
-                )?;
-                conn.stream.write_message(|buf| {
-                    Ok::<(), io::Error>(frontend::copy_done(buf))
-                })?;
-                conn.stream.write_message(
-                    |buf| Ok::<(), io::Error>(frontend::sync(buf)),
-                )?;
-                conn.stream.flush()?;
-                match conn.read_message()? {
-                    backend::Message::ErrorResponse(_) => {
-                        // expected from the CopyFail
-                    }
-                    _ => {
-                        conn.desynchronized = true;
-                        return Err(bad_response().into());
-                    }
-                }
-                conn.wait_for_ready()?;
-                return Err(
-                    io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        "called `copy_out` on a non-`COPY TO STDOUT` statement",
-                    ).into(),
-                );
-            }
-            backend::Message::ErrorResponse(body) => {
-                conn.wait_for_ready()?;
-                return Err(err(&mut body.fields()));
-            }
-            _ => {
-                loop {
-                    if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
-                        return Err(
-                            io::Error::new(
-                                io::ErrorKind::InvalidInput,
-                                "called `copy_out` on a  non-`COPY TO STDOUT` statement",
-                            ).into(),
-                        );
-                    }
-                }
-            }
-        };
-
-        let info = CopyInfo {
-            format: Format::from_u16(format as u16),
-            column_formats: column_formats,
-        };
+        let info = read_copy_out_response(&mut conn, "copy_out")?;
 
         let count;
         loop {
@@ -535,11 +673,8 @@ impl<'conn> Statement<'conn> {
                     break;
                 }
                 backend::Message::ErrorResponse(body) => {
-                    loop {
-                        if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
-                            return Err(err(&mut body.fields()));
-                        }
-                    }
+                    conn.wait_for_ready()?;
+                    return Err(err(&mut body.fields()));
                 }
                 _ => {
                     loop {
@@ -555,6 +690,130 @@ impl<'conn> Statement<'conn> {
         Ok(count)
     }
 
+    /// Executes a `COPY ... TO STDOUT (FORMAT binary)` statement, returning a
+    /// fallible iterator over the decoded rows.
+    ///
+    /// Binary `COPY` output does not describe the types of its fields, so they
+    /// must be provided in `types`, one per column of the output. Rows are
+    /// read from the server as they are requested, so a large table can be
+    /// exported without buffering all of it.
+    ///
+    /// The connection must not be used for anything else until the iterator
+    /// has been exhausted or dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # extern crate fallible_iterator;
+    /// # extern crate postgres;
+    /// # use fallible_iterator::FallibleIterator;
+    /// # use postgres::{Connection, TlsMode};
+    /// # use postgres::types::{INT4, TEXT};
+    /// # fn main() {
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let stmt = conn.prepare("COPY people TO STDOUT (FORMAT binary)").unwrap();
+    /// let mut rows = stmt.copy_out_binary(&[], &[INT4, TEXT]).unwrap();
+    /// while let Some(row) = rows.next().unwrap() {
+    ///     let id: i32 = row.get(0);
+    ///     let name: String = row.get(1);
+    ///     println!("{}: {}", id, name);
+    /// }
+    /// # }
+    /// ```
+    pub fn copy_out_binary<'a>(
+        &'a self,
+        params: &[&ToSql],
+        types: &[Type],
+    ) -> Result<BinaryCopyRows<'a>> {
+        let mut conn = self.conn.0.borrow_mut();
+        conn.log_query(&self.info.query, params);
+        conn.raw_execute(
+            &self.info.name,
+            "",
+            0,
+            self.param_types(),
+            params,
+            &self.info.result_formats,
+        )?;
+
+        let info = read_copy_out_response(&mut conn, "copy_out_binary")?;
+        if info.format() != Format::Binary {
+            loop {
+                if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
+                    return Err(
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "called `copy_out_binary` on a text `COPY TO STDOUT` statement",
+                        ).into(),
+                    );
+                }
+            }
+        }
+
+        Ok(BinaryCopyRows::new(self.conn, types))
+    }
+
+    /// Executes a `COPY TO STDOUT` statement, returning a fallible iterator
+    /// over the lines of its output.
+    ///
+    /// Lines are read from the server as they are requested, so a large table
+    /// can be exported without buffering all of it. Each line is returned
+    /// without its trailing newline. The output must be in the text or CSV
+    /// format; note that quoted CSV values may themselves contain newlines.
+    ///
+    /// The connection must not be used for anything else until the iterator
+    /// has been exhausted or dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # extern crate fallible_iterator;
+    /// # extern crate postgres;
+    /// # use fallible_iterator::FallibleIterator;
+    /// # use postgres::{Connection, TlsMode};
+    /// # fn main() {
+    /// # let conn = Connection::connect("", TlsMode::None).unwrap();
+    /// let stmt = conn.prepare("COPY (SELECT row_to_json(people) FROM people) TO STDOUT").unwrap();
+    /// let mut lines = stmt.copy_out_lines(&[]).unwrap();
+    /// while let Some(line) = lines.next().unwrap() {
+    ///     println!("{}", String::from_utf8_lossy(&line));
+    /// }
+    /// # }
+    /// ```
+    pub fn copy_out_lines<'a>(&'a self, params: &[&ToSql]) -> Result<CopyOutLines<'a>> {
+        let mut conn = self.conn.0.borrow_mut();
+        conn.log_query(&self.info.query, params);
+        conn.raw_execute(
+            &self.info.name,
+            "",
+            0,
+            self.param_types(),
+            params,
+            &self.info.result_formats,
+        )?;
+
+        let info = read_copy_out_response(&mut conn, "copy_out_lines")?;
+        if info.format() == Format::Binary {
+            loop {
+                if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
+                    return Err(
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "called `copy_out_lines` on a binary `COPY TO STDOUT` statement",
+                        ).into(),
+                    );
+                }
+            }
+        }
+
+        Ok(CopyOutLines {
+            stmt: self,
+            buf: vec![],
+            pos: 0,
+            done: false,
+        })
+    }
+
     /// Consumes the statement, clearing it from the Postgres session.
     ///
     /// If this statement was created via the `prepare_cached` method, `finish`
@@ -565,6 +824,389 @@ impl<'conn> Statement<'conn> {
     pub fn finish(mut self) -> Result<()> {
         self.finish_inner()
     }
+
+    /// Converts the statement into an `OwnedStatement` which does not borrow
+    /// the connection.
+    ///
+    /// The statement is not closed, and can be used again by binding it to
+    /// the connection which prepared it.
+    pub fn into_owned(mut self) -> OwnedStatement {
+        self.finished = true;
+        OwnedStatement {
+            info: self.info.clone(),
+            conn_id: self.conn.0.borrow().id,
+        }
+    }
+}
+
+/// A prepared statement which is not tied to the lifetime of a connection.
+///
+/// Unlike a `Statement`, an `OwnedStatement` can be stored alongside the
+/// `Connection` that prepared it, or sent to another thread with that
+/// connection. It has to be bound to the connection to be executed.
+///
+/// The statement is not closed when the `OwnedStatement` is dropped; use the
+/// `close` method to do so.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use postgres::{Connection, TlsMode};
+/// use postgres::stmt::OwnedStatement;
+///
+/// struct Queries {
+///     conn: Connection,
+///     get_user: OwnedStatement,
+/// }
+///
+/// let conn = Connection::connect("postgres://postgres@localhost", TlsMode::None).unwrap();
+/// let get_user = conn.prepare("SELECT name FROM users WHERE id = $1").unwrap().into_owned();
+/// let queries = Queries {
+///     conn: conn,
+///     get_user: get_user,
+/// };
+///
+/// let stmt = queries.get_user.bind(&queries.conn).unwrap();
+/// for row in &stmt.query(&[&1i32]).unwrap() {
+///     let name: String = row.get(0);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct OwnedStatement {
+    info: Arc<StatementInfo>,
+    conn_id: usize,
+}
+
+impl fmt::Debug for OwnedStatement {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.info, fmt)
+    }
+}
+
+impl OwnedStatement {
+    /// Returns a slice containing the expected parameter types.
+    pub fn param_types(&self) -> &[Type] {
+        &self.info.param_types
+    }
+
+    /// Returns a slice describing the columns of the result of the query.
+    pub fn columns(&self) -> &[Column] {
+        &self.info.columns
+    }
+
+    /// Returns a `Statement` which can be used to execute the statement on
+    /// the connection.
+    ///
+    /// Returns an error if `conn` is not the connection which prepared the
+    /// statement. Dropping the returned `Statement` does not close it.
+    pub fn bind<'a>(&self, conn: &'a Connection) -> Result<Statement<'a>> {
+        self.bind_inner(conn, true)
+    }
+
+    /// Closes the statement.
+    ///
+    /// If the statement was created via the `prepare_cached` method, it is
+    /// left open in the connection's cache and `close` does nothing.
+    ///
+    /// Returns an error if `conn` is not the connection which prepared the
+    /// statement.
+    pub fn close(self, conn: &Connection) -> Result<()> {
+        self.bind_inner(conn, self.info.cached)?.finish()
+    }
+
+    fn bind_inner<'a>(&self, conn: &'a Connection, finished: bool) -> Result<Statement<'a>> {
+        if conn.0.borrow().id != self.conn_id {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the statement was prepared by a different connection",
+            ).into());
+        }
+
+        Ok(Statement::new(conn, self.info.clone(), Cell::new(0), finished))
+    }
+}
+
+/// A fallible iterator over the lines of `COPY TO STDOUT` output.
+///
+/// Returned by `Statement::copy_out_lines`.
+pub struct CopyOutLines<'a> {
+    stmt: &'a Statement<'a>,
+    buf: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<'a> fmt::Debug for CopyOutLines<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CopyOutLines")
+            .field("statement", &self.stmt)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<'a> Drop for CopyOutLines<'a> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        let mut conn = self.stmt.conn.0.borrow_mut();
+        if conn.is_desynchronized() {
+            return;
+        }
+        loop {
+            match conn.read_message() {
+                Ok(backend::Message::ReadyForQuery(_)) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+    }
+}
+
+impl<'a> FallibleIterator for CopyOutLines<'a> {
+    type Item = Vec<u8>;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            // a line may be split across any number of CopyData messages
+            if let Some(i) = self.buf[self.pos..].iter().position(|&b| b == b'\n') {
+                let line = self.buf[self.pos..self.pos + i].to_vec();
+                self.pos += i + 1;
+                return Ok(Some(line));
+            }
+
+            if self.done {
+                if self.pos == self.buf.len() {
+                    return Ok(None);
+                }
+                let line = self.buf[self.pos..].to_vec();
+                self.pos = self.buf.len();
+                return Ok(Some(line));
+            }
+
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+
+            let mut conn = self.stmt.conn.0.borrow_mut();
+            check_desync!(conn);
+            match conn.read_message()? {
+                backend::Message::CopyData(body) => self.buf.extend_from_slice(body.data()),
+                backend::Message::CopyDone => {}
+                backend::Message::CommandComplete(_) => {
+                    self.done = true;
+                    conn.wait_for_ready()?;
+                }
+                backend::Message::ErrorResponse(body) => {
+                    self.done = true;
+                    self.buf.clear();
+                    conn.wait_for_ready()?;
+                    return Err(err(&mut body.fields()));
+                }
+                _ => {
+                    self.done = true;
+                    self.buf.clear();
+                    loop {
+                        if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
+                            return Err(bad_response().into());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A writer for the data of a `COPY FROM STDIN` statement.
+///
+/// Returned by `Statement::copy_in_writer`.
+pub struct CopyInWriter<'a> {
+    stmt: &'a Statement<'a>,
+    finished: bool,
+}
+
+impl<'a> fmt::Debug for CopyInWriter<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CopyInWriter")
+            .field("statement", &self.stmt)
+            .finish()
+    }
+}
+
+impl<'a> Drop for CopyInWriter<'a> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        let mut conn = self.stmt.conn.0.borrow_mut();
+        if !conn.is_desynchronized() {
+            let _ = abort_copy_in(&mut conn);
+        }
+    }
+}
+
+impl<'a> CopyInWriter<'a> {
+    /// Completes the `COPY`, returning the number of rows added.
+    pub fn finish(mut self) -> Result<u64> {
+        self.finished = true;
+        let mut conn = self.stmt.conn.0.borrow_mut();
+        check_desync!(conn);
+        finish_copy_in(&mut conn)
+    }
+}
+
+impl<'a> Write for CopyInWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut conn = self.stmt.conn.0.borrow_mut();
+        if conn.is_desynchronized() {
+            return Err(desynchronized());
+        }
+        conn.stream.write_message(|out| frontend::copy_data(buf, out))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stmt.conn.0.borrow_mut().stream.flush()
+    }
+}
+
+fn read_copy_in_response(conn: &mut InnerConnection, method: &str) -> Result<CopyInfo> {
+    let (format, column_formats) = match conn.read_message()? {
+        backend::Message::CopyInResponse(body) => {
+            let format = body.format();
+            let column_formats = body.column_formats().map(|f| Format::from_u16(f)).collect()?;
+            (format, column_formats)
+        }
+        backend::Message::ErrorResponse(body) => {
+            conn.wait_for_ready()?;
+            return Err(err(&mut body.fields()));
+        }
+        _ => {
+            loop {
+                if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
+                    return Err(
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("called `{}` on a non-`COPY FROM STDIN` statement", method),
+                        ).into(),
+                    );
+                }
+            }
+        }
+    };
+
+    Ok(CopyInfo {
+        format: Format::from_u16(format as u16),
+        column_formats: column_formats,
+    })
+}
+
+// Returns the connection to the ready state after the data of a
+// COPY FROM STDIN have been sent.
+fn finish_copy_in(conn: &mut InnerConnection) -> Result<u64> {
+    conn.stream.write_message(|buf| {
+        Ok::<(), io::Error>(frontend::copy_done(buf))
+    })?;
+    conn.stream.write_message(
+        |buf| Ok::<(), io::Error>(frontend::sync(buf)),
+    )?;
+    conn.stream.flush()?;
+
+    let num = match conn.read_message()? {
+        backend::Message::CommandComplete(body) => parse_update_count(body.tag()?),
+        backend::Message::ErrorResponse(body) => {
+            conn.wait_for_ready()?;
+            return Err(err(&mut body.fields()));
+        }
+        _ => {
+            conn.desynchronized = true;
+            return Err(bad_response().into());
+        }
+    };
+
+    conn.wait_for_ready()?;
+    Ok(num)
+}
+
+fn abort_copy_in(conn: &mut InnerConnection) -> Result<()> {
+    conn.stream.write_message(
+        |buf| frontend::copy_fail("", buf),
+    )?;
+    conn.stream.write_message(|buf| {
+        Ok::<(), io::Error>(frontend::copy_done(buf))
+    })?;
+    conn.stream.write_message(
+        |buf| Ok::<(), io::Error>(frontend::sync(buf)),
+    )?;
+    conn.stream.flush()?;
+    match conn.read_message()? {
+        backend::Message::ErrorResponse(_) => {
+            // expected from the CopyFail
+        }
+        _ => {
+            conn.desynchronized = true;
+            return Err(bad_response().into());
+        }
+    }
+    conn.wait_for_ready()
+}
+
+fn read_copy_out_response(conn: &mut InnerConnection, method: &str) -> Result<CopyInfo> {
+    let (format, column_formats) = match conn.read_message()? {
+        backend::Message::CopyOutResponse(body) => {
+            let format = body.format();
+            let column_formats = body.column_formats().map(|f| Format::from_u16(f)).collect()?;
+            (format, column_formats)
+        }
+        backend::Message::CopyInResponse(_) => {
+            conn.stream.write_message(
+                |buf| frontend::copy_fail("", buf),
+            )?;
+            conn.stream.write_message(|buf| {
+                Ok::<(), io::Error>(frontend::copy_done(buf))
+            })?;
+            conn.stream.write_message(
+                |buf| Ok::<(), io::Error>(frontend::sync(buf)),
+            )?;
+            conn.stream.flush()?;
+            match conn.read_message()? {
+                backend::Message::ErrorResponse(_) => {
+                    // expected from the CopyFail
+                }
+                _ => {
+                    conn.desynchronized = true;
+                    return Err(bad_response().into());
+                }
+            }
+            conn.wait_for_ready()?;
+            return Err(not_copy_out(method).into());
+        }
+        backend::Message::ErrorResponse(body) => {
+            conn.wait_for_ready()?;
+            return Err(err(&mut body.fields()));
+        }
+        _ => {
+            loop {
+                if let backend::Message::ReadyForQuery(_) = conn.read_message()? {
+                    return Err(not_copy_out(method).into());
+                }
+            }
+        }
+    };
+
+    Ok(CopyInfo {
+        format: Format::from_u16(format as u16),
+        column_formats: column_formats,
+    })
+}
+
+fn not_copy_out(method: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("called `{}` on a non-`COPY TO STDOUT` statement", method),
+    )
 }
 
 fn fill_copy_buf<R: ReadWithInfo>(buf: &mut [u8], r: &mut R, info: &CopyInfo) -> io::Result<usize> {
@@ -580,6 +1222,31 @@ fn fill_copy_buf<R: ReadWithInfo>(buf: &mut [u8], r: &mut R, info: &CopyInfo) ->
     Ok(nread)
 }
 
+// Writes a CSV field, quoting it if necessary. `None` is written as an empty,
+// unquoted field, which distinguishes it from the empty string.
+fn write_csv_field(buf: &mut Vec<u8>, value: Option<&[u8]>) {
+    let value = match value {
+        Some(value) => value,
+        None => return,
+    };
+
+    let quote = value.is_empty() ||
+        value.iter().any(|&b| b == b',' || b == b'"' || b == b'\n' || b == b'\r');
+    if !quote {
+        buf.extend_from_slice(value);
+        return;
+    }
+
+    buf.push(b'"');
+    for &b in value {
+        if b == b'"' {
+            buf.push(b'"');
+        }
+        buf.push(b);
+    }
+    buf.push(b'"');
+}
+
 /// A struct containing information relevant for a `COPY` operation.
 pub struct CopyInfo {
     format: Format,
@@ -626,7 +1293,7 @@ impl<W: Write> WriteWithInfo for W {
     }
 }
 
-/// The format of a portion of COPY query data.
+/// The format of a portion of query or COPY data.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Format {
     /// A text based format.
@@ -642,8 +1309,149 @@ impl Format {
             _ => Format::Binary,
         }
     }
+
+    pub(crate) fn to_i16(&self) -> i16 {
+        match *self {
+            Format::Text => 0,
+            Format::Binary => 1,
+        }
+    }
+}
+
+/// The format of the data passed to a `COPY` statement.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CopyFormat {
+    /// The default, tab-delimited text format.
+    Text,
+    /// The comma-separated values format.
+    Csv,
+    /// The binary format.
+    Binary,
+}
+
+/// Options for a `COPY FROM STDIN` statement executed by
+/// `Connection::copy_in`.
+///
+/// Options which are not set are left at the server's defaults.
+#[derive(Debug, Clone, Default)]
+pub struct CopyOptions {
+    format: Option<CopyFormat>,
+    delimiter: Option<String>,
+    null: Option<String>,
+    quote: Option<String>,
+    escape: Option<String>,
+    header: bool,
+}
+
+impl CopyOptions {
+    /// Creates a new set of options.
+    pub fn new() -> CopyOptions {
+        CopyOptions::default()
+    }
+
+    /// Sets the format of the data.
+    pub fn format(&mut self, format: CopyFormat) -> &mut CopyOptions {
+        self.format = Some(format);
+        self
+    }
+
+    /// Sets the character which separates columns.
+    ///
+    /// It must be a single one-byte character.
+    pub fn delimiter(&mut self, delimiter: &str) -> &mut CopyOptions {
+        self.delimiter = Some(delimiter.to_owned());
+        self
+    }
+
+    /// Sets the string which represents a null value.
+    pub fn null(&mut self, null: &str) -> &mut CopyOptions {
+        self.null = Some(null.to_owned());
+        self
+    }
+
+    /// Sets the character used to quote values in the CSV format.
+    ///
+    /// It must be a single one-byte character.
+    pub fn quote(&mut self, quote: &str) -> &mut CopyOptions {
+        self.quote = Some(quote.to_owned());
+        self
+    }
+
+    /// Sets the character used to escape the quote character in the CSV
+    /// format.
+    ///
+    /// It must be a single one-byte character.
+    pub fn escape(&mut self, escape: &str) -> &mut CopyOptions {
+        self.escape = Some(escape.to_owned());
+        self
+    }
+
+    /// Determines if the first line of CSV data is a header which should be
+    /// ignored.
+    pub fn header(&mut self, header: bool) -> &mut CopyOptions {
+        self.header = header;
+        self
+    }
+
+    pub(crate) fn copy_in_query(&self, target: &str) -> Result<String> {
+        let mut options = vec![];
+
+        if let Some(format) = self.format {
+            let format = match format {
+                CopyFormat::Text => "text",
+                CopyFormat::Csv => "csv",
+                CopyFormat::Binary => "binary",
+            };
+            options.push(format!("FORMAT {}", format));
+        }
+        if let Some(ref delimiter) = self.delimiter {
+            options.push(format!("DELIMITER {}", single_byte_literal("delimiter", delimiter)?));
+        }
+        if let Some(ref null) = self.null {
+            options.push(format!("NULL {}", escape_literal(null)?));
+        }
+        if let Some(ref quote) = self.quote {
+            options.push(format!("QUOTE {}", single_byte_literal("quote", quote)?));
+        }
+        if let Some(ref escape) = self.escape {
+            options.push(format!("ESCAPE {}", single_byte_literal("escape", escape)?));
+        }
+        if self.header {
+            options.push("HEADER".to_owned());
+        }
+
+        let mut query = format!("COPY {} FROM STDIN", target);
+        if !options.is_empty() {
+            query.push_str(" WITH (");
+            query.push_str(&options.join(", "));
+            query.push(')');
+        }
+        Ok(query)
+    }
 }
 
-fn parse_update_count(tag: &str) -> u64 {
+fn single_byte_literal(option: &str, value: &str) -> Result<String> {
+    if value.len() != 1 {
+        let msg = format!("the {} must be a single one-byte character", option);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into());
+    }
+
+    escape_literal(value)
+}
+
+pub(crate) fn parse_update_count(tag: &str) -> u64 {
     tag.split(' ').last().unwrap().parse().unwrap_or(0)
 }
+
+// The tag of an `INSERT` is `INSERT <oid> <rows>`, where the OID is 0 unless a
+// single row was inserted into a table with OIDs.
+fn parse_insert_oid(tag: &str) -> Option<Oid> {
+    let mut parts = tag.split(' ');
+    if parts.next() != Some("INSERT") {
+        return None;
+    }
+    match parts.next().and_then(|oid| oid.parse().ok()) {
+        Some(0) | None => None,
+        Some(oid) => Some(oid),
+    }
+}
@@ -5,10 +5,10 @@ use std::fmt;
 #[allow(unused_imports)]
 use std::ascii::AsciiExt;
 
-use {bad_response, Connection, Result};
+use {bad_response, escape_identifier, Connection, Result};
 use rows::Rows;
-use stmt::Statement;
-use types::ToSql;
+use stmt::{CopyOptions, ReadWithInfo, Statement};
+use types::{FromSql, ToSql};
 
 /// An enumeration of transaction isolation levels.
 ///
@@ -56,6 +56,28 @@ impl IsolationLevel {
     }
 }
 
+/// The transaction status of a connection, as reported by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionStatus {
+    /// The connection is not in a transaction block.
+    Idle,
+    /// The connection is in a transaction block.
+    InTransaction,
+    /// The connection is in a failed transaction block, and all statements
+    /// will be rejected until it is rolled back.
+    Failed,
+}
+
+impl TransactionStatus {
+    pub(crate) fn from_u8(status: u8) -> TransactionStatus {
+        match status {
+            b'T' => TransactionStatus::InTransaction,
+            b'E' => TransactionStatus::Failed,
+            _ => TransactionStatus::Idle,
+        }
+    }
+}
+
 /// Configuration of a transaction.
 #[derive(Debug)]
 pub struct Config {
@@ -195,7 +217,14 @@ impl<'conn> Transaction<'conn> {
             (false, &Some(ref sp)) => conn.quick_query(&format!("ROLLBACK TO {}", sp))?,
             (false, &None) => conn.quick_query("ROLLBACK")?,
             (true, &Some(ref sp)) => conn.quick_query(&format!("RELEASE {}", sp))?,
-            (true, &None) => conn.quick_query("COMMIT")?,
+            (true, &None) => conn.quick_query("COMMIT").or_else(|e| {
+                // The server normally ends the transaction itself when a
+                // COMMIT fails, but make sure the connection is left idle.
+                if conn.transaction_status != TransactionStatus::Idle {
+                    let _ = conn.quick_query("ROLLBACK");
+                }
+                Err(e)
+            })?,
         };
 
         Ok(())
@@ -206,6 +235,11 @@ impl<'conn> Transaction<'conn> {
         self.conn.prepare(query)
     }
 
+    /// Like `Connection::prepare_all`.
+    pub fn prepare_all(&self, queries: &[&str]) -> Result<Vec<Statement<'conn>>> {
+        self.conn.prepare_all(queries)
+    }
+
     /// Like `Connection::prepare_cached`.
     ///
     /// # Note
@@ -226,11 +260,85 @@ impl<'conn> Transaction<'conn> {
         self.conn.query(query, params)
     }
 
+    /// Like `Connection::query_scalar`.
+    pub fn query_scalar<T>(&self, query: &str, params: &[&ToSql]) -> Result<T>
+    where
+        T: FromSql,
+    {
+        self.conn.query_scalar(query, params)
+    }
+
+    /// Like `Connection::try_execute`.
+    pub fn try_execute(&self, query: &str, params: &[&ToSql]) -> Result<u64> {
+        self.conn.try_execute(query, params)
+    }
+
+    /// Like `Connection::try_query`.
+    pub fn try_query<'a>(&'a self, query: &str, params: &[&ToSql]) -> Result<Rows> {
+        self.conn.try_query(query, params)
+    }
+
+    /// Like `Connection::execute_raw`.
+    pub fn execute_raw<'p, I>(&self, query: &str, params: I) -> Result<u64>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<ToSql + 'p>,
+    {
+        self.conn.execute_raw(query, params)
+    }
+
+    /// Like `Connection::query_raw`.
+    pub fn query_raw<'p, I>(&self, query: &str, params: I) -> Result<Rows>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<ToSql + 'p>,
+    {
+        self.conn.query_raw(query, params)
+    }
+
     /// Like `Connection::batch_execute`.
     pub fn batch_execute(&self, query: &str) -> Result<()> {
         self.conn.batch_execute(query)
     }
 
+    /// Like `Connection::copy_in`.
+    pub fn copy_in<R: ReadWithInfo>(
+        &self,
+        target: &str,
+        options: &CopyOptions,
+        r: &mut R,
+    ) -> Result<u64> {
+        self.conn.copy_in(target, options, r)
+    }
+
+    /// Fetches up to `count` rows from the cursor named `name`.
+    ///
+    /// This is typically used to read the results of a function returning a
+    /// `REFCURSOR`, whose value is the name of the cursor. Cursors are only
+    /// valid for the duration of the transaction they were opened in. An
+    /// empty set of rows is returned once the cursor is exhausted.
+    pub fn fetch_cursor(&self, name: &str, count: i64) -> Result<Rows> {
+        let query = format!("FETCH {} FROM {}", count, escape_identifier(name)?);
+        self.query(&query, &[])
+    }
+
+    /// Acquires a transaction-level advisory lock, blocking until it is
+    /// available.
+    ///
+    /// The lock is released when the outermost transaction ends, and cannot
+    /// otherwise be released.
+    pub fn advisory_lock(&self, key: i64) -> Result<()> {
+        let stmt = self.prepare_cached("SELECT pg_catalog.pg_advisory_xact_lock($1)")?;
+        stmt.execute(&[&key]).map(|_| ())
+    }
+
+    /// Attempts to acquire a transaction-level advisory lock without
+    /// blocking, returning `true` if it was acquired.
+    pub fn try_advisory_lock(&self, key: i64) -> Result<bool> {
+        let stmt = self.prepare_cached("SELECT pg_catalog.pg_try_advisory_xact_lock($1)")?;
+        Ok(stmt.query(&[&key])?.get(0).get(0))
+    }
+
     /// Like `Connection::transaction`, but creates a nested transaction via
     /// a savepoint.
     ///
@@ -265,6 +373,33 @@ impl<'conn> Transaction<'conn> {
         })
     }
 
+    /// Runs a closure within a savepoint with the specified name.
+    ///
+    /// The savepoint is released if the closure returns `Ok`, and rolled back
+    /// to if it returns `Err`, so that a failure only discards the work done
+    /// by the closure rather than that of the whole transaction. The name is
+    /// quoted, so it may contain arbitrary characters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is an active nested transaction.
+    pub fn with_savepoint<F, T>(&self, name: &str, f: F) -> Result<T>
+    where
+        F: FnOnce(&Transaction) -> Result<T>,
+    {
+        let savepoint = self.savepoint(&escape_identifier(name)?)?;
+        match f(&savepoint) {
+            Ok(v) => {
+                savepoint.commit()?;
+                Ok(v)
+            }
+            Err(e) => {
+                let _ = savepoint.finish();
+                Err(e)
+            }
+        }
+    }
+
     /// Returns a reference to the `Transaction`'s `Connection`.
     pub fn connection(&self) -> &'conn Connection {
         self.conn
@@ -279,7 +414,7 @@ impl<'conn> Transaction<'conn> {
     pub fn set_config(&self, config: &Config) -> Result<()> {
         let mut command = "SET TRANSACTION".to_owned();
         config.build_command(&mut command);
-        self.batch_execute(&command)
+        self.conn.0.borrow_mut().quick_query(&command).map(|_| ())
     }
 
     /// Determines if the transaction is currently set to commit or roll back.
@@ -298,6 +433,9 @@ impl<'conn> Transaction<'conn> {
     }
 
     /// A convenience method which consumes and commits a transaction.
+    ///
+    /// If the commit fails, for example because of a deferred constraint
+    /// violation, the transaction is rolled back and the error returned.
     pub fn commit(self) -> Result<()> {
         self.set_commit();
         self.finish()
@@ -4,6 +4,7 @@ extern crate native_tls;
 #[cfg(feature = "with-openssl")]
 extern crate openssl;
 extern crate postgres;
+extern crate postgres_protocol;
 #[macro_use]
 extern crate postgres_shared;
 #[cfg(feature = "with-security-framework")]
@@ -12,16 +13,29 @@ extern crate url;
 
 use fallible_iterator::FallibleIterator;
 use postgres::{Connection, GenericConnection, HandleNotice, TlsMode};
-use postgres::transaction::{self, IsolationLevel};
-use postgres::error::{DbError, CARDINALITY_VIOLATION, INVALID_CATALOG_NAME, INVALID_PASSWORD,
-                      QUERY_CANCELED, SYNTAX_ERROR, UNDEFINED_TABLE};
-use postgres::types::{FLOAT8, INT4, Kind, Oid, Type, WrongType, VARCHAR};
+use postgres::transaction::{self, IsolationLevel, TransactionStatus};
+use postgres::error::{DbError, ACTIVE_SQL_TRANSACTION, ADMIN_SHUTDOWN, CARDINALITY_VIOLATION,
+                      FOREIGN_KEY_VIOLATION, IN_FAILED_SQL_TRANSACTION, INVALID_CATALOG_NAME,
+                      INVALID_PASSWORD, NOT_NULL_VIOLATION, QUERY_CANCELED,
+                      READ_ONLY_SQL_TRANSACTION, SYNTAX_ERROR, UNDEFINED_TABLE, UNIQUE_VIOLATION};
+use postgres::rows::{FromRow, Row};
+use postgres::types::{FLOAT8, INT4, FromSql, Kind, Lsn, Oid, TEXT, ToSql, Type, WrongType, VARCHAR};
 use postgres::error::ErrorPosition::Normal;
-use postgres::notification::Notification;
-use postgres::params::IntoConnectParams;
+use postgres::large_objects::{self, LargeObject, Mode};
+use postgres::notification::{Notification, OverflowPolicy};
+use postgres::stmt::{CopyFormat, CopyOptions, Format, OwnedStatement};
+use postgres::params::{ConnectParams, Connector, Host, IntoConnectParams};
+use postgres::replication::{ReplicationConnection, ReplicationMessage};
+use postgres::tls::{Stream, TlsHandshake, TlsStream};
+use std::env;
+use std::error::Error as StdError;
+use std::fs::{self, File};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::io;
-use std::time::Duration;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
 
 macro_rules! or_panic {
     ($e:expr) => (
@@ -81,6 +95,221 @@ fn test_connection_finish() {
     assert!(conn.finish().is_ok());
 }
 
+#[test]
+fn test_connect_timeout_startup() {
+    // accepts the TCP connection but never responds to the startup message
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let (mut socket, _) = listener.accept().unwrap();
+        let mut buf = vec![];
+        let _ = socket.read_to_end(&mut buf);
+    });
+
+    let params = ConnectParams::builder()
+        .port(port)
+        .user("postgres", None)
+        .connect_timeout(Some(Duration::from_millis(500)))
+        .build(Host::Tcp("127.0.0.1".to_owned()));
+    match Connection::connect(params, TlsMode::None) {
+        Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::TimedOut) => {}
+        Err(e) => panic!("unexpected error {:?}", e),
+        Ok(_) => panic!("unexpected success"),
+    }
+
+    server.join().unwrap();
+}
+
+// A minimal SOCKS5 proxy which requires username/password authentication and
+// forwards the first connection it accepts successfully.
+fn socks5_proxy() -> (u16, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let proxy = thread::spawn(move || {
+        let mut targets = vec![];
+        for socket in listener.incoming() {
+            let mut socket = socket.unwrap();
+
+            let mut buf = [0; 2];
+            socket.read_exact(&mut buf).unwrap();
+            let mut methods = vec![0; buf[1] as usize];
+            socket.read_exact(&mut methods).unwrap();
+            if !methods.contains(&2) {
+                socket.write_all(&[5, 0xff]).unwrap();
+                continue;
+            }
+            socket.write_all(&[5, 2]).unwrap();
+
+            let mut buf = [0; 2];
+            socket.read_exact(&mut buf).unwrap();
+            let mut name = vec![0; buf[1] as usize];
+            socket.read_exact(&mut name).unwrap();
+            let mut len = [0; 1];
+            socket.read_exact(&mut len).unwrap();
+            let mut password = vec![0; len[0] as usize];
+            socket.read_exact(&mut password).unwrap();
+            if name != b"proxyuser" || password != b"hunter2" {
+                socket.write_all(&[1, 1]).unwrap();
+                continue;
+            }
+            socket.write_all(&[1, 0]).unwrap();
+
+            let mut buf = [0; 5];
+            socket.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf[..4], &[5, 1, 0, 3]);
+            let mut host = vec![0; buf[4] as usize + 2];
+            socket.read_exact(&mut host).unwrap();
+            let port = (host[host.len() - 2] as u16) << 8 | host[host.len() - 1] as u16;
+            let host = String::from_utf8(host[..host.len() - 2].to_vec()).unwrap();
+            targets.push(format!("{}:{}", host, port));
+
+            let server = TcpStream::connect((&*host, port)).unwrap();
+            socket.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0]).unwrap();
+
+            let mut socket_read = socket.try_clone().unwrap();
+            let mut server_write = server.try_clone().unwrap();
+            let forward = thread::spawn(move || {
+                let _ = io::copy(&mut socket_read, &mut server_write);
+                let _ = server_write.shutdown(Shutdown::Write);
+            });
+            let mut server_read = server;
+            let _ = io::copy(&mut server_read, &mut socket);
+            forward.join().unwrap();
+            break;
+        }
+        targets
+    });
+    (port, proxy)
+}
+
+#[test]
+fn test_connect_socks5_proxy() {
+    let (proxy_port, proxy) = socks5_proxy();
+
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .proxy("127.0.0.1", proxy_port, None)
+        .build(Host::Tcp("localhost".to_owned()));
+    match Connection::connect(params, TlsMode::None) {
+        Err(ref e) if e.as_io().is_some() => {
+            assert!(e.to_string().contains("authentication methods"), "{}", e);
+        }
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .proxy("127.0.0.1", proxy_port, Some(("proxyuser", "wrong")))
+        .build(Host::Tcp("localhost".to_owned()));
+    match Connection::connect(params, TlsMode::None) {
+        Err(ref e) if e.as_io().is_some() => {
+            assert!(e.to_string().contains("rejected the credentials"), "{}", e);
+        }
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .proxy("127.0.0.1", proxy_port, Some(("proxyuser", "hunter2")))
+        .build(Host::Tcp("localhost".to_owned()));
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+    let rows = or_panic!(conn.query("SELECT 1::INT4", &[]));
+    assert_eq!(rows.get(0).get::<_, i32>(0), 1);
+    or_panic!(conn.finish());
+
+    assert_eq!(proxy.join().unwrap(), ["localhost:5433"]);
+}
+
+#[test]
+fn test_connect_timeout_cleared() {
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .connect_timeout(Some(Duration::from_millis(500)))
+        .build(Host::Tcp("localhost".to_owned()));
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+    or_panic!(conn.execute("SELECT pg_sleep(1)", &[]));
+}
+
+#[test]
+fn test_protocol_version() {
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .build(Host::Tcp("localhost".to_owned()));
+    assert_eq!(params.protocol_version(), (3, 0));
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+    or_panic!(conn.execute("SELECT 1", &[]));
+
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .protocol_version(3, 9999)
+        .build(Host::Tcp("localhost".to_owned()));
+    match Connection::connect(params, TlsMode::None) {
+        Err(e) => assert!(e.to_string().contains("newest minor version"), "{}", e),
+        Ok(_) => panic!("unexpected success"),
+    }
+
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .protocol_version(9999, 0)
+        .build(Host::Tcp("localhost".to_owned()));
+    match Connection::connect(params, TlsMode::None) {
+        Err(ref e) if e.as_db().is_some() => {}
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_protocol_extensions() {
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .option("_pq_.rust_postgres_unknown", "on")
+        .build(Host::Tcp("localhost".to_owned()));
+    match Connection::connect(params, TlsMode::None) {
+        Err(e) => {
+            let msg = e.to_string();
+            assert!(msg.contains("protocol extensions _pq_.rust_postgres_unknown"), "{}", msg);
+            assert!(!msg.contains("minor version"), "{}", msg);
+        }
+        Ok(_) => panic!("unexpected success"),
+    }
+}
+
+#[test]
+fn test_read_timeout() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert!(conn.set_read_timeout(Some(Duration::from_secs(0))).is_err());
+
+    or_panic!(conn.set_read_timeout(Some(Duration::from_millis(500))));
+    or_panic!(conn.execute("SELECT pg_sleep(0.1)", &[]));
+    assert!(or_panic!(conn.notifications().iter().next()).is_none());
+
+    match conn.execute("SELECT pg_sleep(2)", &[]) {
+        Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::TimedOut) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    assert!(conn.is_desynchronized());
+    assert!(conn.execute("SELECT 1", &[]).is_err());
+
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.set_read_timeout(Some(Duration::from_millis(500))));
+    or_panic!(conn.set_read_timeout(None));
+    or_panic!(conn.execute("SELECT pg_sleep(1)", &[]));
+}
+
 #[test]
 #[ignore] // doesn't work on our CI setup
 fn test_unix_connection() {
@@ -107,6 +336,69 @@ fn test_unix_connection() {
     assert!(conn.finish().is_ok());
 }
 
+#[test]
+fn test_transaction_status() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert_eq!(conn.transaction_status(), TransactionStatus::Idle);
+
+    let trans = or_panic!(conn.transaction());
+    or_panic!(trans.execute("SELECT 1", &[]));
+    assert_eq!(conn.transaction_status(), TransactionStatus::InTransaction);
+
+    assert!(trans.execute("SELECT 1/0", &[]).is_err());
+    assert_eq!(conn.transaction_status(), TransactionStatus::Failed);
+    match trans.execute("SELECT 1", &[]) {
+        Err(ref e) if e.code() == Some(&IN_FAILED_SQL_TRANSACTION) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    or_panic!(trans.finish());
+    assert_eq!(conn.transaction_status(), TransactionStatus::Idle);
+}
+
+#[test]
+fn test_large_object() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let trans = or_panic!(conn.transaction());
+
+    let data = (0..1024 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let oid = or_panic!(large_objects::create(&trans));
+    {
+        let mut lo = or_panic!(LargeObject::open(&trans, oid, Mode::Write));
+        assert_eq!(lo.oid(), oid);
+        or_panic!(lo.write_all(&data));
+        or_panic!(lo.finish());
+    }
+
+    let mut lo = or_panic!(LargeObject::open(&trans, oid, Mode::ReadWrite));
+    let mut buf = vec![];
+    or_panic!(lo.read_to_end(&mut buf));
+    assert!(buf == data);
+
+    assert_eq!(or_panic!(lo.seek(SeekFrom::Start(1000))), 1000);
+    let mut buf = [0; 5];
+    or_panic!(lo.read_exact(&mut buf));
+    assert_eq!(buf, &data[1000..1005]);
+    assert_eq!(or_panic!(lo.seek(SeekFrom::Current(-5))), 1000);
+    assert_eq!(or_panic!(lo.seek(SeekFrom::End(0))), data.len() as u64);
+
+    or_panic!(lo.truncate(10));
+    or_panic!(lo.seek(SeekFrom::Start(0)));
+    let mut buf = vec![];
+    or_panic!(lo.read_to_end(&mut buf));
+    assert_eq!(buf, &data[..10]);
+    drop(lo);
+
+    or_panic!(large_objects::unlink(&trans, oid));
+    assert!(LargeObject::open(&trans, oid, Mode::Read).is_err());
+}
+
 #[test]
 fn test_transaction_commit() {
     let conn = or_panic!(Connection::connect(
@@ -358,6 +650,42 @@ fn test_nested_transactions_finish() {
     );
 }
 
+#[test]
+fn test_with_savepoint() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.execute(
+        "CREATE TEMPORARY TABLE foo (id INT PRIMARY KEY)",
+        &[],
+    ));
+
+    let trans = or_panic!(conn.transaction());
+    or_panic!(trans.execute("INSERT INTO foo (id) VALUES (1)", &[]));
+
+    let r = trans.with_savepoint("my \"savepoint\"", |sp| {
+        sp.execute("INSERT INTO foo (id) VALUES (2)", &[])?;
+        sp.execute("INSERT INTO foo (id) VALUES (1)", &[])
+    });
+    match r {
+        Err(ref e) if e.code() == Some(&UNIQUE_VIOLATION) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    let n = or_panic!(trans.with_savepoint("sp", |sp| {
+        sp.execute("INSERT INTO foo (id) VALUES (3)", &[])
+    }));
+    assert_eq!(n, 1);
+
+    let rows = or_panic!(trans.query("SELECT id FROM foo ORDER BY id", &[]));
+    assert_eq!(
+        vec![1i32, 3],
+        rows.iter().map(|row| row.get(0)).collect::<Vec<i32>>()
+    );
+    or_panic!(trans.commit());
+}
+
 #[test]
 #[should_panic(expected = "active transaction")]
 fn test_conn_trans_when_nested() {
@@ -479,6 +807,73 @@ fn test_transaction_batch_execute() {
     );
 }
 
+#[test]
+fn test_terminated_by_server() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert!(!conn.was_terminated_by_server());
+
+    let pid: i32 = or_panic!(conn.query("SELECT pg_backend_pid()", &[])).get(0).get(0);
+    match conn.execute("SELECT pg_terminate_backend($1)", &[&pid]) {
+        Err(ref e) if e.code() == Some(&ADMIN_SHUTDOWN) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    assert!(conn.was_terminated_by_server());
+    assert!(conn.is_desynchronized());
+
+    match conn.execute("SELECT 1", &[]) {
+        Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::ConnectionAborted) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_transaction_retry() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.execute(
+        "CREATE TEMPORARY TABLE foo (id INT PRIMARY KEY)",
+        &[],
+    ));
+
+    let mut attempts = 0;
+    let id = or_panic!(conn.transaction_retry(3, |trans| {
+        attempts += 1;
+        trans.set_config(transaction::Config::new().isolation_level(IsolationLevel::Serializable))?;
+        trans.execute("INSERT INTO foo (id) VALUES ($1)", &[&attempts])?;
+        if attempts == 1 {
+            trans.batch_execute(
+                "DO $$ BEGIN RAISE EXCEPTION 'conflict' USING ERRCODE = 'serialization_failure'; \
+                 END $$",
+            )?;
+        }
+        Ok(attempts)
+    }));
+    assert_eq!(2, id);
+    assert_eq!(2, attempts);
+
+    let rows = or_panic!(conn.query("SELECT id FROM foo", &[]));
+    assert_eq!(
+        vec![2],
+        rows.iter().map(|row| row.get(0)).collect::<Vec<i32>>()
+    );
+
+    attempts = 0;
+    match conn.transaction_retry(3, |trans| {
+        attempts += 1;
+        trans.execute("INSERT INTO foo (id) VALUES (2)", &[])
+    }) {
+        Err(ref e) if e.code().is_some() && !e.is_retryable() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    assert_eq!(1, attempts);
+    assert!(conn.is_active());
+}
+
 #[test]
 fn test_query() {
     let conn = or_panic!(Connection::connect(
@@ -503,165 +898,539 @@ fn test_query() {
 }
 
 #[test]
-fn test_error_after_datarow() {
+fn test_text_result_format() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    let stmt = or_panic!(conn.prepare(
-        "
-SELECT
-    (SELECT generate_series(1, ss.i))
-FROM (SELECT gs.i
-      FROM generate_series(1, 2) gs(i)
-      ORDER BY gs.i
-      LIMIT 2) ss",
-    ));
-    match stmt.query(&[]) {
-        Err(ref e) if e.code() == Some(&CARDINALITY_VIOLATION) => {}
-        Err(err) => panic!("Unexpected error {:?}", err),
-        Ok(_) => panic!("Expected failure"),
-    };
+    let mut stmt = or_panic!(conn.prepare("SELECT 10::INT4, 'hello'::TEXT, 20::INT4, NULL::INT4"));
+    assert_eq!(stmt.result_formats(), &[Format::Binary; 4][..]);
+    stmt.set_result_format(0, Format::Text);
+    stmt.set_result_format(3, Format::Text);
+
+    let rows = or_panic!(stmt.query(&[]));
+    let row = rows.get(0);
+    assert_eq!(row.get::<_, String>(0), "10");
+    assert_eq!(row.get_bytes(0), Some(&b"10"[..]));
+    assert_eq!(row.get::<_, String>(1), "hello");
+    assert_eq!(row.get::<_, i32>(2), 20);
+    assert_eq!(row.get::<_, Option<String>>(3), None);
+    match row.get_opt::<_, i32>(0) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    let cached = or_panic!(conn.prepare_cached("SELECT 1::INT4"));
+    let mut text = or_panic!(conn.prepare_cached("SELECT 1::INT4"));
+    text.set_result_format(0, Format::Text);
+    assert_eq!(or_panic!(text.query(&[])).get(0).get::<_, String>(0), "1");
+    assert_eq!(or_panic!(cached.query(&[])).get(0).get::<_, i32>(0), 1);
 }
 
 #[test]
-fn test_lazy_query() {
+fn test_rows_result_formats() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
+    let mut stmt = or_panic!(conn.prepare("SELECT 1::INT4, 'a'::TEXT, 2::INT8"));
+    let binary = or_panic!(stmt.query(&[]));
+    assert_eq!(binary.result_formats(), &[Format::Binary; 3][..]);
 
-    let trans = or_panic!(conn.transaction());
-    or_panic!(trans.execute("CREATE TEMPORARY TABLE foo (id INT PRIMARY KEY)", &[],));
-    let stmt = or_panic!(trans.prepare("INSERT INTO foo (id) VALUES ($1)"));
-    let values = vec![0i32, 1, 2, 3, 4, 5];
-    for value in &values {
-        or_panic!(stmt.execute(&[value]));
-    }
-    let stmt = or_panic!(trans.prepare("SELECT id FROM foo ORDER BY id"));
-    let result = or_panic!(stmt.lazy_query(&trans, &[], 2));
-    assert_eq!(
-        values,
-        result.map(|row| row.get(0)).collect::<Vec<i32>>().unwrap()
-    );
+    stmt.set_result_format(1, Format::Text);
+    let text = or_panic!(stmt.query(&[]));
+    assert_eq!(text.result_formats(), &[Format::Binary, Format::Text, Format::Binary][..]);
+    assert_eq!(binary.result_formats(), &[Format::Binary; 3][..]);
 }
 
 #[test]
-#[should_panic(expected = "same `Connection` as")]
-fn test_lazy_query_wrong_conn() {
-    let conn1 = or_panic!(Connection::connect(
-        "postgres://postgres@localhost:5433",
-        TlsMode::None,
-    ));
-    let conn2 = or_panic!(Connection::connect(
+fn test_column_source() {
+    let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
+    or_panic!(conn.batch_execute("CREATE TEMPORARY TABLE foo (name TEXT, id INT)"));
+    let oid: Oid = or_panic!(conn.query("SELECT 'foo'::regclass::oid", &[])).get(0).get(0);
 
-    let trans = or_panic!(conn1.transaction());
-    let stmt = or_panic!(conn2.prepare("SELECT 1::INT"));
-    stmt.lazy_query(&trans, &[], 1).unwrap();
+    let stmt = or_panic!(conn.prepare("SELECT id, 1 AS one FROM foo"));
+    let columns = stmt.columns();
+    assert_eq!(columns[0].table_oid(), Some(oid));
+    assert_eq!(columns[0].column_id(), Some(2));
+    assert_eq!(columns[1].table_oid(), None);
+    assert_eq!(columns[1].column_id(), None);
 }
 
 #[test]
-fn test_param_types() {
+fn test_text_only_types() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    let stmt = or_panic!(conn.prepare("SELECT $1::INT, $2::VARCHAR"));
-    assert_eq!(stmt.param_types(), &[INT4, VARCHAR][..]);
-}
+    or_panic!(conn.batch_execute(
+        "CREATE TEMPORARY TABLE foo (id INT);
+         GRANT SELECT ON foo TO PUBLIC;",
+    ));
 
-#[test]
-fn test_columns() {
-    let conn = or_panic!(Connection::connect(
-        "postgres://postgres@localhost:5433",
-        TlsMode::None,
+    let stmt = or_panic!(conn.prepare(
+        "SELECT relacl, relacl[1] FROM pg_class WHERE oid = 'foo'::regclass",
     ));
-    let stmt = or_panic!(conn.prepare("SELECT 1::INT as a, 'hi'::VARCHAR as b"));
-    let cols = stmt.columns();
-    assert_eq!(2, cols.len());
-    assert_eq!(cols[0].name(), "a");
-    assert_eq!(cols[0].type_(), &INT4);
-    assert_eq!(cols[1].name(), "b");
-    assert_eq!(cols[1].type_(), &VARCHAR);
+    assert_eq!(stmt.result_formats(), &[Format::Text, Format::Text][..]);
+    let rows = or_panic!(stmt.query(&[]));
+    let acl: String = rows.get(0).get(0);
+    assert!(acl.starts_with('{') && acl.contains("=r/"), "{}", acl);
+    let item: String = rows.get(0).get(1);
+    assert!(item.contains("=ar"), "{}", item);
 }
 
 #[test]
-fn test_execute_counts() {
+fn test_binary_io_introspection() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
+    or_panic!(conn.batch_execute(
+        "CREATE DOMAIN pg_temp.acl AS aclitem;
+         CREATE TYPE pg_temp.grant_info AS (grantee TEXT, acl aclitem);
+         CREATE TYPE pg_temp.mood AS ENUM ('sad', 'happy');",
+    ));
+
+    let stmt = or_panic!(conn.prepare(
+        "SELECT 'postgres=r/postgres'::pg_temp.acl, \
+                ARRAY['postgres=r/postgres'::pg_temp.acl], \
+                ROW('postgres', 'postgres=r/postgres')::pg_temp.grant_info, \
+                'happy'::pg_temp.mood",
+    ));
+    let binary_io = stmt.columns()
+        .iter()
+        .map(|c| c.type_().has_binary_io())
+        .collect::<Vec<_>>();
+    assert_eq!(binary_io, [false, false, false, true]);
     assert_eq!(
-        0,
-        or_panic!(conn.execute(
-            "CREATE TEMPORARY TABLE foo (
-                                            id SERIAL PRIMARY KEY,
-                                            b INT
-                                         )",
-            &[],
-        ))
-    );
-    assert_eq!(
-        3,
-        or_panic!(conn.execute(
-            "INSERT INTO foo (b) VALUES ($1), ($2), ($2)",
-            &[&1i32, &2i32],
-        ))
-    );
-    assert_eq!(
-        2,
-        or_panic!(conn.execute("UPDATE foo SET b = 0 WHERE b = 2", &[]))
+        stmt.result_formats(),
+        &[Format::Text, Format::Text, Format::Text, Format::Binary][..]
     );
-    assert_eq!(3, or_panic!(conn.execute("SELECT * FROM foo", &[])));
+
+    let rows = or_panic!(stmt.query(&[]));
+    let row = rows.get(0);
+    assert_eq!(row.get::<_, String>(0), "postgres=r/postgres");
+    assert_eq!(row.get::<_, String>(1), "{postgres=r/postgres}");
+    assert_eq!(row.get::<_, String>(2), "(postgres,postgres=r/postgres)");
+    assert_eq!(row.get_enum(3, &["sad", "happy"]).unwrap().unwrap(), "happy");
 }
 
 #[test]
-fn test_wrong_param_type() {
+fn test_column_type_modifier() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    let err = conn.execute("SELECT $1::VARCHAR", &[&1i32]).unwrap_err();
-    match err.as_conversion() {
-        Some(e) if e.is::<WrongType>() => {}
-        _ => panic!("unexpected result {:?}", err),
-    }
+    or_panic!(conn.batch_execute("CREATE TEMPORARY TABLE foo (name VARCHAR(5), id INT)"));
+
+    let stmt = or_panic!(conn.prepare("SELECT name, id FROM foo"));
+    let columns = stmt.columns();
+    // the modifier of a VARCHAR(n) includes the 4 byte length header
+    assert_eq!(columns[0].type_modifier(), Some(5 + 4));
+    assert_eq!(columns[1].type_modifier(), None);
 }
 
 #[test]
-#[should_panic(expected = "expected 2 parameters but got 1")]
-fn test_too_few_params() {
+fn test_try_execute_param_count() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    let _ = conn.execute("SELECT $1::INT, $2::INT", &[&1i32]);
+
+    match conn.try_execute("SELECT $1::INT4, $2::INT4", &[&1i32]) {
+        Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::InvalidInput) => {
+            assert!(e.to_string().contains("expected 2 parameters but got 1"), "{}", e);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    match conn.try_query("SELECT 1", &[&1i32]) {
+        Err(ref e) if e.as_io().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    let stmt = or_panic!(conn.prepare("SELECT $1::INT4"));
+    assert!(stmt.try_query(&[]).is_err());
+    assert!(stmt.try_execute(&[&1i32, &2i32]).is_err());
+    assert_eq!(or_panic!(stmt.try_query(&[&1i32])).get(0).get::<_, i32>(0), 1);
+    assert_eq!(or_panic!(conn.try_execute("SELECT $1::INT4", &[&1i32])), 1);
 }
 
 #[test]
-#[should_panic(expected = "expected 2 parameters but got 3")]
-fn test_too_many_params() {
+fn test_empty_query() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    let _ = conn.execute("SELECT $1::INT, $2::INT", &[&1i32, &2i32, &3i32]);
+
+    match conn.execute("-- just a comment", &[]) {
+        Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::InvalidInput) => {
+            assert!(e.to_string().contains("the query was empty"), "{}", e);
+            assert!(e.is_empty_query());
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    match conn.query("", &[]) {
+        Err(ref e) if e.as_io().is_some() => assert!(e.is_empty_query()),
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    let stmt = or_panic!(conn.prepare("  "));
+    assert!(stmt.execute(&[]).unwrap_err().is_empty_query());
+    assert!(stmt.query(&[]).unwrap_err().is_empty_query());
+
+    match conn.try_execute("SELECT $1", &[]) {
+        Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::InvalidInput) => {
+            assert!(!e.is_empty_query());
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    // the connection is still usable
+    assert_eq!(or_panic!(conn.execute("SELECT 1", &[])), 1);
 }
 
 #[test]
-fn test_index_named() {
+fn test_query_raw_boxed_params() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    let stmt = or_panic!(conn.prepare("SELECT 10::INT as val"));
-    let result = or_panic!(stmt.query(&[]));
+    or_panic!(conn.execute(
+        "CREATE TEMPORARY TABLE foo (id BIGINT PRIMARY KEY, name TEXT)",
+        &[],
+    ));
 
+    let mut params: Vec<Box<ToSql>> = vec![];
+    params.push(Box::new(1i64));
+    params.push(Box::new("a".to_owned()));
     assert_eq!(
-        vec![10i32],
+        1,
+        or_panic!(conn.execute_raw("INSERT INTO foo (id, name) VALUES ($1, $2)", &params))
+    );
+
+    let trans = or_panic!(conn.transaction());
+    let stmt = or_panic!(trans.prepare("INSERT INTO foo (id, name) VALUES ($1, $2)"));
+    let params: Vec<Box<ToSql>> = vec![Box::new(2i64), Box::new(None::<String>)];
+    assert_eq!(1, or_panic!(stmt.execute_raw(params)));
+    let params: Vec<Box<ToSql + Sync>> = vec![Box::new(3i64), Box::new("c")];
+    assert_eq!(1, or_panic!(stmt.execute_raw(&params[..])));
+    let id = 4i64;
+    let name = "d".to_owned();
+    let params: Vec<&ToSql> = vec![&id, &name];
+    assert_eq!(1, or_panic!(stmt.execute_raw(params.iter().cloned())));
+    or_panic!(trans.commit());
+
+    let params: Vec<Box<ToSql>> = vec![Box::new(0i64)];
+    let result = or_panic!(conn.query_raw(
+        "SELECT id, name FROM foo WHERE id > $1 ORDER BY id",
+        &params,
+    ));
+    assert_eq!(
+        vec![
+            (1i64, Some("a".to_owned())),
+            (2, None),
+            (3, Some("c".to_owned())),
+            (4, Some("d".to_owned())),
+        ],
+        result
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect::<Vec<(i64, Option<String>)>>()
+    );
+
+    let params: Vec<Box<ToSql + Sync>> = vec![Box::new(2i64)];
+    let result = or_panic!(conn.query_raw("SELECT id FROM foo WHERE id > $1", &params[..]));
+    assert_eq!(2, result.len());
+
+    let stmt = or_panic!(conn.prepare("SELECT id, name FROM foo WHERE id < $1 ORDER BY id"));
+    let params: Vec<&ToSql> = vec![&id];
+    let result = or_panic!(stmt.query_raw(params.iter().cloned()));
+    assert_eq!(
+        vec![(1i64, Some("a".to_owned())), (2, None), (3, Some("c".to_owned()))],
+        result
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect::<Vec<(i64, Option<String>)>>()
+    );
+}
+
+#[test]
+fn test_error_after_datarow() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let stmt = or_panic!(conn.prepare(
+        "
+SELECT
+    (SELECT generate_series(1, ss.i))
+FROM (SELECT gs.i
+      FROM generate_series(1, 2) gs(i)
+      ORDER BY gs.i
+      LIMIT 2) ss",
+    ));
+    match stmt.query(&[]) {
+        Err(ref e) if e.code() == Some(&CARDINALITY_VIOLATION) => {}
+        Err(err) => panic!("Unexpected error {:?}", err),
+        Ok(_) => panic!("Expected failure"),
+    };
+}
+
+#[test]
+fn test_db_error_object_fields() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "CREATE TEMPORARY TABLE users (
+            id SERIAL PRIMARY KEY,
+            email TEXT NOT NULL CONSTRAINT users_email_key UNIQUE
+        );
+        INSERT INTO users (email) VALUES ('a@example.com');",
+    ));
+
+    match conn.execute("INSERT INTO users (email) VALUES ('a@example.com')", &[]) {
+        Err(ref e) if e.code() == Some(&UNIQUE_VIOLATION) => {
+            let db = e.as_db().unwrap();
+            assert_eq!(db.constraint.as_ref().map(|s| &**s), Some("users_email_key"));
+            assert_eq!(db.table.as_ref().map(|s| &**s), Some("users"));
+            assert!(db.schema.as_ref().unwrap().starts_with("pg_temp"));
+            assert_eq!(db.column, None);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    match conn.execute("INSERT INTO users (email) VALUES (NULL)", &[]) {
+        Err(ref e) if e.code() == Some(&NOT_NULL_VIOLATION) => {
+            let db = e.as_db().unwrap();
+            assert_eq!(db.column.as_ref().map(|s| &**s), Some("email"));
+            assert_eq!(db.table.as_ref().map(|s| &**s), Some("users"));
+            assert_eq!(db.constraint, None);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_execute_returning() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.execute("CREATE TEMPORARY TABLE foo (id SERIAL PRIMARY KEY, name TEXT)", &[]));
+    let stmt = or_panic!(conn.prepare("INSERT INTO foo (name) VALUES ($1) RETURNING id"));
+    let rows = or_panic!(stmt.execute_returning(&[&"a"]));
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows.get(0).get::<_, i32>("id"), 1);
+    let rows = or_panic!(stmt.execute_returning(&[&"b"]));
+    assert_eq!(rows.get(0).get::<_, i32>("id"), 2);
+}
+
+#[test]
+fn test_lazy_query() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let trans = or_panic!(conn.transaction());
+    or_panic!(trans.execute("CREATE TEMPORARY TABLE foo (id INT PRIMARY KEY)", &[],));
+    let stmt = or_panic!(trans.prepare("INSERT INTO foo (id) VALUES ($1)"));
+    let values = vec![0i32, 1, 2, 3, 4, 5];
+    for value in &values {
+        or_panic!(stmt.execute(&[value]));
+    }
+    let stmt = or_panic!(trans.prepare("SELECT id FROM foo ORDER BY id"));
+    let result = or_panic!(stmt.lazy_query(&trans, &[], 2));
+    assert_eq!(
+        values,
+        result.map(|row| row.get(0)).collect::<Vec<i32>>().unwrap()
+    );
+}
+
+#[test]
+fn test_lazy_query_finish_closes_portal() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    fn open_cursors(conn: &Connection) -> i64 {
+        or_panic!(conn.query("SELECT COUNT(*) FROM pg_cursors", &[])).get(0).get(0)
+    }
+
+    let trans = or_panic!(conn.transaction());
+    let stmt = or_panic!(conn.prepare("SELECT * FROM generate_series(1, 100)"));
+    let before = open_cursors(&conn);
+
+    let mut rows = or_panic!(stmt.lazy_query(&trans, &[], 10));
+    for _ in 0..3 {
+        assert!(or_panic!(rows.next()).is_some());
+    }
+    assert_eq!(open_cursors(&conn), before + 1);
+    or_panic!(rows.finish());
+    assert_eq!(open_cursors(&conn), before);
+}
+
+#[test]
+fn test_lazy_query_row_limit() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute("CREATE TEMPORARY SEQUENCE foo"));
+
+    // the sequence is advanced by the server for each row it sends, so its
+    // value is the number of rows fetched so far
+    let trans = or_panic!(conn.transaction());
+    let stmt = or_panic!(trans.prepare("SELECT nextval('foo') FROM generate_series(1, 1000)"));
+    let mut rows = or_panic!(stmt.lazy_query(&trans, &[], 100));
+    assert_eq!(rows.row_limit(), 100);
+
+    let fetched = || -> i64 {
+        let rows = or_panic!(trans.query("SELECT last_value FROM foo", &[]));
+        rows.get(0).get(0)
+    };
+    let mut fetches = 0;
+    let mut last = 0;
+    let mut count = 0;
+    while let Some(row) = or_panic!(rows.next()) {
+        count += 1;
+        assert_eq!(row.get::<_, i64>(0), count);
+
+        let total = fetched();
+        if total != last {
+            fetches += 1;
+            assert_eq!(total - last, 100);
+            last = total;
+        }
+        assert!(total - count < 100);
+    }
+    assert_eq!(count, 1000);
+    assert_eq!(fetched(), 1000);
+    assert_eq!(fetches, 10);
+}
+
+#[test]
+#[should_panic(expected = "same `Connection` as")]
+fn test_lazy_query_wrong_conn() {
+    let conn1 = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let conn2 = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let trans = or_panic!(conn1.transaction());
+    let stmt = or_panic!(conn2.prepare("SELECT 1::INT"));
+    stmt.lazy_query(&trans, &[], 1).unwrap();
+}
+
+#[test]
+fn test_param_types() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let stmt = or_panic!(conn.prepare("SELECT $1::INT, $2::VARCHAR"));
+    assert_eq!(stmt.param_types(), &[INT4, VARCHAR][..]);
+}
+
+#[test]
+fn test_columns() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let stmt = or_panic!(conn.prepare("SELECT 1::INT as a, 'hi'::VARCHAR as b"));
+    let cols = stmt.columns();
+    assert_eq!(2, cols.len());
+    assert_eq!(cols[0].name(), "a");
+    assert_eq!(cols[0].type_(), &INT4);
+    assert_eq!(cols[1].name(), "b");
+    assert_eq!(cols[1].type_(), &VARCHAR);
+}
+
+#[test]
+fn test_execute_counts() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert_eq!(
+        0,
+        or_panic!(conn.execute(
+            "CREATE TEMPORARY TABLE foo (
+                                            id SERIAL PRIMARY KEY,
+                                            b INT
+                                         )",
+            &[],
+        ))
+    );
+    assert_eq!(
+        3,
+        or_panic!(conn.execute(
+            "INSERT INTO foo (b) VALUES ($1), ($2), ($2)",
+            &[&1i32, &2i32],
+        ))
+    );
+    assert_eq!(
+        2,
+        or_panic!(conn.execute("UPDATE foo SET b = 0 WHERE b = 2", &[]))
+    );
+    assert_eq!(3, or_panic!(conn.execute("SELECT * FROM foo", &[])));
+}
+
+#[test]
+fn test_wrong_param_type() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let err = conn.execute("SELECT $1::VARCHAR", &[&1i32]).unwrap_err();
+    match err.as_conversion() {
+        Some(e) if e.is::<WrongType>() => {}
+        _ => panic!("unexpected result {:?}", err),
+    }
+}
+
+#[test]
+#[should_panic(expected = "expected 2 parameters but got 1")]
+fn test_too_few_params() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let _ = conn.execute("SELECT $1::INT, $2::INT", &[&1i32]);
+}
+
+#[test]
+#[should_panic(expected = "expected 2 parameters but got 3")]
+fn test_too_many_params() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let _ = conn.execute("SELECT $1::INT, $2::INT", &[&1i32, &2i32, &3i32]);
+}
+
+#[test]
+fn test_index_named() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let stmt = or_panic!(conn.prepare("SELECT 10::INT as val"));
+    let result = or_panic!(stmt.query(&[]));
+
+    assert_eq!(
+        vec![10i32],
         result
             .iter()
             .map(|row| row.get("val"))
@@ -713,35 +1482,166 @@ fn test_get_was_null() {
 }
 
 #[test]
-fn test_get_off_by_one() {
+fn test_get_as() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    let stmt = or_panic!(conn.prepare("SELECT 10::INT as id"));
+    let stmt = or_panic!(conn.prepare(
+        "SELECT 1::INT2, 2::INT4, 3::INT8, 100000::INT8, 'a'::TEXT, NULL::INT4",
+    ));
     let result = or_panic!(stmt.query(&[]));
+    let row = result.get(0);
 
-    match result.iter().next().unwrap().get_opt::<_, i32>(1) {
-        None => {}
+    assert_eq!(or_panic!(row.get_as::<_, i64>(0).unwrap()), 1);
+    assert_eq!(or_panic!(row.get_as::<_, i64>(1).unwrap()), 2);
+    assert_eq!(or_panic!(row.get_as::<_, i64>(2).unwrap()), 3);
+    assert_eq!(or_panic!(row.get_as::<_, i16>(2).unwrap()), 3);
+    match row.get_as::<_, i16>(3) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
         res => panic!("unexpected result {:?}", res),
-    };
+    }
+    match row.get_as::<_, i64>(4) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    match row.get_as::<_, i64>(5) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    assert!(row.get_as::<_, i64>(6).is_none());
 }
 
 #[test]
-fn test_custom_notice_handler() {
-    static mut COUNT: usize = 0;
-    struct Handler;
+fn test_get_as_text() {
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .prefer_simple_query(true)
+        .build(Host::Tcp("localhost".to_owned()));
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+    let result = or_panic!(conn.query(
+        "SELECT 1::INT2, -2::INT4, 3000000000::INT8, NULL::INT4",
+        &[],
+    ));
+    let row = result.get(0);
 
-    impl HandleNotice for Handler {
-        fn handle_notice(&mut self, notice: DbError) {
-            assert_eq!("note", notice.message);
-            unsafe {
-                COUNT += 1;
-            }
-        }
+    assert_eq!(or_panic!(row.get_as::<_, i64>(0).unwrap()), 1);
+    assert_eq!(or_panic!(row.get_as::<_, i64>(1).unwrap()), -2);
+    assert_eq!(or_panic!(row.get_as::<_, i64>(2).unwrap()), 3000000000);
+    assert_eq!(or_panic!(row.get_as::<_, u32>(2).unwrap()), 3000000000);
+    match row.get_as::<_, u8>(1) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        res => panic!("unexpected result {:?}", res),
     }
-
-    let conn = or_panic!(Connection::connect(
+    match row.get_as::<_, i64>(3) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let mut stmt = or_panic!(conn.prepare("SELECT 1::INT2, 2::INT4, 3::INT8"));
+    for i in 0..3 {
+        stmt.set_result_format(i, Format::Text);
+    }
+    let result = or_panic!(stmt.query(&[]));
+    let row = result.get(0);
+    assert_eq!(or_panic!(row.get_as::<_, i64>(0).unwrap()), 1);
+    assert_eq!(or_panic!(row.get_as::<_, i64>(1).unwrap()), 2);
+    assert_eq!(or_panic!(row.get_as::<_, i64>(2).unwrap()), 3);
+}
+
+#[test]
+fn test_get_bpchar_trimmed() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let stmt = or_panic!(conn.prepare(
+        "SELECT 'foo'::CHAR(10), 'a'::\"char\", ARRAY['a', 'b']::\"char\"[], 'foo'::TEXT",
+    ));
+    let result = or_panic!(stmt.query(&[]));
+    let row = result.get(0);
+
+    assert_eq!(row.get::<_, String>(0), "foo       ");
+    assert_eq!(or_panic!(row.get_bpchar_trimmed(0).unwrap()), "foo");
+    assert_eq!(row.get::<_, i8>(1), b'a' as i8);
+    assert_eq!(row.get::<_, Vec<i8>>(2), vec![b'a' as i8, b'b' as i8]);
+    match row.get_bpchar_trimmed(1) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    match row.get_bpchar_trimmed(3) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+    assert!(row.get_bpchar_trimmed(4).is_none());
+}
+
+#[test]
+fn test_get_off_by_one() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let stmt = or_panic!(conn.prepare("SELECT 10::INT as id"));
+    let result = or_panic!(stmt.query(&[]));
+
+    match result.iter().next().unwrap().get_opt::<_, i32>(1) {
+        None => {}
+        res => panic!("unexpected result {:?}", res),
+    };
+}
+
+#[test]
+fn test_timezone_change_warning() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert_eq!(conn.parameter("TimeZone"), Some("GMT".to_owned()));
+
+    let notices = Arc::new(Mutex::new(vec![]));
+    let notices2 = notices.clone();
+    conn.set_notice_handler(Box::new(move |notice: DbError| {
+        notices2.lock().unwrap().push(notice);
+    }));
+
+    or_panic!(conn.batch_execute("SET TimeZone = 'GMT'"));
+    assert!(notices.lock().unwrap().is_empty());
+
+    or_panic!(conn.batch_execute("SET TimeZone = 'UTC'"));
+    assert_eq!(conn.parameter("TimeZone"), Some("UTC".to_owned()));
+    {
+        let notices = notices.lock().unwrap();
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].severity, "WARNING");
+        assert!(notices[0].message.contains("UTC"), "{}", notices[0].message);
+    }
+
+    or_panic!(conn.batch_execute("SET TimeZone = 'GMT'"));
+    assert_eq!(conn.parameter("TimeZone"), Some("GMT".to_owned()));
+    assert_eq!(notices.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_custom_notice_handler() {
+    static mut COUNT: usize = 0;
+    struct Handler;
+
+    impl HandleNotice for Handler {
+        fn handle_notice(&mut self, notice: DbError) {
+            assert_eq!("note", notice.message);
+            unsafe {
+                COUNT += 1;
+            }
+        }
+    }
+
+    let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433?client_min_messages=NOTICE",
         TlsMode::None,
     ));
@@ -756,110 +1656,600 @@ fn test_custom_notice_handler() {
     ));
     or_panic!(conn.execute("SELECT pg_temp.note()", &[]));
 
-    assert_eq!(unsafe { COUNT }, 1);
+    assert_eq!(unsafe { COUNT }, 1);
+}
+
+#[test]
+fn test_notification_iterator_none() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert!(conn.notifications().iter().next().unwrap().is_none());
+}
+
+fn check_notification(expected: Notification, actual: Notification) {
+    assert_eq!(&expected.channel, &actual.channel);
+    assert_eq!(&expected.payload, &actual.payload);
+}
+
+#[test]
+fn test_notification_iterator_some() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let notifications = conn.notifications();
+    let mut it = notifications.iter();
+    or_panic!(conn.execute(
+        "LISTEN test_notification_iterator_one_channel",
+        &[],
+    ));
+    or_panic!(conn.execute(
+        "LISTEN test_notification_iterator_one_channel2",
+        &[],
+    ));
+    or_panic!(conn.execute(
+        "NOTIFY test_notification_iterator_one_channel, 'hello'",
+        &[],
+    ));
+    or_panic!(conn.execute(
+        "NOTIFY test_notification_iterator_one_channel2, 'world'",
+        &[],
+    ));
+
+    check_notification(
+        Notification {
+            process_id: 0,
+            channel: "test_notification_iterator_one_channel".to_string(),
+            payload: "hello".to_string(),
+        },
+        it.next().unwrap().unwrap(),
+    );
+    check_notification(
+        Notification {
+            process_id: 0,
+            channel: "test_notification_iterator_one_channel2".to_string(),
+            payload: "world".to_string(),
+        },
+        it.next().unwrap().unwrap(),
+    );
+    assert!(it.next().unwrap().is_none());
+
+    or_panic!(conn.execute(
+        "NOTIFY test_notification_iterator_one_channel, '!'",
+        &[],
+    ));
+    check_notification(
+        Notification {
+            process_id: 0,
+            channel: "test_notification_iterator_one_channel".to_string(),
+            payload: "!".to_string(),
+        },
+        it.next().unwrap().unwrap(),
+    );
+    assert!(it.next().unwrap().is_none());
+}
+
+#[test]
+fn test_notification_limit() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let notifications = conn.notifications();
+    or_panic!(conn.listen("test_notification_limit"));
+
+    notifications.set_limit(Some(3), OverflowPolicy::DropOldest);
+    or_panic!(conn.execute(
+        "SELECT pg_notify('test_notification_limit', i::TEXT) FROM generate_series(1, 10) i",
+        &[],
+    ));
+    assert_eq!(notifications.len(), 3);
+    assert!(notifications.dropped());
+    let payloads = or_panic!(notifications.iter().map(|n| n.payload).collect::<Vec<_>>());
+    assert_eq!(payloads, ["8", "9", "10"]);
+    assert!(!notifications.dropped());
+
+    notifications.set_limit(Some(2), OverflowPolicy::Error);
+    or_panic!(conn.execute(
+        "SELECT pg_notify('test_notification_limit', i::TEXT) FROM generate_series(1, 5) i",
+        &[],
+    ));
+    assert_eq!(notifications.len(), 2);
+    assert!(notifications.dropped());
+    let mut it = notifications.iter();
+    assert!(it.next().is_err());
+    assert!(!notifications.dropped());
+    assert_eq!(it.next().unwrap().unwrap().payload, "1");
+    assert_eq!(it.next().unwrap().unwrap().payload, "2");
+    assert!(it.next().unwrap().is_none());
+}
+
+#[test]
+fn test_notifications_next_block() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.execute("LISTEN test_notifications_next_block", &[]));
+
+    let _t = thread::spawn(|| {
+        let conn = or_panic!(Connection::connect(
+            "postgres://postgres@localhost:5433",
+            TlsMode::None,
+        ));
+        thread::sleep(Duration::from_millis(500));
+        or_panic!(conn.execute(
+            "NOTIFY test_notifications_next_block, 'foo'",
+            &[],
+        ));
+    });
+
+    let notifications = conn.notifications();
+    check_notification(
+        Notification {
+            process_id: 0,
+            channel: "test_notifications_next_block".to_string(),
+            payload: "foo".to_string(),
+        },
+        notifications.blocking_iter().next().unwrap().unwrap(),
+    );
+}
+
+#[test]
+fn test_notifications_poll() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.listen("test_notifications_poll"));
+    let notifications = conn.notifications();
+    assert!(or_panic!(notifications.poll()).is_none());
+
+    let other = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(other.execute("NOTIFY test_notifications_poll, 'foo'", &[]));
+
+    let mut notification = None;
+    for _ in 0..50 {
+        notification = or_panic!(notifications.poll());
+        if notification.is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    check_notification(
+        Notification {
+            process_id: 0,
+            channel: "test_notifications_poll".to_string(),
+            payload: "foo".to_string(),
+        },
+        notification.expect("no notification received"),
+    );
+    assert!(or_panic!(notifications.poll()).is_none());
+}
+
+#[test]
+fn test_notifications_drain() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.listen("test_notifications_drain"));
+    or_panic!(conn.execute("NOTIFY test_notifications_drain, 'foo'", &[]));
+    or_panic!(conn.execute("NOTIFY test_notifications_drain, 'bar'", &[]));
+    or_panic!(conn.execute("NOTIFY test_notifications_drain, 'baz'", &[]));
+
+    let notifications = conn.notifications();
+    let payloads = or_panic!(notifications.drain())
+        .into_iter()
+        .map(|n| n.payload)
+        .collect::<Vec<_>>();
+    assert_eq!(payloads, ["foo", "bar", "baz"]);
+    assert!(notifications.is_empty());
+    assert!(or_panic!(notifications.drain()).is_empty());
+}
+
+#[test]
+fn test_execute_with_oid() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute("CREATE TEMPORARY TABLE foo (id INT)"));
+
+    let stmt = or_panic!(conn.prepare("INSERT INTO foo (id) VALUES ($1)"));
+    assert_eq!(or_panic!(stmt.execute_with_oid(&[&1i32])), (1, None));
+    let stmt = or_panic!(conn.prepare("UPDATE foo SET id = 2"));
+    assert_eq!(or_panic!(stmt.execute_with_oid(&[])), (1, None));
+
+    // tables with OIDs were removed in Postgres 12
+    if conn.server_version().unwrap() >= (12, 0, 0) {
+        return;
+    }
+    or_panic!(conn.batch_execute("CREATE TEMPORARY TABLE bar (id INT) WITH OIDS"));
+    let stmt = or_panic!(conn.prepare("INSERT INTO bar (id) VALUES ($1)"));
+    let (count, oid) = or_panic!(stmt.execute_with_oid(&[&1i32]));
+    assert_eq!(count, 1);
+    let rows = or_panic!(conn.query("SELECT oid FROM bar", &[]));
+    assert_eq!(oid, Some(rows.get(0).get(0)));
+}
+
+#[test]
+fn test_clear_type_cache() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute("CREATE TYPE pg_temp.mood AS ENUM ('sad', 'happy')"));
+
+    let variants = |conn: &Connection| {
+        let stmt = or_panic!(conn.prepare("SELECT $1::mood"));
+        match *stmt.param_types()[0].kind() {
+            Kind::Enum(ref variants) => variants.clone(),
+            _ => panic!("bad type"),
+        }
+    };
+    assert_eq!(variants(&conn), ["sad", "happy"]);
+
+    or_panic!(conn.batch_execute("ALTER TYPE pg_temp.mood ADD VALUE 'ok'"));
+    assert_eq!(variants(&conn), ["sad", "happy"]);
+
+    conn.clear_type_cache();
+    assert_eq!(variants(&conn), ["sad", "happy", "ok"]);
+}
+
+#[test]
+fn test_fetch_cursor() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "CREATE FUNCTION pg_temp.numbers() RETURNS refcursor AS $$
+         DECLARE
+             c refcursor := 'my \"cursor\"';
+         BEGIN
+             OPEN c FOR SELECT * FROM generate_series(1, 5);
+             RETURN c;
+         END;
+         $$ LANGUAGE plpgsql",
+    ));
+
+    let trans = or_panic!(conn.transaction());
+    let rows = or_panic!(trans.query("SELECT pg_temp.numbers()", &[]));
+    let name: String = rows.get(0).get(0);
+    assert_eq!(name, "my \"cursor\"");
+
+    let rows = or_panic!(trans.fetch_cursor(&name, 3));
+    assert_eq!(rows.iter().map(|r| r.get(0)).collect::<Vec<i32>>(), vec![1, 2, 3]);
+    let rows = or_panic!(trans.fetch_cursor(&name, 3));
+    assert_eq!(rows.iter().map(|r| r.get(0)).collect::<Vec<i32>>(), vec![4, 5]);
+    let rows = or_panic!(trans.fetch_cursor(&name, 3));
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn test_write_csv() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "CREATE TEMPORARY TABLE foo (id INT, name TEXT);
+         INSERT INTO foo VALUES (1, 'john'), (2, 'smith, \"jr\"'), (3, ''), (4, NULL);",
+    ));
+
+    let stmt = or_panic!(conn.prepare("SELECT id, name FROM foo WHERE id > $1 ORDER BY id"));
+    let mut buf = vec![];
+    assert_eq!(or_panic!(stmt.write_csv(&[&0i32], &mut buf)), 4);
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "id,name\n1,john\n2,\"smith, \"\"jr\"\"\"\n3,\"\"\n4,\n"
+    );
+}
+
+#[test]
+fn test_typeinfo_queries_prepared_lazily() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let count = "SELECT COUNT(*) FROM pg_prepared_statements WHERE name LIKE '\\_\\_typeinfo%'";
+
+    or_panic!(conn.query("SELECT 1::INT, 'a'::TEXT, ARRAY[1::BIGINT]", &[]));
+    let rows = or_panic!(conn.query(count, &[]));
+    assert_eq!(rows.get(0).get::<_, i64>(0), 0);
+
+    or_panic!(conn.batch_execute("CREATE TYPE pg_temp.mood AS ENUM ('sad', 'happy')"));
+    or_panic!(conn.query("SELECT 'sad'::mood", &[]));
+    let rows = or_panic!(conn.query(count, &[]));
+    assert!(rows.get(0).get::<_, i64>(0) > 0);
+}
+
+#[test]
+fn test_batch_execute_atomic() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute("CREATE TEMPORARY TABLE foo (id INT PRIMARY KEY)"));
+
+    match conn.batch_execute_atomic("INSERT INTO foo VALUES (1); INSERT INTO foo VALUES (1);") {
+        Err(ref e) if e.code() == Some(&UNIQUE_VIOLATION) => assert_eq!(e.batch_index(), Some(2)),
+        Err(e) => panic!("unexpected error {:?}", e),
+        Ok(()) => panic!("unexpected success"),
+    }
+    assert!(conn.is_active());
+    let rows = or_panic!(conn.query("SELECT COUNT(*) FROM foo", &[]));
+    assert_eq!(rows.get(0).get::<_, i64>(0), 0);
+
+    match conn.batch_execute_atomic("INSERT INTO foo VALUES (1); VACUUM foo;") {
+        Err(ref e) if e.code() == Some(&ACTIVE_SQL_TRANSACTION) => {}
+        Err(e) => panic!("unexpected error {:?}", e),
+        Ok(()) => panic!("unexpected success"),
+    }
+    let rows = or_panic!(conn.query("SELECT COUNT(*) FROM foo", &[]));
+    assert_eq!(rows.get(0).get::<_, i64>(0), 0);
+
+    or_panic!(conn.batch_execute_atomic("INSERT INTO foo VALUES (1); INSERT INTO foo VALUES (2);"));
+    let rows = or_panic!(conn.query("SELECT COUNT(*) FROM foo", &[]));
+    assert_eq!(rows.get(0).get::<_, i64>(0), 2);
+}
+
+#[test]
+fn test_execute_file() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let path = env::temp_dir().join("rust_postgres_test_execute_file.sql");
+    or_panic!(or_panic!(File::create(&path)).write_all(
+        b"CREATE TEMPORARY TABLE foo (id INT PRIMARY KEY, name TEXT NOT NULL);
+          INSERT INTO foo (id, name) VALUES (1, 'joe'), (2, 'bob');",
+    ));
+    or_panic!(conn.execute_file(&path, false));
+    let rows = or_panic!(conn.query("SELECT name FROM foo ORDER BY id", &[]));
+    assert_eq!(rows.get(0).get::<_, String>(0), "joe");
+    assert_eq!(rows.get(1).get::<_, String>(0), "bob");
+
+    or_panic!(or_panic!(File::create(&path)).write_all(
+        b"INSERT INTO foo (id, name) VALUES (3, 'tim');
+          INSERT INTO foo (id, name) VALUES (1, 'joe');",
+    ));
+    match conn.execute_file(&path, true) {
+        Err(ref e) if e.code() == Some(&UNIQUE_VIOLATION) => {
+            assert_eq!(e.file_path(), Some(&*path));
+            assert_eq!(e.batch_index(), Some(2));
+        }
+        Err(e) => panic!("unexpected error {:?}", e),
+        Ok(()) => panic!("unexpected success"),
+    }
+    let rows = or_panic!(conn.query("SELECT COUNT(*) FROM foo", &[]));
+    assert_eq!(rows.get(0).get::<_, i64>(0), 2);
+
+    or_panic!(fs::remove_file(&path));
+    match conn.execute_file(&path, false) {
+        Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::NotFound) => {
+            assert_eq!(e.file_path(), Some(&*path));
+            assert!(e.to_string().contains(&*path.to_string_lossy()));
+        }
+        Err(e) => panic!("unexpected error {:?}", e),
+        Ok(()) => panic!("unexpected success"),
+    }
+}
+
+#[test]
+fn test_query_logger() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let log = Arc::new(Mutex::new(vec![]));
+    let log2 = log.clone();
+    let logger = move |query: &str, params: &[&ToSql]| {
+        let params = params.iter().map(|p| format!("{:?}", p)).collect::<Vec<_>>();
+        log2.lock().unwrap().push((query.to_owned(), params));
+    };
+    assert!(conn.set_query_logger(Some(Box::new(logger))).is_none());
+
+    or_panic!(conn.query("SELECT $1::INT, $2::TEXT", &[&1i32, &"hello"]));
+    or_panic!(conn.batch_execute("SELECT 1"));
+    assert!(conn.set_query_logger(None).is_some());
+    or_panic!(conn.execute("SELECT 1", &[]));
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            (
+                "SELECT $1::INT, $2::TEXT".to_owned(),
+                vec!["1".to_owned(), "\"hello\"".to_owned()],
+            ),
+            ("SELECT 1".to_owned(), vec![]),
+        ]
+    );
+}
+
+#[test]
+fn test_commit_error() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "CREATE TEMPORARY TABLE parent (id INT PRIMARY KEY);
+         CREATE TEMPORARY TABLE child (
+             parent INT REFERENCES parent (id) DEFERRABLE INITIALLY DEFERRED
+         );",
+    ));
+
+    let trans = or_panic!(conn.transaction());
+    or_panic!(trans.execute("INSERT INTO child (parent) VALUES (1)", &[]));
+    match trans.commit() {
+        Err(ref e) if e.code() == Some(&FOREIGN_KEY_VIOLATION) => {}
+        Err(e) => panic!("unexpected error {:?}", e),
+        Ok(()) => panic!("unexpected success"),
+    }
+
+    assert!(conn.is_active());
+    assert_eq!(conn.transaction_status(), TransactionStatus::Idle);
+    let trans = or_panic!(conn.transaction());
+    or_panic!(trans.execute("INSERT INTO parent (id) VALUES (1)", &[]));
+    or_panic!(trans.execute("INSERT INTO child (parent) VALUES (1)", &[]));
+    or_panic!(trans.commit());
+    let rows = or_panic!(conn.query("SELECT parent FROM child", &[]));
+    assert_eq!(rows.get(0).get::<_, i32>(0), 1);
+}
+
+#[test]
+fn test_advisory_locks() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let other = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    or_panic!(conn.advisory_lock(652));
+    assert!(!or_panic!(other.try_advisory_lock(652)));
+    assert!(or_panic!(conn.try_advisory_lock(652)));
+    assert!(or_panic!(conn.advisory_unlock(652)));
+    assert!(!or_panic!(other.try_advisory_lock(652)));
+    assert!(or_panic!(conn.advisory_unlock(652)));
+    assert!(!or_panic!(conn.advisory_unlock(652)));
+
+    assert!(or_panic!(other.try_advisory_lock(652)));
+    assert!(!or_panic!(conn.try_advisory_lock(652)));
+    assert!(or_panic!(other.advisory_unlock(652)));
+
+    {
+        let trans = or_panic!(conn.transaction());
+        or_panic!(trans.advisory_lock(652));
+        assert!(or_panic!(trans.try_advisory_lock(653)));
+        assert!(!or_panic!(other.try_advisory_lock(652)));
+        assert!(!or_panic!(other.try_advisory_lock(653)));
+    }
+    assert!(or_panic!(other.try_advisory_lock(652)));
+    assert!(or_panic!(other.try_advisory_lock(653)));
 }
 
 #[test]
-fn test_notification_iterator_none() {
+fn test_escape() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    assert!(conn.notifications().iter().next().unwrap().is_none());
-}
 
-fn check_notification(expected: Notification, actual: Notification) {
-    assert_eq!(&expected.channel, &actual.channel);
-    assert_eq!(&expected.payload, &actual.payload);
+    let ident = or_panic!(postgres::escape_identifier("foo \"Bar\" \\ baz"));
+    assert_eq!(ident, "\"foo \"\"Bar\"\" \\ baz\"");
+    or_panic!(conn.batch_execute(&format!("CREATE TEMPORARY TABLE {} (id INT)", ident)));
+    let rows = or_panic!(conn.query(
+        "SELECT relname::TEXT FROM pg_class WHERE relname LIKE 'foo %'",
+        &[],
+    ));
+    assert_eq!(rows.get(0).get::<_, String>(0), "foo \"Bar\" \\ baz");
+
+    let value = "it's a \\ backslash";
+    assert_eq!(or_panic!(postgres::escape_literal("it's")), "'it''s'");
+    assert_eq!(
+        or_panic!(postgres::escape_literal(value)),
+        " E'it''s a \\\\ backslash'"
+    );
+    for setting in &["on", "off"] {
+        or_panic!(conn.batch_execute(&format!("SET standard_conforming_strings = {}", setting)));
+        let query = format!("SELECT {}::TEXT", or_panic!(postgres::escape_literal(value)));
+        let rows = or_panic!(conn.query(&query, &[]));
+        assert_eq!(rows.get(0).get::<_, String>(0), value);
+    }
+
+    assert!(postgres::escape_identifier("a\0b").is_err());
+    assert!(postgres::escape_literal("a\0b").is_err());
 }
 
 #[test]
-fn test_notification_iterator_some() {
+fn test_listen_unlisten() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    let notifications = conn.notifications();
-    let mut it = notifications.iter();
-    or_panic!(conn.execute(
-        "LISTEN test_notification_iterator_one_channel",
-        &[],
-    ));
-    or_panic!(conn.execute(
-        "LISTEN test_notification_iterator_one_channel2",
-        &[],
-    ));
-    or_panic!(conn.execute(
-        "NOTIFY test_notification_iterator_one_channel, 'hello'",
-        &[],
-    ));
+    or_panic!(conn.listen("test_listen\"Unlisten"));
     or_panic!(conn.execute(
-        "NOTIFY test_notification_iterator_one_channel2, 'world'",
+        "NOTIFY \"test_listen\"\"Unlisten\", 'hello'",
         &[],
     ));
 
+    let notifications = conn.notifications();
     check_notification(
         Notification {
             process_id: 0,
-            channel: "test_notification_iterator_one_channel".to_string(),
+            channel: "test_listen\"Unlisten".to_string(),
             payload: "hello".to_string(),
         },
-        it.next().unwrap().unwrap(),
-    );
-    check_notification(
-        Notification {
-            process_id: 0,
-            channel: "test_notification_iterator_one_channel2".to_string(),
-            payload: "world".to_string(),
-        },
-        it.next().unwrap().unwrap(),
+        notifications.iter().next().unwrap().unwrap(),
     );
-    assert!(it.next().unwrap().is_none());
 
+    or_panic!(conn.unlisten("test_listen\"Unlisten"));
     or_panic!(conn.execute(
-        "NOTIFY test_notification_iterator_one_channel, '!'",
+        "NOTIFY \"test_listen\"\"Unlisten\", 'world'",
         &[],
     ));
-    check_notification(
-        Notification {
-            process_id: 0,
-            channel: "test_notification_iterator_one_channel".to_string(),
-            payload: "!".to_string(),
-        },
-        it.next().unwrap().unwrap(),
-    );
-    assert!(it.next().unwrap().is_none());
+    assert!(notifications.iter().next().unwrap().is_none());
+
+    match conn.listen("") {
+        Err(ref e) if e.as_io().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
 }
 
 #[test]
-fn test_notifications_next_block() {
+fn test_listen_all() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    or_panic!(conn.execute("LISTEN test_notifications_next_block", &[]));
+    let channels = [
+        "test_listen_all_1",
+        "test_listen_all_2",
+        "test_listen_all\"3",
+        "test_listen_all_4",
+        "Test_Listen_All_5",
+    ];
+    or_panic!(conn.listen_all(&channels));
 
-    let _t = thread::spawn(|| {
-        let conn = or_panic!(Connection::connect(
-            "postgres://postgres@localhost:5433",
-            TlsMode::None,
-        ));
-        thread::sleep(Duration::from_millis(500));
-        or_panic!(conn.execute(
-            "NOTIFY test_notifications_next_block, 'foo'",
-            &[],
-        ));
-    });
+    match conn.listen_all(&["test_listen_all_6", ""]) {
+        Err(ref e) if e.as_io().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    or_panic!(conn.batch_execute(
+        "NOTIFY test_listen_all_1, 'one';
+         NOTIFY test_listen_all_2, 'two';
+         NOTIFY \"test_listen_all\"\"3\", 'three';
+         NOTIFY test_listen_all_4, 'four';
+         NOTIFY \"Test_Listen_All_5\", 'five';
+         NOTIFY test_listen_all_6, 'six';",
+    ));
 
     let notifications = conn.notifications();
-    check_notification(
-        Notification {
-            process_id: 0,
-            channel: "test_notifications_next_block".to_string(),
-            payload: "foo".to_string(),
-        },
-        notifications.blocking_iter().next().unwrap().unwrap(),
-    );
+    let mut it = notifications.iter();
+    for (channel, payload) in channels.iter().zip(&["one", "two", "three", "four", "five"]) {
+        check_notification(
+            Notification {
+                process_id: 0,
+                channel: channel.to_string(),
+                payload: payload.to_string(),
+            },
+            it.next().unwrap().unwrap(),
+        );
+    }
+    assert!(it.next().unwrap().is_none());
 }
 
 #[test]
@@ -1018,6 +2408,206 @@ fn native_tls_ssl() {
     or_panic!(conn.execute("SELECT 1::VARCHAR", &[]));
 }
 
+#[test]
+fn test_direct_tls() {
+    #[derive(Debug)]
+    struct Probe;
+
+    impl TlsHandshake for Probe {
+        fn tls_handshake(
+            &self,
+            _: &str,
+            mut stream: Stream,
+        ) -> Result<Box<TlsStream>, Box<StdError + Sync + Send>> {
+            stream.write_all(b"\x16\x03\x01\x00\x00\x00\x00\x00")?;
+            stream.flush()?;
+            Err("probe".into())
+        }
+    }
+
+    // Returns the first 8 bytes sent by the client.
+    fn connect(direct_tls: bool) -> (postgres::Result<Connection>, Vec<u8>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut socket = listener.accept().unwrap().0;
+            let mut buf = vec![0; 8];
+            socket.read_exact(&mut buf).unwrap();
+            let _ = socket.write_all(b"N");
+            buf
+        });
+
+        let params = ConnectParams::builder()
+            .port(port)
+            .user("postgres", None)
+            .direct_tls(direct_tls)
+            .build(Host::Tcp("127.0.0.1".to_owned()));
+        let result = Connection::connect(params, TlsMode::Require(&Probe));
+        (result, server.join().unwrap())
+    }
+
+    let (result, sent) = connect(false);
+    assert_eq!(sent, [0, 0, 0, 8, 4, 210, 22, 47]);
+    match result {
+        Err(e) => assert!(e.to_string().contains("does not support TLS"), "{}", e),
+        Ok(_) => panic!("unexpected success"),
+    }
+
+    let (result, sent) = connect(true);
+    assert_eq!(sent, b"\x16\x03\x01\x00\x00\x00\x00\x00");
+    match result {
+        Err(e) => assert!(e.to_string().contains("probe"), "{}", e),
+        Ok(_) => panic!("unexpected success"),
+    }
+}
+
+#[test]
+fn test_max_message_size() {
+    fn assert_invalid_data(result: postgres::Result<Connection>) {
+        match result {
+            Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::InvalidData) => {}
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("unexpected success"),
+        }
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let mut socket = listener.accept().unwrap().0;
+        let mut len = [0; 4];
+        socket.read_exact(&mut len).unwrap();
+        let len = u32::from(len[0]) << 24 | u32::from(len[1]) << 16 | u32::from(len[2]) << 8 |
+            u32::from(len[3]);
+        let mut startup = vec![0; len as usize - 4];
+        socket.read_exact(&mut startup).unwrap();
+        socket.write_all(b"R\xff\xff\xff\xf0").unwrap();
+        // wait for the client to hang up
+        let _ = socket.read_to_end(&mut vec![]);
+    });
+    let params = ConnectParams::builder()
+        .port(port)
+        .user("postgres", None)
+        .build(Host::Tcp("127.0.0.1".to_owned()));
+    assert_invalid_data(Connection::connect(params, TlsMode::None));
+    server.join().unwrap();
+
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .max_message_size(16)
+        .build(Host::Tcp("localhost".to_owned()));
+    assert_invalid_data(Connection::connect(params, TlsMode::None));
+}
+
+#[test]
+fn test_prefer_simple_query() {
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .prefer_simple_query(true)
+        .build(Host::Tcp("localhost".to_owned()));
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+    or_panic!(conn.execute(
+        "CREATE TEMPORARY TABLE foo (id INT, name TEXT, data BYTEA)",
+        &[],
+    ));
+
+    let name = "O'Reilly \\ $1 --'); DROP TABLE foo; --";
+    let n = or_panic!(conn.execute(
+        "INSERT INTO foo (id, name, data) VALUES ($1, $2, $3), ($1 + 1, NULL, $4)",
+        &[&1i32, &name, &&b"\x00'\\"[..], &None::<Vec<u8>>],
+    ));
+    assert_eq!(n, 2);
+
+    let rows = or_panic!(conn.query(
+        "SELECT id, name, data = $2, '$1' /* $1 */ FROM foo WHERE id = $1 -- $2",
+        &[&1i64, &&b"\x00'\\"[..]],
+    ));
+    assert_eq!(rows.result_formats(), [Format::Text; 4]);
+    assert_eq!(rows.len(), 1);
+    let row = rows.get(0);
+    assert_eq!(row.get::<_, String>(0), "1");
+    assert_eq!(row.get::<_, String>(1), name);
+    assert_eq!(row.get::<_, String>(2), "t");
+    assert_eq!(row.get::<_, String>(3), "$1");
+
+    let rows = or_panic!(conn.query(
+        "SELECT $1, $2, $3, $4, E'\\\\'",
+        &[&true, &-32768i16, &f64::NAN, &0.1f32],
+    ));
+    let row = rows.get(0);
+    assert_eq!(row.get::<_, String>(0), "t");
+    assert_eq!(row.get::<_, String>(1), "-32768");
+    assert_eq!(row.get::<_, String>(2), "NaN");
+    assert_eq!(row.get::<_, String>(3), "0.1");
+    assert_eq!(row.get::<_, String>(4), "\\");
+
+    let rows = or_panic!(conn.query(
+        "SELECT $1::BOOL, $2::INT2, $3::INT4, $4::INT8, 'pg_class'::REGCLASS::OID, $5::FLOAT4,
+                $6::FLOAT8, $7::BYTEA, NULL::INT4, '{1}'::INT4[]",
+        &[&true, &-32768i16, &7i32, &-9i64, &0.5f32, &f64::NEG_INFINITY, &&b"\x00'\\"[..]],
+    ));
+    let row = rows.get(0);
+    assert_eq!(row.get::<_, bool>(0), true);
+    assert_eq!(row.get::<_, i16>(1), -32768);
+    assert_eq!(row.get::<_, i32>(2), 7);
+    assert_eq!(row.get::<_, i64>(3), -9);
+    assert_eq!(row.get::<_, Oid>(4), 1259);
+    assert_eq!(row.get::<_, f32>(5), 0.5);
+    assert_eq!(row.get::<_, f64>(6), f64::NEG_INFINITY);
+    assert_eq!(row.get::<_, Vec<u8>>(7), b"\x00'\\");
+    assert_eq!(row.get::<_, Option<i32>>(8), None);
+    assert_eq!(row.get::<_, String>(2), "7");
+    match row.get_opt::<_, i64>(2) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    match row.get_opt::<_, Vec<i32>>(9) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    let count: i64 = or_panic!(conn.query_scalar("SELECT COUNT(*) FROM foo", &[]));
+    assert_eq!(count, 2);
+
+    match conn.query("SELECT $1", &[&1i8]) {
+        Err(ref e) if e.as_conversion().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    for query in &["SELECT 'a\\b', $1", "SELECT $1; SELECT 1"] {
+        match conn.query(query, &[&1i32]) {
+            Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::InvalidInput) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+    assert!(conn.try_query("SELECT $2", &[&1i32]).is_err());
+    assert_eq!(or_panic!(conn.query("SELECT COUNT(*) FROM foo", &[])).len(), 1);
+}
+
+#[test]
+fn test_connector() {
+    #[derive(Debug)]
+    struct LocalConnector(Arc<Mutex<Vec<(String, u16)>>>);
+
+    impl Connector for LocalConnector {
+        fn connect(&self, host: &str, port: u16, _: Option<Duration>) -> io::Result<TcpStream> {
+            self.0.lock().unwrap().push((host.to_owned(), port));
+            TcpStream::connect("127.0.0.1:5433")
+        }
+    }
+
+    let calls = Arc::new(Mutex::new(vec![]));
+    let params = ConnectParams::builder()
+        .port(1)
+        .user("postgres", None)
+        .connector(LocalConnector(calls.clone()))
+        .build(Host::Tcp("db.invalid".to_owned()));
+    assert!(params.connector().is_some());
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+    or_panic!(conn.execute("SELECT 1", &[]));
+    assert_eq!(*calls.lock().unwrap(), [("db.invalid".to_owned(), 1)]);
+}
+
 #[test]
 fn test_plaintext_pass() {
     or_panic!(Connection::connect(
@@ -1083,6 +2673,50 @@ fn test_md5_pass_wrong_pass() {
     }
 }
 
+#[test]
+fn test_md5_prehashed_pass() {
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user_md5("md5_user", "md57cd2b9a92689eab2e58caf2da80afa15")
+        .database("postgres")
+        .build(Host::Tcp("localhost".to_owned()));
+    or_panic!(Connection::connect(params, TlsMode::None));
+
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user_md5("md5_user", "md500000000000000000000000000000000")
+        .database("postgres")
+        .build(Host::Tcp("localhost".to_owned()));
+    match Connection::connect(params, TlsMode::None) {
+        Err(ref e) if e.code() == Some(&INVALID_PASSWORD) => {}
+        Err(err) => panic!("Unexpected error {:?}", err),
+        _ => panic!("Expected error"),
+    }
+
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user_md5("md5_user", "password")
+        .database("postgres")
+        .build(Host::Tcp("localhost".to_owned()));
+    match Connection::connect(params, TlsMode::None) {
+        Err(ref e) if e.as_connection().is_some() => {}
+        Err(err) => panic!("Unexpected error {:?}", err),
+        _ => panic!("Expected error"),
+    }
+
+    // the hash cannot be used with other authentication methods
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user_md5("scram_user", "md57cd2b9a92689eab2e58caf2da80afa15")
+        .database("postgres")
+        .build(Host::Tcp("localhost".to_owned()));
+    match Connection::connect(params, TlsMode::None) {
+        Err(ref e) if e.as_connection().is_some() => {}
+        Err(err) => panic!("Unexpected error {:?}", err),
+        _ => panic!("Expected error"),
+    }
+}
+
 #[test]
 fn test_scram_pass() {
     or_panic!(Connection::connect(
@@ -1139,6 +2773,50 @@ fn test_execute_copy_from_err() {
     }
 }
 
+#[test]
+fn test_execute_copy_names_method() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.execute("CREATE TEMPORARY TABLE foo (id INT)", &[]));
+    or_panic!(conn.execute("INSERT INTO foo (id) VALUES (1)", &[]));
+
+    let err = conn.execute("COPY foo (id) FROM STDIN", &[]).unwrap_err();
+    match err.as_db() {
+        Some(err) if err.message.contains("`Statement::copy_in`") => {}
+        _ => panic!("Unexpected error {:?}", err),
+    }
+
+    let err = conn.execute("COPY foo (id) TO STDOUT", &[]).unwrap_err();
+    match err.as_io() {
+        Some(e) if e.to_string().contains("`Statement::copy_out`") => {}
+        _ => panic!("Unexpected error {:?}", err),
+    }
+
+    or_panic!(conn.execute("SELECT 1", &[]));
+}
+
+#[test]
+fn test_batch_execute_error_index() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let query = "CREATE TEMPORARY TABLE foo (id INT);
+                 INSERT INTO missing (id) VALUES (1);
+                 INSERT INTO foo (id) VALUES (1);";
+    match conn.batch_execute(query) {
+        Err(ref e) if e.code() == Some(&UNDEFINED_TABLE) => {
+            assert_eq!(e.batch_index(), Some(2));
+            assert_eq!(e.prepare_index(), None);
+            assert!(e.to_string().contains("query 2 of batch"), "{}", e);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    or_panic!(conn.batch_execute("SELECT 1"));
+}
+
 #[test]
 fn test_batch_execute_copy_from_err() {
     let conn = or_panic!(Connection::connect(
@@ -1199,6 +2877,44 @@ fn test_copy() {
     );
 }
 
+#[test]
+fn test_copy_in_writer() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.execute("CREATE TEMPORARY TABLE foo (id INT, name TEXT)", &[]));
+    let stmt = or_panic!(conn.prepare("COPY foo (id, name) FROM STDIN"));
+
+    let mut writer = or_panic!(stmt.copy_in_writer(&[]));
+    or_panic!(writer.write_all(b"1\tjohn\n2\tja"));
+    or_panic!(writer.write_all(b"ne\n"));
+    or_panic!(writer.write_all(b"3\tjoe\n"));
+    assert_eq!(or_panic!(writer.finish()), 3);
+
+    {
+        let mut writer = or_panic!(stmt.copy_in_writer(&[]));
+        or_panic!(writer.write_all(b"4\tjim\n"));
+    }
+
+    let rows = or_panic!(conn.query("SELECT id, name FROM foo ORDER BY id", &[]));
+    let rows = rows.iter()
+        .map(|r| (r.get(0), r.get(1)))
+        .collect::<Vec<(i32, String)>>();
+    assert_eq!(
+        rows,
+        [
+            (1, "john".to_owned()),
+            (2, "jane".to_owned()),
+            (3, "joe".to_owned()),
+        ]
+    );
+
+    let stmt = or_panic!(conn.prepare("SELECT 1"));
+    assert!(stmt.copy_in_writer(&[]).is_err());
+    or_panic!(conn.execute("SELECT 1", &[]));
+}
+
 #[test]
 fn test_query_copy_out_err() {
     let conn = or_panic!(Connection::connect(
@@ -1219,24 +2935,183 @@ fn test_query_copy_out_err() {
 }
 
 #[test]
-fn test_copy_out() {
+fn test_copy_out() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "
+         CREATE TEMPORARY TABLE foo (id INT);
+         INSERT INTO foo (id) VALUES (0), (1), (2), (3)",
+    ));
+    let stmt = or_panic!(conn.prepare(
+        "COPY (SELECT id FROM foo ORDER BY id) TO STDOUT",
+    ));
+    let mut buf = vec![];
+    let count = or_panic!(stmt.copy_out(&[], &mut buf));
+    assert_eq!(count, 4);
+    assert_eq!(buf, b"0\n1\n2\n3\n");
+    or_panic!(conn.batch_execute("SELECT 1"));
+}
+
+#[test]
+fn test_copy_out_lines() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "
+         CREATE TEMPORARY TABLE foo (id INT, name TEXT);
+         INSERT INTO foo (id, name)
+             SELECT i, repeat('x', i * 10) FROM generate_series(1, 1000) i",
+    ));
+
+    let stmt = or_panic!(conn.prepare(
+        "COPY (SELECT row_to_json(foo) FROM foo ORDER BY id) TO STDOUT",
+    ));
+    let lines = or_panic!(or_panic!(stmt.copy_out_lines(&[])).collect::<Vec<_>>());
+    assert_eq!(lines.len(), 1000);
+    assert_eq!(lines[0], format!("{{\"id\":1,\"name\":\"{}\"}}", "x".repeat(10)).as_bytes());
+    assert!(lines.iter().all(|l| !l.contains(&b'\n')));
+
+    // dropping the iterator early leaves the connection usable
+    {
+        let mut lines = or_panic!(stmt.copy_out_lines(&[]));
+        assert!(or_panic!(lines.next()).is_some());
+    }
+    assert_eq!(or_panic!(conn.execute("SELECT 1", &[])), 1);
+
+    let stmt = or_panic!(conn.prepare("COPY foo TO STDOUT (FORMAT binary)"));
+    assert!(stmt.copy_out_lines(&[]).is_err());
+    let stmt = or_panic!(conn.prepare("SELECT 1"));
+    assert!(stmt.copy_out_lines(&[]).is_err());
+    or_panic!(conn.batch_execute("SELECT 1"));
+}
+
+#[test]
+fn test_copy_in_options() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute("CREATE TEMPORARY TABLE foo (id INT, name TEXT)"));
+
+    let mut options = CopyOptions::new();
+    options.delimiter(";").null("<none>");
+    let mut data = "1;jo'hn\n2;<none>\n3;\\N\n".as_bytes();
+    assert_eq!(or_panic!(conn.copy_in("foo (id, name)", &options, &mut data)), 3);
+
+    let mut options = CopyOptions::new();
+    options
+        .format(CopyFormat::Csv)
+        .delimiter("|")
+        .quote("'")
+        .null("")
+        .header(true);
+    let mut data = "id|name\n4|'a|b'\n5|\n".as_bytes();
+    assert_eq!(or_panic!(conn.copy_in("foo", &options, &mut data)), 2);
+
+    let rows = or_panic!(conn.query("SELECT id, name FROM foo ORDER BY id", &[]));
+    let rows = rows.iter()
+        .map(|r| (r.get(0), r.get(1)))
+        .collect::<Vec<(i32, Option<String>)>>();
+    assert_eq!(
+        rows,
+        [
+            (1, Some("jo'hn".to_owned())),
+            (2, None),
+            (3, Some("N".to_owned())),
+            (4, Some("a|b".to_owned())),
+            (5, None),
+        ]
+    );
+
+    let mut options = CopyOptions::new();
+    options.delimiter(";;");
+    match conn.copy_in("foo", &options, &mut "".as_bytes()) {
+        Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::InvalidInput) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    assert!(conn.is_active());
+}
+
+#[test]
+fn test_copy_out_binary() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "
+         CREATE TEMPORARY TABLE foo (id INT, name TEXT);
+         INSERT INTO foo (id, name) VALUES (1, 'steven'), (2, NULL), (3, 'timothy')",
+    ));
+    let stmt = or_panic!(conn.prepare(
+        "COPY (SELECT id, name FROM foo ORDER BY id) TO STDOUT (FORMAT binary)",
+    ));
+    let rows = or_panic!(stmt.copy_out_binary(&[], &[INT4, TEXT]));
+    assert_eq!(rows.types(), &[INT4, TEXT]);
+    let rows = or_panic!(rows.collect::<Vec<_>>());
+    assert_eq!(
+        vec![
+            (1, Some("steven".to_owned())),
+            (2, None),
+            (3, Some("timothy".to_owned())),
+        ],
+        rows.iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect::<Vec<(i32, Option<String>)>>()
+    );
+
+    match rows[0].get_opt::<String>(0) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    let mut rows = or_panic!(stmt.copy_out_binary(&[], &[INT4]));
+    match rows.next() {
+        Err(ref e) if e.as_io().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    drop(rows);
+    or_panic!(conn.batch_execute("SELECT 1"));
+
+    let stmt = or_panic!(conn.prepare("COPY foo TO STDOUT"));
+    match stmt.copy_out_binary(&[], &[INT4, TEXT]) {
+        Err(ref e) if e.as_io().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    or_panic!(conn.batch_execute("SELECT 1"));
+}
+
+#[test]
+fn test_copy_out_binary_streaming() {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
         TlsMode::None,
     ));
-    or_panic!(conn.batch_execute(
-        "
-         CREATE TEMPORARY TABLE foo (id INT);
-         INSERT INTO foo (id) VALUES (0), (1), (2), (3)",
-    ));
     let stmt = or_panic!(conn.prepare(
-        "COPY (SELECT id FROM foo ORDER BY id) TO STDOUT",
+        "COPY (SELECT i, repeat('x', i % 100) FROM generate_series(1, 10000) i)
+         TO STDOUT (FORMAT binary)",
     ));
-    let mut buf = vec![];
-    let count = or_panic!(stmt.copy_out(&[], &mut buf));
-    assert_eq!(count, 4);
-    assert_eq!(buf, b"0\n1\n2\n3\n");
-    or_panic!(conn.batch_execute("SELECT 1"));
+
+    let mut rows = or_panic!(stmt.copy_out_binary(&[], &[INT4, TEXT]));
+    let mut count = 0;
+    while let Some(row) = or_panic!(rows.next()) {
+        count += 1;
+        assert_eq!(row.get::<i32>(0), count);
+        assert_eq!(row.get::<String>(1).len(), count as usize % 100);
+    }
+    assert_eq!(count, 10000);
+    assert!(or_panic!(rows.next()).is_none());
+    drop(rows);
+
+    let mut rows = or_panic!(stmt.copy_out_binary(&[], &[INT4, TEXT]));
+    assert_eq!(or_panic!(rows.next()).unwrap().get::<i32>(0), 1);
+    drop(rows);
+    assert_eq!(or_panic!(conn.query("SELECT 1::INT4", &[])).get(0).get::<_, i32>(0), 1);
 }
 
 #[test]
@@ -1261,6 +3136,98 @@ fn test_copy_out_error() {
     }
 }
 
+#[test]
+fn test_query_scalar() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "CREATE TEMPORARY TABLE foo (id INT);
+         INSERT INTO foo (id) VALUES (1), (2), (3)",
+    ));
+
+    let count: i64 = or_panic!(conn.query_scalar("SELECT count(*) FROM foo", &[]));
+    assert_eq!(3, count);
+
+    let trans = or_panic!(conn.transaction());
+    let max: i32 = or_panic!(GenericConnection::query_scalar(
+        &trans,
+        "SELECT max(id) FROM foo WHERE id < $1",
+        &[&3i32]
+    ));
+    assert_eq!(2, max);
+
+    for query in &["SELECT id FROM foo", "SELECT 1, 2", "SELECT 1 WHERE false"] {
+        match conn.query_scalar::<i32>(query, &[]) {
+            Err(ref e) if e.as_conversion().is_some() => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+    match conn.query_scalar::<String>("SELECT count(*) FROM foo", &[]) {
+        Err(ref e) if e.as_conversion().map_or(false, |e| e.is::<WrongType>()) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_from_row() {
+    #[derive(Debug, PartialEq)]
+    struct Person {
+        id: i32,
+        name: String,
+    }
+
+    impl FromRow for Person {
+        fn from_row(row: &Row) -> postgres::Result<Person> {
+            fn column<T: FromSql>(row: &Row, name: &str) -> postgres::Result<T> {
+                match row.get_opt(name) {
+                    Some(value) => value,
+                    None => {
+                        let msg = format!("missing column `{}`", name);
+                        Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into())
+                    }
+                }
+            }
+
+            Ok(Person {
+                id: column(row, "id")?,
+                name: column(row, "name")?,
+            })
+        }
+    }
+
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let rows = or_panic!(conn.query(
+        "SELECT 'alice' AS name, 1 AS id UNION ALL SELECT 'bob', 2 ORDER BY id",
+        &[],
+    ));
+    let people = or_panic!(rows.map::<Person>().collect::<postgres::Result<Vec<_>>>());
+    assert_eq!(
+        people,
+        [
+            Person {
+                id: 1,
+                name: "alice".to_owned(),
+            },
+            Person {
+                id: 2,
+                name: "bob".to_owned(),
+            },
+        ]
+    );
+
+    let rows = or_panic!(conn.query("SELECT 1 AS id", &[]));
+    match rows.map::<Person>().next() {
+        Some(Err(ref e)) if e.to_string().contains("missing column `name`") => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
 #[test]
 // Just make sure the impls don't infinite loop
 fn test_generic_connection() {
@@ -1299,6 +3266,232 @@ fn test_custom_range_element_type() {
     assert_eq!(&Kind::Range(FLOAT8), ty.kind());
 }
 
+#[test]
+fn test_preload_types() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "CREATE TYPE pg_temp.preload_mood AS ENUM ('sad', 'happy');
+         CREATE TYPE pg_temp.preload_pair AS (a INT4, b TEXT);",
+    ));
+    let rows = or_panic!(conn.query(
+        "SELECT 'pg_temp.preload_mood'::regtype::oid, 'pg_temp.preload_pair'::regtype::oid",
+        &[],
+    ));
+    let mood: Oid = rows.get(0).get(0);
+    let pair: Oid = rows.get(0).get(1);
+
+    or_panic!(conn.preload_types(&[mood, pair]));
+
+    // log every statement the server runs back to us so type lookups can be counted
+    let log = Arc::new(Mutex::new(vec![]));
+    let handler_log = log.clone();
+    conn.set_notice_handler(Box::new(move |notice: DbError| {
+        handler_log.lock().unwrap().push(notice.message)
+    }));
+    or_panic!(conn.batch_execute("SET client_min_messages = LOG; SET log_statement = 'all'"));
+
+    let stmt = or_panic!(conn.prepare(
+        "SELECT 'happy'::pg_temp.preload_mood, (1, 'a')::pg_temp.preload_pair",
+    ));
+    assert_eq!(stmt.columns()[0].type_().name(), "preload_mood");
+    assert_eq!(
+        stmt.columns()[0].type_().kind(),
+        &Kind::Enum(vec!["sad".to_owned(), "happy".to_owned()])
+    );
+    assert_eq!(stmt.columns()[1].type_().name(), "preload_pair");
+    match *stmt.columns()[1].type_().kind() {
+        Kind::Composite(ref fields) => assert_eq!(fields.len(), 2),
+        ref kind => panic!("unexpected kind {:?}", kind),
+    }
+
+    let log = log.lock().unwrap();
+    assert!(!log.iter().any(|m| m.contains("pg_type")), "{:?}", *log);
+}
+
+#[test]
+fn test_skip_type_introspection() {
+    let params = ConnectParams::builder()
+        .user("postgres", None)
+        .port(5433)
+        .skip_type_introspection(true)
+        .build(Host::Tcp("localhost".to_owned()));
+    assert!(params.skip_type_introspection());
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+    or_panic!(conn.batch_execute(
+        "CREATE TYPE pg_temp.skip_mood AS ENUM ('sad', 'happy')",
+    ));
+    let oid: Oid = or_panic!(conn.query("SELECT 'pg_temp.skip_mood'::regtype::oid", &[]))
+        .get(0)
+        .get(0);
+
+    let log = Arc::new(Mutex::new(vec![]));
+    let handler_log = log.clone();
+    conn.set_notice_handler(Box::new(move |notice: DbError| {
+        handler_log.lock().unwrap().push(notice.message)
+    }));
+    or_panic!(conn.batch_execute("SET client_min_messages = LOG; SET log_statement = 'all'"));
+
+    let rows = or_panic!(conn.query("SELECT 'happy'::pg_temp.skip_mood", &[]));
+    let ty = rows.columns()[0].type_();
+    assert_eq!(ty.oid(), oid);
+    assert_eq!(ty.name(), oid.to_string());
+    assert_eq!(ty.kind(), &Kind::Simple);
+    assert_eq!(rows.get(0).get_bytes(0), Some(&b"happy"[..]));
+    or_panic!(conn.preload_types(&[oid]));
+
+    let log = log.lock().unwrap();
+    assert!(!log.iter().any(|m| m.contains("pg_type")), "{:?}", *log);
+}
+
+#[test]
+fn test_owned_statement() {
+    struct Queries {
+        conn: Connection,
+        add: OwnedStatement,
+    }
+
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let add = or_panic!(conn.prepare("SELECT $1::INT4 + 1")).into_owned();
+    assert_eq!(add.param_types(), &[INT4]);
+    assert_eq!(add.columns()[0].type_(), &INT4);
+    let queries = Queries {
+        conn: conn,
+        add: add,
+    };
+
+    let queries = thread::spawn(move || {
+        {
+            let stmt = or_panic!(queries.add.bind(&queries.conn));
+            assert_eq!(or_panic!(stmt.query(&[&1i32])).get(0).get::<_, i32>(0), 2);
+        }
+        queries
+    }).join()
+        .unwrap();
+
+    // dropping the bound statement does not close it
+    let stmt = or_panic!(queries.add.bind(&queries.conn));
+    assert_eq!(or_panic!(stmt.query(&[&2i32])).get(0).get::<_, i32>(0), 3);
+
+    let other = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert!(queries.add.bind(&other).is_err());
+
+    or_panic!(queries.add.clone().close(&queries.conn));
+    assert!(stmt.query(&[&2i32]).is_err());
+}
+
+#[test]
+fn test_owned_statement_cached() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let add = or_panic!(conn.prepare_cached("SELECT $1::INT4 + 1")).into_owned();
+    or_panic!(add.close(&conn));
+
+    // the statement is still held by the cache
+    let stmt = or_panic!(conn.prepare_cached("SELECT $1::INT4 + 1"));
+    assert_eq!(or_panic!(stmt.query(&[&1i32])).get(0).get::<_, i32>(0), 2);
+}
+
+#[test]
+fn test_statement_from_name() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute("PREPARE foo AS SELECT $1::INT4 + 1, 'bar'::TEXT"));
+
+    let stmt = or_panic!(conn.statement_from_name("foo"));
+    assert_eq!(stmt.param_types(), &[INT4]);
+    assert_eq!(stmt.columns().len(), 2);
+    assert_eq!(stmt.columns()[1].type_(), &TEXT);
+    let rows = or_panic!(stmt.query(&[&41i32]));
+    assert_eq!(rows.get(0).get::<_, i32>(0), 42);
+    assert_eq!(rows.get(0).get::<_, String>(1), "bar");
+    or_panic!(stmt.finish());
+
+    // the statement is still usable after the Statement is gone
+    let rows = or_panic!(conn.query("EXECUTE foo(1)", &[]));
+    assert_eq!(rows.get(0).get::<_, i32>(0), 2);
+
+    match conn.statement_from_name("nonexistent") {
+        Err(ref e) if e.as_db().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    or_panic!(conn.batch_execute("SELECT 1"));
+}
+
+#[test]
+fn test_prepare_all() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute("CREATE TEMPORARY TABLE foo (id INT)"));
+
+    let stmts = or_panic!(conn.prepare_all(&[
+        "SELECT 1::INT4",
+        "SELECT $1::TEXT",
+        "INSERT INTO foo (id) VALUES ($1)",
+        "SELECT id FROM foo",
+        "DELETE FROM foo",
+    ]));
+    assert_eq!(stmts.len(), 5);
+    assert_eq!(or_panic!(stmts[0].query(&[])).get(0).get::<_, i32>(0), 1);
+    assert_eq!(stmts[1].param_types(), &[TEXT]);
+    assert_eq!(or_panic!(stmts[2].execute(&[&5i32])), 1);
+    assert_eq!(or_panic!(stmts[3].query(&[])).get(0).get::<_, i32>(0), 5);
+    assert_eq!(stmts[3].columns()[0].name(), "id");
+    assert_eq!(or_panic!(stmts[4].execute(&[])), 1);
+
+    match conn.prepare_all(&["SELECT 1", "SELECT 2", "SELEC 3", "SELECT 4"]) {
+        Err(ref e) if e.code() == Some(&SYNTAX_ERROR) => {
+            assert_eq!(e.prepare_index(), Some(2));
+            assert_eq!(e.batch_index(), None);
+            assert!(e.to_string().contains("query at index 2"), "{}", e);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    match conn.prepare_all(&["SELECT 1", "SELECT * FROM missing"]) {
+        Err(ref e) if e.code() == Some(&UNDEFINED_TABLE) => assert_eq!(e.prepare_index(), Some(1)),
+        r => panic!("unexpected result {:?}", r),
+    }
+    assert_eq!(or_panic!(conn.query("SELECT 1::INT4", &[])).get(0).get::<_, i32>(0), 1);
+    let count: i64 = or_panic!(conn.query("SELECT COUNT(*) FROM pg_prepared_statements", &[]))
+        .get(0)
+        .get(0);
+    assert_eq!(count, 5);
+}
+
+#[test]
+fn test_cached_statement_queries() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert_eq!(conn.cached_statement_count(), 0);
+    assert!(conn.cached_statement_queries().is_empty());
+
+    or_panic!(conn.prepare_cached("SELECT 1"));
+    or_panic!(conn.prepare_cached("SELECT 2"));
+    or_panic!(conn.prepare_cached("SELECT 1"));
+    or_panic!(conn.prepare("SELECT 3"));
+
+    assert_eq!(conn.cached_statement_count(), 2);
+    let mut queries = conn.cached_statement_queries();
+    queries.sort();
+    assert_eq!(queries, ["SELECT 1", "SELECT 2"]);
+}
+
 #[test]
 fn test_prepare_cached() {
     let conn = or_panic!(Connection::connect(
@@ -1372,6 +3565,96 @@ fn test_parameter() {
     assert_eq!(None, conn.parameter("asdf"));
 }
 
+#[test]
+fn test_integer_datetimes() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert_eq!(Some("on".to_string()), conn.parameter("integer_datetimes"));
+    assert!(conn.integer_datetimes());
+}
+
+#[test]
+fn test_prepare_datetime_statement() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let prepared = || -> i64 {
+        let rows = or_panic!(conn.query("SELECT count(*) FROM pg_prepared_statements", &[]));
+        rows.get(0).get(0)
+    };
+    let before = prepared();
+
+    {
+        let stmt = or_panic!(conn.prepare(
+            "SELECT ts::text, ts FROM (SELECT now() AS ts) t WHERE ts > now() - '1 day'::interval",
+        ));
+        assert_eq!(before + 1, prepared());
+        let rows = or_panic!(stmt.query(&[]));
+        assert!(!rows.get(0).get::<_, String>(0).is_empty());
+        assert!(rows.get(0).get_bytes(1).is_some());
+    }
+    assert_eq!(before, prepared());
+
+    assert!(conn.prepare("SELECT ts FROM nonexistent_table").is_err());
+    assert_eq!(before, prepared());
+}
+
+#[test]
+fn test_read_only() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert!(!or_panic!(conn.is_read_only()));
+
+    let params = ConnectParams::builder()
+        .user("postgres", None)
+        .port(5433)
+        .read_only(true)
+        .build(Host::Tcp("localhost".to_owned()));
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+    assert!(or_panic!(conn.is_read_only()));
+
+    or_panic!(conn.query("SELECT 1", &[]));
+    match conn.execute("CREATE TEMPORARY TABLE foo (id INT)", &[]) {
+        Err(ref e) if e.code() == Some(&READ_ONLY_SQL_TRANSACTION) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_application_name() {
+    let params = ConnectParams::builder()
+        .user("postgres", None)
+        .port(5433)
+        .application_name("test_application_name")
+        .build(Host::Tcp("localhost".to_owned()));
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+    assert_eq!(
+        conn.application_name(),
+        Some("test_application_name".to_owned())
+    );
+
+    let rows = or_panic!(conn.query(
+        "SELECT application_name FROM pg_stat_activity WHERE pid = pg_backend_pid()",
+        &[],
+    ));
+    assert_eq!(rows.get(0).get::<_, String>(0), "test_application_name");
+}
+
+#[test]
+fn test_server_version() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let (major, _, _) = conn.server_version().unwrap();
+    assert!(major >= 9);
+}
+
 #[test]
 fn test_get_bytes() {
     let conn = or_panic!(Connection::connect(
@@ -1537,3 +3820,136 @@ fn transaction_set_config() {
     trans.set_config(&config).unwrap();
     trans.finish().unwrap();
 }
+
+#[test]
+fn test_query_cancellable() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancel2 = cancel.clone();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        cancel2.store(true, Ordering::SeqCst);
+    });
+
+    match conn.query_cancellable("SELECT pg_sleep(10)", &[], &cancel) {
+        Err(ref e) if e.code() == Some(&QUERY_CANCELED) => {}
+        Err(e) => panic!("unexpected error {:?}", e),
+        _ => panic!("unexpected success"),
+    }
+    t.join().unwrap();
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let rows = or_panic!(conn.query_cancellable("SELECT 1::INT", &[], &cancel));
+    assert_eq!(1, rows.get(0).get::<_, i32>(0));
+}
+
+#[test]
+fn test_query_cancellable_read_timeout() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.set_read_timeout(Some(Duration::from_millis(300))));
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let start = Instant::now();
+    match conn.query_cancellable("SELECT pg_sleep(5)", &[], &cancel) {
+        Err(ref e) if e.as_io().map(|e| e.kind()) == Some(io::ErrorKind::TimedOut) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(conn.is_desynchronized());
+}
+
+#[test]
+fn test_query_cancellable_simple_query() {
+    let params = ConnectParams::builder()
+        .port(5433)
+        .user("postgres", None)
+        .prefer_simple_query(true)
+        .build(Host::Tcp("localhost".to_owned()));
+    let conn = or_panic!(Connection::connect(params, TlsMode::None));
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let rows = or_panic!(conn.query_cancellable("SELECT $1::TEXT", &[&"foo"], &cancel));
+    assert_eq!(rows.result_formats(), [Format::Text]);
+    assert_eq!(rows.get(0).get::<_, String>(0), "foo");
+
+    let cancel2 = cancel.clone();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        cancel2.store(true, Ordering::SeqCst);
+    });
+    match conn.query_cancellable("SELECT pg_sleep($1)", &[&10i32], &cancel) {
+        Err(ref e) if e.code() == Some(&QUERY_CANCELED) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    t.join().unwrap();
+}
+
+#[test]
+fn test_logical_replication() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "DROP TABLE IF EXISTS replication_test;
+         CREATE TABLE replication_test (id INT)",
+    ));
+
+    let repl = or_panic!(ReplicationConnection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let system = or_panic!(repl.identify_system());
+    assert_eq!(system.database, Some("postgres".to_owned()));
+    assert!(system.timeline >= 1);
+
+    or_panic!(repl.batch_execute(
+        "CREATE_REPLICATION_SLOT rust_postgres_test TEMPORARY LOGICAL test_decoding",
+    ));
+    or_panic!(conn.batch_execute(
+        "INSERT INTO replication_test (id) VALUES (1), (2)",
+    ));
+
+    {
+        let mut stream = or_panic!(repl.start_logical_replication(
+            "rust_postgres_test",
+            Lsn(0),
+            &[("include-xids", "0")],
+        ));
+        let mut changes = vec![];
+        while changes.len() < 2 {
+            match or_panic!(stream.next()) {
+                Some(ReplicationMessage::XLogData(data)) => {
+                    let data = String::from_utf8(data.data).unwrap();
+                    if data.starts_with("table public.replication_test: INSERT") {
+                        changes.push(data);
+                    }
+                }
+                Some(ReplicationMessage::PrimaryKeepalive(keepalive)) => {
+                    let lsn = keepalive.wal_end;
+                    or_panic!(stream.send_standby_status_update(lsn, lsn, lsn, false));
+                }
+                m => panic!("unexpected message {:?}", m),
+            }
+        }
+        assert_eq!(
+            changes,
+            [
+                "table public.replication_test: INSERT: id[integer]:1",
+                "table public.replication_test: INSERT: id[integer]:2",
+            ]
+        );
+        or_panic!(stream.finish());
+    }
+
+    let system = or_panic!(repl.identify_system());
+    assert!(system.xlog_pos > Lsn(0));
+    or_panic!(conn.batch_execute("DROP TABLE replication_test"));
+}
@@ -1,9 +1,11 @@
 extern crate chrono;
 
-use self::chrono::{TimeZone, NaiveDate, NaiveTime, NaiveDateTime, DateTime, Utc};
+use self::chrono::{TimeZone, NaiveDate, NaiveTime, NaiveDateTime, DateTime, Duration, FixedOffset,
+                   Timelike, Utc};
 use types::test_type;
 
-use postgres::types::{Date, Timestamp};
+use postgres::{Connection, TlsMode};
+use postgres::types::{Date, Interval, Range, RangeBound, TimeTz, Timestamp};
 
 #[test]
 fn test_naive_date_time_params() {
@@ -93,6 +95,25 @@ fn test_with_special_date_time_params() {
     );
 }
 
+#[test]
+fn test_date_time_with_timezone() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let rows = or_panic!(conn.query(
+        "SELECT '2018-01-15 12:30:00 America/New_York'::TIMESTAMPTZ",
+        &[],
+    ));
+    let utc: DateTime<Utc> = rows.get(0).get(0);
+    assert_eq!(utc, Utc.ymd(2018, 1, 15).and_hms(17, 30, 0));
+
+    // New York is five hours behind UTC in January
+    let new_york = utc.with_timezone(&FixedOffset::west(5 * 3600));
+    assert_eq!((new_york.hour(), new_york.minute()), (12, 30));
+    assert_eq!(new_york, utc);
+}
+
 #[test]
 fn test_date_params() {
     fn make_check<'a>(time: &'a str) -> (Option<NaiveDate>, &'a str) {
@@ -150,3 +171,65 @@ fn test_time_params() {
         ],
     );
 }
+
+#[test]
+fn test_tstzrange_array_params() {
+    let time = |s: &str| Utc.datetime_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+    test_type(
+        "TSTZRANGE[]",
+        &[
+            (
+                Some(vec![
+                    Range::Nonempty(
+                        RangeBound::Inclusive(time("2010-02-09 23:11:45")),
+                        RangeBound::Exclusive(time("2010-02-10 23:11:45")),
+                    ),
+                    Range::Nonempty(
+                        RangeBound::Exclusive(time("1970-01-01 00:00:00")),
+                        RangeBound::Unbounded,
+                    ),
+                ]),
+                "ARRAY['[2010-02-09 23:11:45Z,2010-02-10 23:11:45Z)', \
+                 '(1970-01-01 00:00:00Z,)']",
+            ),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_interval_to_chrono_duration() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let get = |s: &str| -> Interval {
+        let rows = or_panic!(conn.query(&format!("SELECT {}::INTERVAL", s), &[]));
+        rows.get(0).get(0)
+    };
+
+    assert_eq!(
+        get("'2 days 03:00:00'").to_chrono_duration().unwrap(),
+        Duration::days(2) + Duration::hours(3)
+    );
+    assert_eq!(
+        get("'-1 day 00:00:00.000001'").to_chrono_duration().unwrap(),
+        Duration::days(-1) + Duration::microseconds(1)
+    );
+    assert!(get("'1 month'").to_chrono_duration().is_err());
+}
+
+#[test]
+fn test_timetz_chrono() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let rows = or_panic!(conn.query("SELECT '12:34:56+02'::TIMETZ, '24:00:00+00'::TIMETZ", &[]));
+    let (time, offset) = rows.get(0).get::<_, TimeTz>(0).to_chrono().unwrap();
+    assert_eq!(time, NaiveTime::from_hms(12, 34, 56));
+    assert_eq!(offset, FixedOffset::east(2 * 60 * 60));
+    assert_eq!(TimeTz::from_chrono(time, offset), rows.get(0).get(0));
+    assert!(rows.get(0).get::<_, TimeTz>(1).to_chrono().is_err());
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::error;
 use std::f32;
 use std::f64;
@@ -6,7 +6,11 @@ use std::fmt;
 use std::result;
 
 use postgres::{Connection, TlsMode};
-use postgres::types::{ToSql, FromSql, WrongType, Type, IsNull, Kind, TEXT, INT4, NUMERIC};
+use postgres_protocol::types;
+use postgres::types::{ToSql, FromSql, WrongType, Type, IsNull, Kind, Cidr, Composite, Interval, Json,
+                      JsonPath, Jsonb, Lsn, MacAddr, MacAddr8, Range, RangeBound, Snapshot,
+                      TsLexeme, TsPosition, TimeTz, TsQuery, TsVector, TsWeight, TEXT, INT4, NUMERIC, PG_LSN,
+                      UNKNOWN};
 
 #[cfg(feature = "with-bit-vec")]
 mod bit_vec;
@@ -52,6 +56,25 @@ fn test_ref_tosql() {
     stmt.query(&[num]).unwrap();
 }
 
+#[test]
+fn test_option_ref_tosql() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let stmt = or_panic!(conn.prepare("SELECT $1::TEXT, $2::INT"));
+
+    let name = "hello".to_owned();
+    let num = 1;
+    let rows = or_panic!(stmt.query(&[&Some(&*name), &Some(&num)]));
+    assert_eq!(rows.get(0).get::<_, Option<String>>(0), Some(name));
+    assert_eq!(rows.get(0).get::<_, Option<i32>>(1), Some(1));
+
+    let rows = or_panic!(stmt.query(&[&None::<&str>, &None::<&i32>]));
+    assert_eq!(rows.get(0).get::<_, Option<String>>(0), None);
+    assert_eq!(rows.get(0).get::<_, Option<i32>>(1), None);
+}
+
 #[test]
 fn test_bool_params() {
     test_type(
@@ -116,6 +139,47 @@ fn test_oid_params() {
     );
 }
 
+#[test]
+fn test_oid_alias_params() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let rows = or_panic!(conn.query(
+        "SELECT 'pg_class'::REGCLASS, 'int4'::REGTYPE, 'now'::REGPROC, 'pg_catalog'::REGNAMESPACE",
+        &[],
+    ));
+    let row = rows.get(0);
+    assert_eq!(row.get::<_, u32>(0), 1259);
+    assert_eq!(row.get::<_, u32>(1), 23);
+    assert_eq!(row.get::<_, u32>(2), 1299);
+    assert_eq!(row.get::<_, u32>(3), 11);
+
+    let rows = or_panic!(conn.query(
+        "SELECT $1::REGCLASS::TEXT, $2::REGTYPE::TEXT",
+        &[&1259u32, &23u32],
+    ));
+    assert_eq!(rows.get(0).get::<_, String>(0), "pg_class");
+    assert_eq!(rows.get(0).get::<_, String>(1), "integer");
+}
+
+#[test]
+fn test_oid_column() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let rows = or_panic!(conn.query("SELECT 'pg_class'::regclass::oid", &[]));
+    let row = rows.get(0);
+    assert_eq!(row.get::<_, u32>(0), 1259);
+    match row.get_opt::<_, i32>(0) {
+        Some(Err(ref e)) if e.as_conversion().map_or(false, |e| e.is::<WrongType>()) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
 #[test]
 fn test_i64_params() {
     test_type(
@@ -184,6 +248,18 @@ fn test_text_params() {
     );
 }
 
+#[test]
+fn test_unknown_literal() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    assert!(<String as FromSql>::accepts(&UNKNOWN));
+
+    let rows = or_panic!(conn.query("SELECT 'hello'", &[]));
+    assert_eq!(rows.get(0).get::<_, String>(0), "hello");
+}
+
 #[test]
 fn test_bpchar_params() {
     let conn = or_panic!(Connection::connect(
@@ -253,6 +329,23 @@ fn test_bytea_params() {
     );
 }
 
+#[test]
+fn test_bytea_text_output() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let value = vec![0u8, 1, b'\\', b'a', 254, 255];
+
+    for output in &["hex", "escape"] {
+        or_panic!(conn.batch_execute(&format!("SET bytea_output = '{}'", output)));
+        let rows = or_panic!(conn.query("SELECT $1::BYTEA::TEXT", &[&value]));
+        let text = rows.get(0).get::<_, String>(0);
+        assert_eq!(text.starts_with("\\x"), *output == "hex");
+        assert_eq!(types::bytea_from_text(&text).unwrap(), value);
+    }
+}
+
 #[test]
 fn test_hstore_params() {
     macro_rules! make_map {
@@ -293,6 +386,523 @@ fn test_array_params() {
     );
 }
 
+#[test]
+fn test_void() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let rows = or_panic!(conn.query("SELECT pg_advisory_lock(1)", &[]));
+    rows.get(0).get::<_, ()>(0);
+    let rows = or_panic!(conn.query("SELECT pg_advisory_unlock(1)", &[]));
+    match rows.get(0).get_opt::<_, ()>(0) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_array_null_elements() {
+    test_type(
+        "int4[]",
+        &[
+            (Some(vec![Some(1i32), None, Some(3)]), "'{1,NULL,3}'"),
+            (Some(vec![None]), "'{NULL}'"),
+        ],
+    );
+
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let rows = or_panic!(conn.query("SELECT '{a,NULL,b}'::TEXT[]", &[]));
+    assert_eq!(
+        rows.get(0).get::<_, Vec<Option<String>>>(0),
+        [Some("a".to_owned()), None, Some("b".to_owned())]
+    );
+    match rows.get(0).get_opt::<_, Vec<String>>(0) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_raw_json_params() {
+    test_type(
+        "JSON",
+        &[
+            (Some(Json("{\"a\":  [1, 2]}".to_owned())), "'{\"a\":  [1, 2]}'"),
+            (None, "NULL"),
+        ],
+    );
+    test_type(
+        "JSONB",
+        &[
+            (Some(Jsonb("{\"a\": [1, 2]}".to_owned())), "'{\"a\": [1, 2]}'"),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_raw_jsonb_column() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute("CREATE TEMPORARY TABLE foo (data JSONB)"));
+
+    let value = Jsonb("{\"b\":1,  \"a\": null}".to_owned());
+    or_panic!(conn.execute("INSERT INTO foo (data) VALUES ($1)", &[&value]));
+    let rows = or_panic!(conn.query("SELECT data FROM foo", &[]));
+    assert_eq!(rows.get(0).get::<_, Jsonb>(0), Jsonb("{\"a\": null, \"b\": 1}".to_owned()));
+}
+
+#[test]
+fn test_jsonpath_params() {
+    test_type(
+        "JSONPATH",
+        &[
+            (Some(JsonPath("$.\"a\".\"b\"".to_owned())), "'$.a.b'"),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_int4range_params() {
+    test_type(
+        "INT4RANGE",
+        &[
+            (
+                Some(Range::Nonempty(RangeBound::Inclusive(1i32), RangeBound::Exclusive(11))),
+                "'[1,10]'",
+            ),
+            (
+                Some(Range::Nonempty(RangeBound::Unbounded, RangeBound::Exclusive(5))),
+                "'(,5)'",
+            ),
+            (Some(Range::Empty), "'empty'"),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_domain_range_params() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "CREATE DOMAIN pg_temp.posint AS INT4 CHECK (VALUE > 0);
+         CREATE TYPE pg_temp.posintrange AS RANGE (subtype = pg_temp.posint);",
+    ));
+
+    let range = Range::Nonempty(RangeBound::Inclusive(1i32), RangeBound::Inclusive(10));
+    let stmt = or_panic!(conn.prepare(
+        "SELECT $1::pg_temp.posintrange, '[1,10]'::pg_temp.posintrange",
+    ));
+    let rows = or_panic!(stmt.query(&[&range]));
+    assert_eq!(rows.get(0).get::<_, Range<i32>>(0), range);
+    assert_eq!(rows.get(0).get::<_, Range<i32>>(1), range);
+
+    let stmt = or_panic!(conn.prepare(
+        "SELECT ARRAY['[1,2]', 'empty']::pg_temp.posintrange[]",
+    ));
+    let rows = or_panic!(stmt.query(&[]));
+    assert_eq!(
+        rows.get(0).get::<_, Vec<Range<i32>>>(0),
+        vec![
+            Range::Nonempty(RangeBound::Inclusive(1), RangeBound::Inclusive(2)),
+            Range::Empty,
+        ]
+    );
+}
+
+#[test]
+fn test_cidr_params() {
+    test_type(
+        "CIDR",
+        &[
+            (
+                Some(Cidr {
+                    addr: "192.168.0.0".parse().unwrap(),
+                    prefix: 24,
+                }),
+                "'192.168.0.0/24'",
+            ),
+            (
+                Some(Cidr {
+                    addr: "2001:db8::".parse().unwrap(),
+                    prefix: 32,
+                }),
+                "'2001:db8::/32'",
+            ),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_cidr_inet_params() {
+    test_type(
+        "INET",
+        &[
+            (
+                Some(Cidr {
+                    addr: "192.168.0.1".parse().unwrap(),
+                    prefix: 24,
+                }),
+                "'192.168.0.1/24'",
+            ),
+            (
+                Some(Cidr {
+                    addr: "::1".parse().unwrap(),
+                    prefix: 128,
+                }),
+                "'::1'",
+            ),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_tsvector() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let lexeme = |text: &str, position| {
+        TsLexeme {
+            text: text.to_owned(),
+            positions: vec![
+                TsPosition {
+                    position: position,
+                    weight: TsWeight::D,
+                },
+            ],
+        }
+    };
+
+    let rows = or_panic!(conn.query(
+        "SELECT to_tsvector('english', 'the quick brown fox'), 'a:1A,3 b'::tsvector",
+        &[],
+    ));
+    let vector: TsVector = rows.get(0).get(0);
+    assert_eq!(
+        vector,
+        TsVector(vec![lexeme("brown", 3), lexeme("fox", 4), lexeme("quick", 2)])
+    );
+
+    let vector: TsVector = rows.get(0).get(1);
+    let mut a = lexeme("a", 1);
+    a.positions[0].weight = TsWeight::A;
+    a.positions.push(TsPosition {
+        position: 3,
+        weight: TsWeight::D,
+    });
+    let b = TsLexeme {
+        text: "b".to_owned(),
+        positions: vec![],
+    };
+    assert_eq!(vector, TsVector(vec![a, b]));
+}
+
+#[test]
+fn test_tsquery_params() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let queries = [
+        "fox",
+        "quick & !fox | 'brown':A* <-> x",
+        "(a | b) & c <2> 'it''s'",
+        "!!a <-> (b <-> c)",
+    ];
+    for query in &queries {
+        let rows = or_panic!(conn.query(
+            "SELECT $1::tsquery = $2::text::tsquery",
+            &[&TsQuery(query.to_string()), query],
+        ));
+        assert!(rows.get(0).get::<_, bool>(0), "{}", query);
+    }
+
+    let rows = or_panic!(conn.query(
+        "SELECT to_tsvector('english', 'the quick brown fox') @@ $1",
+        &[&TsQuery("quick <-> brown & !cat".to_owned())],
+    ));
+    assert!(rows.get(0).get::<_, bool>(0));
+
+    for query in &["a & (b", "a &", " "] {
+        assert!(
+            conn.query("SELECT $1::tsquery", &[&TsQuery(query.to_string())])
+                .is_err()
+        );
+    }
+}
+
+#[test]
+fn test_name_array_params() {
+    test_type(
+        "NAME[]",
+        &[
+            (Some(vec!["a".to_owned(), "b".to_owned()]), "ARRAY['a','b']"),
+            (Some(vec![]), "ARRAY[]::NAME[]"),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_catalog_arrays() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "CREATE TEMPORARY TABLE foo (a INT, b TEXT, c INT, PRIMARY KEY (c, a))",
+    ));
+
+    let rows = or_panic!(conn.query(
+        "SELECT i.indkey, ARRAY(SELECT attname FROM pg_attribute
+                                WHERE attrelid = 'foo'::regclass AND attnum > 0
+                                ORDER BY attnum),
+                '23 25'::oidvector, ARRAY['x', 'y']::TEXT[]
+         FROM pg_index i WHERE i.indrelid = 'foo'::regclass",
+        &[],
+    ));
+    let row = rows.get(0);
+    assert_eq!(row.get::<_, Vec<i16>>(0), [3, 1]);
+    assert_eq!(row.get::<_, Vec<String>>(1), ["a", "b", "c"]);
+    assert_eq!(row.get::<_, Vec<u32>>(2), [23, 25]);
+    assert_eq!(row.get::<_, Vec<String>>(3), ["x", "y"]);
+}
+
+#[test]
+fn test_fixed_size_array_params() {
+    test_type(
+        "FLOAT8[]",
+        &[
+            (Some([1.5f64, 2., -3.25]), "ARRAY[1.5, 2, -3.25]"),
+            (None, "NULL"),
+        ],
+    );
+    test_type("INT4[]", &[(Some([0i32; 0]), "ARRAY[]::INT4[]")]);
+    test_type(
+        "TEXT[]",
+        &[(Some([Some("a".to_owned()), None]), "ARRAY['a', NULL]")],
+    );
+}
+
+#[test]
+fn test_set_params() {
+    test_type(
+        "INT4[]",
+        &[
+            (Some(vec![3i32, 1, 2].into_iter().collect::<BTreeSet<_>>()), "ARRAY[1, 2, 3]"),
+            (Some(BTreeSet::new()), "ARRAY[]::INT4[]"),
+            (None, "NULL"),
+        ],
+    );
+
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let rows = or_panic!(conn.query("SELECT '{a,b,a}'::TEXT[]", &[]));
+    let set = rows.get(0).get::<_, HashSet<String>>(0);
+    assert_eq!(set.len(), 2);
+    assert!(set.contains("a") && set.contains("b"));
+
+    let rows = or_panic!(conn.query("SELECT $1::TEXT[]", &[&set]));
+    assert_eq!(rows.get(0).get::<_, HashSet<String>>(0), set);
+    assert_eq!(rows.get(0).get::<_, Vec<String>>(0).len(), 2);
+}
+
+#[test]
+fn test_fixed_size_array_length_mismatch() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let rows = or_panic!(conn.query(
+        "SELECT ARRAY[1, 2]::FLOAT8[], ARRAY[[1, 2, 3]]::FLOAT8[]",
+        &[],
+    ));
+    match rows.get(0).get_opt::<_, [f64; 3]>(0) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {
+            assert!(e.to_string().contains("expected an array of length 3 but got 2"), "{}", e);
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    match rows.get(0).get_opt::<_, [f64; 3]>(1) {
+        Some(Err(ref e)) if e.as_conversion().is_some() => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_macaddr_params() {
+    test_type(
+        "MACADDR",
+        &[
+            (Some(MacAddr([0x08, 0x00, 0x2b, 0x01, 0x02, 0x03])), "'08:00:2b:01:02:03'"),
+            (None, "NULL"),
+        ],
+    );
+    assert_eq!(
+        MacAddr([0x08, 0x00, 0x2b, 0x01, 0x02, 0x03]).to_string(),
+        "08:00:2b:01:02:03"
+    );
+}
+
+#[test]
+fn test_macaddr8_params() {
+    test_type(
+        "MACADDR8",
+        &[
+            (
+                Some(MacAddr8([0x08, 0x00, 0x2b, 0x01, 0x02, 0x03, 0x04, 0x05])),
+                "'08:00:2b:01:02:03:04:05'",
+            ),
+            (None, "NULL"),
+        ],
+    );
+    assert_eq!(
+        MacAddr8([0x08, 0x00, 0x2b, 0x01, 0x02, 0x03, 0x04, 0x05]).to_string(),
+        "08:00:2b:01:02:03:04:05"
+    );
+}
+
+#[test]
+fn test_interval_params() {
+    test_type(
+        "INTERVAL",
+        &[
+            (
+                Some(Interval {
+                    months: 0,
+                    days: 2,
+                    microseconds: 3 * 60 * 60 * 1_000_000,
+                }),
+                "'2 days 03:00:00'",
+            ),
+            (
+                Some(Interval {
+                    months: 14,
+                    days: -3,
+                    microseconds: -1_500_000,
+                }),
+                "'1 year 2 mons -3 days -00:00:01.5'",
+            ),
+            (Some(Interval::default()), "'0'"),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_txid_snapshot_params() {
+    test_type(
+        "TXID_SNAPSHOT",
+        &[
+            (
+                Some(Snapshot {
+                    xmin: 10,
+                    xmax: 20,
+                    xip: vec![12, 15],
+                }),
+                "'10:20:12,15'",
+            ),
+            (
+                Some(Snapshot {
+                    xmin: 10,
+                    xmax: 10,
+                    xip: vec![],
+                }),
+                "'10:10:'",
+            ),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_current_snapshot() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let rows = or_panic!(conn.query(
+        "SELECT txid_current_snapshot(), txid_current_snapshot()::TEXT",
+        &[],
+    ));
+    let row = rows.get(0);
+    let snapshot = row.get::<_, Snapshot>(0);
+    assert!(snapshot.xmin <= snapshot.xmax);
+    assert_eq!(snapshot.to_string(), row.get::<_, String>(1));
+}
+
+#[test]
+fn test_timetz_params() {
+    test_type(
+        "TIMETZ",
+        &[
+            (
+                Some(TimeTz {
+                    time_micros: ((12 * 60 + 34) * 60 + 56) * 1_000_000,
+                    zone_offset_secs: 2 * 60 * 60,
+                }),
+                "'12:34:56+02'",
+            ),
+            (
+                Some(TimeTz {
+                    time_micros: 500_000,
+                    zone_offset_secs: -(5 * 60 + 30) * 60,
+                }),
+                "'00:00:00.5-05:30'",
+            ),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_lsn_params() {
+    test_type(
+        "PG_LSN",
+        &[
+            (Some(Lsn(0x16_b374_d848)), "'16/B374D848'"),
+            (Some(Lsn(0)), "'0/0'"),
+            (None, "NULL"),
+        ],
+    );
+}
+
+#[test]
+fn test_lsn_current() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    let stmt = or_panic!(conn.prepare("SELECT pg_current_wal_lsn(), pg_current_wal_lsn()::TEXT"));
+    assert_eq!(stmt.columns()[0].type_(), &PG_LSN);
+    let rows = or_panic!(stmt.query(&[]));
+    let lsn = rows.get(0).get::<_, Lsn>(0);
+    let text = rows.get(0).get::<_, String>(1);
+    assert_eq!(lsn.to_string(), text);
+    assert_eq!(text.parse::<Lsn>().unwrap(), lsn);
+    assert!("16B374D848".parse::<Lsn>().is_err());
+}
+
 fn test_nan_param<T: PartialEq + ToSql + FromSql>(sql_type: &str) {
     let conn = or_panic!(Connection::connect(
         "postgres://postgres@localhost:5433",
@@ -314,6 +924,48 @@ fn test_f64_nan_param() {
     test_nan_param::<f64>("DOUBLE PRECISION");
 }
 
+fn test_special_float_param<T>(sql_type: &str, nan: T, inf: T, neg_inf: T)
+where
+    T: PartialEq + ToSql + FromSql + fmt::Debug,
+{
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+
+    let query = format!("SELECT $1::{0}, $1::{0} = 'NaN'::{0}", sql_type);
+    let stmt = or_panic!(conn.prepare(&query));
+    let result = or_panic!(stmt.query(&[&nan]));
+    let row = result.iter().next().unwrap();
+    let val: T = row.get(0);
+    assert!(val != val);
+    assert!(row.get::<_, bool>(1));
+
+    for &(ref val, literal) in &[(inf, "Infinity"), (neg_inf, "-Infinity")] {
+        let query = format!("SELECT $1::{0}, $1::{0} = '{1}'::{0}", sql_type, literal);
+        let stmt = or_panic!(conn.prepare(&query));
+        let result = or_panic!(stmt.query(&[val]));
+        let row = result.iter().next().unwrap();
+        assert_eq!(*val, row.get::<_, T>(0));
+        assert!(row.get::<_, bool>(1));
+    }
+}
+
+#[test]
+fn test_f32_special_param() {
+    test_special_float_param("REAL", f32::NAN, f32::INFINITY, f32::NEG_INFINITY);
+}
+
+#[test]
+fn test_f64_special_param() {
+    test_special_float_param(
+        "DOUBLE PRECISION",
+        f64::NAN,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+    );
+}
+
 #[test]
 fn test_pg_database_datname() {
     let conn = or_panic!(Connection::connect(
@@ -458,6 +1110,35 @@ fn composite() {
     }
 }
 
+#[test]
+fn composite_values() {
+    let conn = Connection::connect("postgres://postgres@localhost:5433", TlsMode::None).unwrap();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.inventory_item AS (
+                            name TEXT,
+                            supplier INTEGER,
+                            price NUMERIC
+                        )",
+    ).unwrap();
+
+    let rows = conn.query(
+        "SELECT ROW('fuzzy dice', 42, 1.99)::inventory_item,
+                ROW('fuzzy dice', NULL, 1.99)::inventory_item",
+        &[],
+    ).unwrap();
+    let item = rows.get(0).get::<_, Composite>(0);
+    assert_eq!(item.fields().len(), 3);
+    assert_eq!(item.get::<String>(0).unwrap(), "fuzzy dice");
+    assert_eq!(item.get_named::<i32>("supplier").unwrap(), 42);
+    assert!(item.get::<String>(2).unwrap_err().is::<WrongType>());
+    assert!(item.get::<String>(3).is_err());
+    assert!(item.get_named::<String>("color").is_err());
+
+    let item = rows.get(0).get::<_, Composite>(1);
+    assert_eq!(item.get_named::<Option<i32>>("supplier").unwrap(), None);
+    assert!(item.get_named::<i32>("supplier").is_err());
+}
+
 #[test]
 fn enum_() {
     let conn = Connection::connect("postgres://postgres@localhost:5433", TlsMode::None).unwrap();
@@ -477,3 +1158,20 @@ fn enum_() {
         _ => panic!("bad type"),
     }
 }
+
+#[test]
+fn enum_labels() {
+    let conn = Connection::connect("postgres://postgres@localhost:5433", TlsMode::None).unwrap();
+    conn.batch_execute("CREATE TYPE pg_temp.mood AS ENUM ('sad', 'ok', 'happy');")
+        .unwrap();
+
+    let rows = conn.query("SELECT 'ok'::mood, 'happy'::mood, NULL::mood, 'ok'::TEXT", &[])
+        .unwrap();
+    let row = rows.get(0);
+    let labels = ["sad", "ok"];
+    assert_eq!(row.get_enum(0, &labels).unwrap().unwrap(), "ok");
+    assert!(row.get_enum(1, &labels).unwrap().is_err());
+    assert!(row.get_enum(2, &labels).unwrap().is_err());
+    assert!(row.get_enum(3, &labels).unwrap().is_err());
+    assert!(row.get_enum(4, &labels).is_none());
+}
@@ -1,6 +1,8 @@
 extern crate serde_json;
 
 use self::serde_json::Value;
+use postgres::{Connection, TlsMode};
+use postgres::types::{JSON, JSONB, TEXT, TEXT_ARRAY};
 use types::test_type;
 
 #[test]
@@ -38,3 +40,49 @@ fn test_jsonb_params() {
         ],
     )
 }
+
+#[test]
+fn test_json_and_jsonb_columns() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute("CREATE TEMPORARY TABLE foo (j JSON, jb JSONB)"));
+
+    let value = serde_json::from_str::<Value>(r#"{"a": [1, 2.5, null], "b": {"c": "d"}}"#).unwrap();
+    let stmt = or_panic!(conn.prepare("INSERT INTO foo (j, jb) VALUES ($1, $2)"));
+    assert_eq!(stmt.param_types(), &[JSON, JSONB]);
+    or_panic!(stmt.execute(&[&value, &value]));
+
+    let rows = or_panic!(conn.query("SELECT j, jb FROM foo", &[]));
+    assert_eq!(rows.get(0).get::<_, Value>(0), value);
+    assert_eq!(rows.get(0).get::<_, Value>(1), value);
+}
+
+#[test]
+fn test_jsonb_operators() {
+    let conn = or_panic!(Connection::connect(
+        "postgres://postgres@localhost:5433",
+        TlsMode::None,
+    ));
+    or_panic!(conn.batch_execute(
+        "CREATE TEMPORARY TABLE foo (id INT, data JSONB);
+         INSERT INTO foo VALUES (1, '{\"a\": {\"b\": 1}}'), (2, '{\"c\": [1, 2]}')",
+    ));
+
+    let stmt = or_panic!(conn.prepare("SELECT id FROM foo WHERE data @> $1::JSONB"));
+    assert_eq!(stmt.param_types(), &[JSONB]);
+    let value = serde_json::from_str::<Value>(r#"{"c": [2]}"#).unwrap();
+    let rows = or_panic!(stmt.query(&[&value]));
+    assert_eq!(rows.iter().map(|r| r.get(0)).collect::<Vec<i32>>(), vec![2]);
+
+    let stmt = or_panic!(conn.prepare("SELECT id FROM foo WHERE data ? $1::TEXT"));
+    assert_eq!(stmt.param_types(), &[TEXT]);
+    let rows = or_panic!(stmt.query(&[&"a"]));
+    assert_eq!(rows.iter().map(|r| r.get(0)).collect::<Vec<i32>>(), vec![1]);
+
+    let stmt = or_panic!(conn.prepare("SELECT data #> $1::TEXT[] FROM foo WHERE id = 1"));
+    assert_eq!(stmt.param_types(), &[TEXT_ARRAY]);
+    let rows = or_panic!(stmt.query(&[&vec!["a", "b"]]));
+    assert_eq!(rows.get(0).get::<_, Value>(0), Value::from(1));
+}
@@ -632,7 +632,13 @@ impl Connection {
                 match m {
                     backend::Message::RowDescription(body) => {
                         match body.fields()
-                            .map(|f| (f.name().to_owned(), f.type_oid()))
+                            .map(|f| {
+                                (f.name().to_owned(),
+                                 f.type_oid(),
+                                 f.table_oid(),
+                                 f.column_id(),
+                                 f.type_modifier())
+                            })
                             .collect::<Vec<_>>() {
                                 Ok(d) => Ok((p, d, s)),
                                 Err(e) => Err((error::io(e), Connection(s))),
@@ -651,7 +657,7 @@ impl Connection {
                 s.get_types(r.into_iter(),
                             vec![],
                             |f| f.1,
-                            |f, t| Column::new(f.0, t))
+                            |f, t| Column::new(f.0, t, f.2, f.3, f.4))
                     .map(|(r, s)| (p, r, s))
             })
             .boxed2()
@@ -781,8 +787,10 @@ impl Connection {
                     Either::A(Ok((Kind::Simple, c)).into_future())
                 };
 
+                // values are always exchanged in the binary format, so
+                // support for it is not looked up
                 Either::B(kind.map(
-                    move |(k, c)| (Type::_new(name, oid, k, schema), c),
+                    move |(k, c)| (Type::_new(name, oid, k, schema, true), c),
                 ))
             })
             .boxed2()